@@ -0,0 +1,17 @@
+use std::process::Command;
+
+fn main() {
+    // Short hash of the commit being built (reported by `/build`), absent outside a git checkout.
+    if let Some(hash) = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+    {
+        println!("cargo:rustc-env=GIT_HASH={}", hash.trim());
+    }
+
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+}
@@ -0,0 +1,259 @@
+//! Length prefixed records in flash (i.e. the persisted config).
+//!
+//! A record is a big-endian `u16` length followed by the data, generic over the `embedded-storage`
+//! traits so it isn't tied to `FlashStorage` (and can be tested against memory).
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use embedded_storage::{ReadStorage, Storage};
+
+const LEN_FIELD_BYTES: u32 = 2;
+// Erased flash reads back as 0xff, so this length marks no record.
+const LEN_NONE: u16 = u16::MAX;
+
+/// A record at `addr` holding up to `max_len` bytes of data (after the length field).
+#[derive(Copy, Clone, Debug)]
+pub struct FlashRecord {
+    addr: u32,
+    max_len: usize,
+}
+
+impl FlashRecord {
+    pub const fn new(addr: u32, max_len: usize) -> Self {
+        Self { addr, max_len }
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// `None` when nothing has been written (or it was cleared).
+    pub fn read<S: ReadStorage>(
+        &self,
+        storage: &mut S,
+    ) -> Result<Option<Vec<u8>>, FlashRecordError<S::Error>> {
+        let mut len_bytes = [0u8; LEN_FIELD_BYTES as usize];
+        storage
+            .read(self.addr, &mut len_bytes)
+            .map_err(FlashRecordError::Storage)?;
+
+        let len = u16::from_be_bytes(len_bytes);
+        if len == LEN_NONE {
+            return Ok(None);
+        }
+        if len as usize > self.max_len {
+            return Err(FlashRecordError::TooLarge {
+                len: len as usize,
+                max_len: self.max_len,
+            });
+        }
+
+        let mut bytes = vec![0u8; len as usize];
+        storage
+            .read(self.addr + LEN_FIELD_BYTES, &mut bytes)
+            .map_err(FlashRecordError::Storage)?;
+
+        Ok(Some(bytes))
+    }
+
+    /// Nothing is written when `bytes` doesn't fit.
+    pub fn write<S: Storage>(
+        &self,
+        storage: &mut S,
+        bytes: &[u8],
+    ) -> Result<(), FlashRecordError<S::Error>> {
+        // Would overrun the region (and the len field can't represent it).
+        if bytes.len() > self.max_len || bytes.len() >= LEN_NONE as usize {
+            return Err(FlashRecordError::TooLarge {
+                len: bytes.len(),
+                max_len: self.max_len,
+            });
+        }
+
+        // Data first, the len field is what marks the record as present.
+        storage
+            .write(self.addr + LEN_FIELD_BYTES, bytes)
+            .map_err(FlashRecordError::Storage)?;
+        self.write_len(storage, bytes.len() as u16)
+    }
+
+    /// Marks the record as absent (the data is left as is).
+    pub fn clear<S: Storage>(&self, storage: &mut S) -> Result<(), FlashRecordError<S::Error>> {
+        self.write_len(storage, LEN_NONE)
+    }
+
+    fn write_len<S: Storage>(
+        &self,
+        storage: &mut S,
+        len: u16,
+    ) -> Result<(), FlashRecordError<S::Error>> {
+        storage
+            .write(self.addr, &len.to_be_bytes())
+            .map_err(FlashRecordError::Storage)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum FlashRecordError<E> {
+    Storage(E),
+    // The data (or a stored length) is larger than the record allows.
+    TooLarge { len: usize, max_len: usize },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CAPACITY: usize = 0x1000;
+    const RECORD: FlashRecord = FlashRecord::new(0x200, 0x800 - 2);
+
+    /// In-memory flash, starting out erased.
+    struct MockStorage {
+        bytes: Vec<u8>,
+        writes: usize,
+    }
+
+    impl MockStorage {
+        fn new() -> Self {
+            Self {
+                bytes: vec![0xff; CAPACITY],
+                writes: 0,
+            }
+        }
+
+        fn range(&self, offset: u32, len: usize) -> Result<core::ops::Range<usize>, MockError> {
+            let start = offset as usize;
+            match start.checked_add(len) {
+                Some(end) if end <= self.bytes.len() => Ok(start..end),
+                _ => Err(MockError::OutOfBounds),
+            }
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum MockError {
+        OutOfBounds,
+    }
+
+    impl ReadStorage for MockStorage {
+        type Error = MockError;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let range = self.range(offset, bytes.len())?;
+            bytes.copy_from_slice(&self.bytes[range]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.bytes.len()
+        }
+    }
+
+    impl Storage for MockStorage {
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            let range = self.range(offset, bytes.len())?;
+            self.bytes[range].copy_from_slice(bytes);
+            self.writes += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn read_erased_is_none() {
+        let mut storage = MockStorage::new();
+
+        assert_eq!(RECORD.read(&mut storage), Ok(None));
+    }
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let mut storage = MockStorage::new();
+        let data: Vec<u8> = (0..=255).collect();
+
+        RECORD.write(&mut storage, &data).unwrap();
+
+        assert_eq!(RECORD.read(&mut storage), Ok(Some(data)));
+    }
+
+    #[test]
+    fn shorter_write_replaces_longer() {
+        let mut storage = MockStorage::new();
+
+        RECORD.write(&mut storage, &[1, 2, 3, 4, 5]).unwrap();
+        RECORD.write(&mut storage, &[9, 8]).unwrap();
+
+        assert_eq!(RECORD.read(&mut storage), Ok(Some(vec![9, 8])));
+    }
+
+    #[test]
+    fn write_empty_is_present() {
+        let mut storage = MockStorage::new();
+
+        RECORD.write(&mut storage, &[]).unwrap();
+
+        assert_eq!(RECORD.read(&mut storage), Ok(Some(Vec::new())));
+    }
+
+    #[test]
+    fn write_max_len_fits() {
+        let mut storage = MockStorage::new();
+        let data = vec![0x5a; RECORD.max_len()];
+
+        RECORD.write(&mut storage, &data).unwrap();
+
+        assert_eq!(RECORD.read(&mut storage), Ok(Some(data)));
+    }
+
+    #[test]
+    fn write_oversized_is_rejected_without_writing() {
+        let mut storage = MockStorage::new();
+        let data = vec![0x5a; RECORD.max_len() + 1];
+
+        assert_eq!(
+            RECORD.write(&mut storage, &data),
+            Err(FlashRecordError::TooLarge {
+                len: RECORD.max_len() + 1,
+                max_len: RECORD.max_len(),
+            })
+        );
+        assert_eq!(storage.writes, 0);
+        assert_eq!(RECORD.read(&mut storage), Ok(None));
+    }
+
+    #[test]
+    fn read_oversized_len_is_rejected() {
+        let mut storage = MockStorage::new();
+        let len = (RECORD.max_len() + 1) as u16;
+        storage.write(0x200, &len.to_be_bytes()).unwrap();
+
+        assert_eq!(
+            RECORD.read(&mut storage),
+            Err(FlashRecordError::TooLarge {
+                len: RECORD.max_len() + 1,
+                max_len: RECORD.max_len(),
+            })
+        );
+    }
+
+    #[test]
+    fn clear_reads_as_none() {
+        let mut storage = MockStorage::new();
+
+        RECORD.write(&mut storage, &[1, 2, 3]).unwrap();
+        RECORD.clear(&mut storage).unwrap();
+
+        assert_eq!(RECORD.read(&mut storage), Ok(None));
+    }
+
+    #[test]
+    fn storage_errors_are_passed_through() {
+        let mut storage = MockStorage::new();
+        let record = FlashRecord::new(CAPACITY as u32 - 1, 16);
+
+        assert_eq!(
+            record.read(&mut storage),
+            Err(FlashRecordError::Storage(MockError::OutOfBounds))
+        );
+    }
+}
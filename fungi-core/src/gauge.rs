@@ -0,0 +1,99 @@
+//! Text of the display's temp and rh gauges and where it's drawn.
+
+use alloc::format;
+use alloc::string::String;
+use num_traits::float::Float;
+
+/// Temp as shown on the gauge (whole degrees are rounded up, below zero keeps its sign).
+pub fn display_temp(temp: f32, decimals: u8) -> String {
+    match decimals {
+        0 => format!("{}", Float::ceil(temp) as i32),
+        _ => format!("{:.*}", decimals as usize, temp),
+    }
+}
+
+/// RH as shown on the gauge.
+pub fn display_rh(rh: f32, decimals: u8) -> String {
+    format!("{:.*}", decimals as usize, rh)
+}
+
+/// Gauge text with its width in chars (as drawn, i.e. '°' is a single char).
+pub fn gauge_text(text: String) -> (String, u32) {
+    let chars = text.chars().count() as u32;
+
+    (text, chars)
+}
+
+/// Offset of the text from the gauge's outer edge, centering it within `gauge_width` (less the
+/// pull towards the side).
+pub fn calculate_gauge_x(gauge_width: u32, chars: u32, font_width: u32, pull_side_px: u32) -> i32 {
+    // Text wider than the gauge starts at the edge.
+    (gauge_width.saturating_sub(chars * font_width) / 2).saturating_sub(pull_side_px) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Half of the 128px panel, with the 10px gauge font.
+    const GAUGE_WIDTH: u32 = 64;
+    const FONT_WIDTH: u32 = 10;
+
+    fn gauge_x(text: &str) -> i32 {
+        let (_, chars) = gauge_text(String::from(text));
+
+        calculate_gauge_x(GAUGE_WIDTH, chars, FONT_WIDTH, 0)
+    }
+
+    #[test]
+    fn temp_whole_degrees_round_up() {
+        assert_eq!(display_temp(23.1, 0), "24");
+        assert_eq!(display_temp(24.0, 0), "24");
+        assert_eq!(display_temp(7.2, 0), "8");
+        assert_eq!(display_temp(7.2, 1), "7.2");
+    }
+
+    #[test]
+    fn temp_below_zero_keeps_its_sign() {
+        assert_eq!(display_temp(-3.7, 0), "-3");
+        assert_eq!(display_temp(-3.7, 1), "-3.7");
+        assert_eq!(display_temp(-12.25, 2), "-12.25");
+        // Rounds up to zero (not "-0").
+        assert_eq!(display_temp(-0.4, 0), "0");
+    }
+
+    #[test]
+    fn rh_full_scale() {
+        assert_eq!(display_rh(100.0, 1), "100.0");
+        assert_eq!(display_rh(100.0, 0), "100");
+        assert_eq!(display_rh(92.46, 1), "92.5");
+    }
+
+    #[test]
+    fn degree_sign_is_one_char() {
+        assert_eq!(gauge_text(String::from("7°C")), (String::from("7°C"), 3));
+        assert_eq!(gauge_text(String::from("100°F")).1, 5);
+        assert_eq!(gauge_text(String::from("100.0%")).1, 6);
+    }
+
+    #[test]
+    fn text_is_centered() {
+        // Single digit temp.
+        assert_eq!(gauge_x("7°C"), 17);
+        assert_eq!(gauge_x("24°C"), 12);
+        assert_eq!(gauge_x("100°F"), 7);
+        assert_eq!(gauge_x("100.0%"), 2);
+    }
+
+    #[test]
+    fn wide_text_starts_at_the_edge() {
+        assert_eq!(gauge_x("-10.5°C"), 0);
+        assert_eq!(gauge_x("-100.25°F"), 0);
+    }
+
+    #[test]
+    fn pull_side_saturates() {
+        assert_eq!(calculate_gauge_x(GAUGE_WIDTH, 3, FONT_WIDTH, 5), 12);
+        assert_eq!(calculate_gauge_x(GAUGE_WIDTH, 6, FONT_WIDTH, 5), 0);
+    }
+}
@@ -0,0 +1,59 @@
+//! The heater's thermostat (the band around the auto schedule's target temp).
+
+/// Whether the heater should run, within the band (target +/- hysteresis) it keeps its state.
+pub fn heater_decide(temp: f32, target_temp: f32, hysteresis: f32, on: bool) -> bool {
+    if temp <= target_temp - hysteresis {
+        true
+    } else if temp >= target_temp + hysteresis {
+        false
+    } else {
+        on
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TARGET_TEMP: f32 = 24.0;
+    const HYSTERESIS: f32 = 0.5;
+
+    #[test]
+    fn on_at_and_below_the_lower_edge() {
+        assert!(heater_decide(23.5, TARGET_TEMP, HYSTERESIS, false));
+        assert!(heater_decide(23.5, TARGET_TEMP, HYSTERESIS, true));
+        assert!(heater_decide(20.0, TARGET_TEMP, HYSTERESIS, false));
+    }
+
+    #[test]
+    fn off_at_and_above_the_upper_edge() {
+        assert!(!heater_decide(24.5, TARGET_TEMP, HYSTERESIS, true));
+        assert!(!heater_decide(24.5, TARGET_TEMP, HYSTERESIS, false));
+        assert!(!heater_decide(30.0, TARGET_TEMP, HYSTERESIS, true));
+    }
+
+    #[test]
+    fn holds_inside_the_band() {
+        for temp in [23.6, 24.0, 24.4] {
+            assert!(heater_decide(temp, TARGET_TEMP, HYSTERESIS, true));
+            assert!(!heater_decide(temp, TARGET_TEMP, HYSTERESIS, false));
+        }
+    }
+
+    #[test]
+    fn heats_up_then_coasts_down() {
+        let mut on = false;
+        let mut switches = 0;
+        for temp in [24.0, 23.4, 23.8, 24.2, 24.6, 24.2, 23.8, 23.4] {
+            let next = heater_decide(temp, TARGET_TEMP, HYSTERESIS, on);
+            if next != on {
+                switches += 1;
+            }
+            on = next;
+        }
+
+        // On at 23.4, off at 24.6 and on again at 23.4.
+        assert!(on);
+        assert_eq!(switches, 3);
+    }
+}
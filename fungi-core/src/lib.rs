@@ -0,0 +1,14 @@
+//! Hardware independent logic of the firmware (no embassy, statics or peripherals), so it can be
+//! tested on the host with `cargo +stable test` from this directory.
+
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod flash;
+pub mod gauge;
+pub mod heater;
+pub mod mister;
+pub mod nullable;
+pub mod schedule;
+pub mod swap;
@@ -0,0 +1,536 @@
+//! The mister's auto rh control (the band it switches within and the flapping guard) and driving
+//! its output for a status.
+
+use serde::Serialize;
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize)]
+pub enum Status {
+    Off,
+    On,
+    Fault,
+}
+
+/// Auto rh band, the mister turns on at or below `on` and off at or above `off` (the step's rh
+/// with the on/off adjustments applied).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct RhBand {
+    pub on: f32,
+    pub off: f32,
+}
+
+impl RhBand {
+    pub const fn new(on: f32, off: f32) -> Self {
+        Self { on, off }
+    }
+
+    /// An inverted (or empty) band would make the mister oscillate or lock.
+    pub fn is_inverted(&self) -> bool {
+        self.on >= self.off
+    }
+}
+
+/// Flapping guard, the status last switched to by the auto rh mode and when.
+#[derive(Clone, PartialEq, Debug)]
+pub struct AutoRhState {
+    status: Status,
+    cycle_start_time: u32,
+}
+
+impl AutoRhState {
+    pub fn new(status: Status, cycle_start_time: u32) -> Self {
+        Self {
+            status,
+            cycle_start_time,
+        }
+    }
+}
+
+/// Outcome of an auto rh poll.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AutoRhDecision {
+    // Apply (or re-apply, verifying the pin state) the status.
+    Apply(Status),
+    // A change was held back by the flapping guard.
+    Guarded,
+    // No metrics.
+    Fault,
+}
+
+/// Decides the next status for the auto rh mode and updates the flapping guard `state`.
+///
+/// Pure (no pin or pubsub I/O), the caller applies the decision.
+pub fn auto_rh_decide(
+    rh: Option<f32>,
+    floor_active: bool,
+    band: RhBand,
+    status: Option<Status>,
+    state: &mut Option<AutoRhState>,
+    now: u32,
+    min_duration_ms: u32,
+) -> AutoRhDecision {
+    let Some(rh) = rh else {
+        // Clear state.
+        let _ = state.take();
+
+        return AutoRhDecision::Fault;
+    };
+
+    // Verify state is accurate.
+    if let Some(cur) = state.as_ref() {
+        if let Some(status) = status.as_ref() {
+            if !cur.status.eq(status) {
+                // Clear state.
+                let _ = state.take();
+            }
+        }
+    }
+
+    // Determine new status (the hard floor overrides the band)
+    let new_status = if floor_active || rh <= band.on {
+        Status::On
+    } else if rh >= band.off {
+        Status::Off
+    } else {
+        // If rh between on and off threshold preserve status (either 'rising' or 'falling').
+        status.unwrap_or(Status::Off)
+    };
+
+    // Change status with guarding against flapping too fast
+    match status {
+        Some(status) if !new_status.eq(&status) => match state.as_mut() {
+            Some(cur) => {
+                // Check threshold and ignore event if required.
+                if now.wrapping_sub(cur.cycle_start_time) >= min_duration_ms {
+                    cur.status = new_status;
+                    cur.cycle_start_time = now;
+
+                    AutoRhDecision::Apply(new_status)
+                } else {
+                    AutoRhDecision::Guarded
+                }
+            }
+            None => {
+                let _ = state.insert(AutoRhState::new(new_status, now));
+
+                AutoRhDecision::Apply(new_status)
+            }
+        },
+        // This just verifies pin state.
+        Some(_) => AutoRhDecision::Apply(new_status),
+        None => {
+            // Assume first init (shouldn't ever be None here though).
+
+            // Clear state.
+            let _ = state.take();
+
+            AutoRhDecision::Apply(new_status)
+        }
+    }
+}
+
+/// The mister's power output.
+pub trait PowerOutput {
+    type Error;
+
+    fn is_on(&mut self) -> Result<bool, Self::Error>;
+
+    fn set_on(&mut self) -> Result<(), Self::Error>;
+
+    fn set_off(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Whether applying `status` has nothing to do (i.e. every sensor sample in Auto), it hasn't
+/// `changed` and the pin is in step, so there's nothing to publish or drive.
+///
+/// Only reads the output, a pin out of step isn't steady (so it's driven again). A 'Fault' (or a
+/// status with a fault reason still to clear) never is, its reason may have changed.
+pub fn status_steady<O: PowerOutput>(
+    status: Status,
+    changed: bool,
+    has_fault_reason: bool,
+    burst_off_phase: bool,
+    output: &mut O,
+) -> Result<bool, O::Error> {
+    if changed || matches!(status, Status::Fault) || has_fault_reason {
+        return Ok(false);
+    }
+
+    match status {
+        // The pin stays low during a burst 'off' phase (burst phases drive it themselves).
+        Status::On => Ok(burst_off_phase || output.is_on()?),
+        Status::Off | Status::Fault => Ok(!output.is_on()?),
+    }
+}
+
+/// Drives the output for `status`, only writing when the pin is out of step.
+pub fn drive_output<O: PowerOutput>(
+    status: Status,
+    burst_off_phase: bool,
+    output: &mut O,
+) -> Result<(), O::Error> {
+    match status {
+        Status::Off | Status::Fault => {
+            if output.is_on()? {
+                output.set_off()?;
+            }
+        }
+        Status::On => {
+            // The pin stays low during a burst 'off' phase (status remains 'On').
+            if !burst_off_phase && !output.is_on()? {
+                output.set_on()?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BAND: RhBand = RhBand::new(88.0, 92.0);
+    const MIN_DURATION_MS: u32 = 10_000;
+
+    fn decide(
+        rh: f32,
+        status: Status,
+        state: &mut Option<AutoRhState>,
+        now: u32,
+    ) -> AutoRhDecision {
+        auto_rh_decide(
+            Some(rh),
+            false,
+            BAND,
+            Some(status),
+            state,
+            now,
+            MIN_DURATION_MS,
+        )
+    }
+
+    #[test]
+    fn band_is_accepted() {
+        // 90% with the default style adjustments (on -2, off +2).
+        assert!(!RhBand::new(88.0, 92.0).is_inverted());
+        // Narrow, but still a band.
+        assert!(!RhBand::new(89.9, 90.0).is_inverted());
+    }
+
+    #[test]
+    fn inverted_band_is_rejected() {
+        // 90% with the adjustments swapped (on +3, off -3).
+        assert!(RhBand::new(93.0, 87.0).is_inverted());
+    }
+
+    #[test]
+    fn empty_band_is_rejected() {
+        assert!(RhBand::new(90.0, 90.0).is_inverted());
+    }
+
+    #[test]
+    fn turns_on_at_or_below_the_band() {
+        let mut state = None;
+
+        assert_eq!(
+            decide(88.0, Status::Off, &mut state, 0),
+            AutoRhDecision::Apply(Status::On)
+        );
+        assert_eq!(
+            decide(70.0, Status::Off, &mut None, 0),
+            AutoRhDecision::Apply(Status::On)
+        );
+    }
+
+    #[test]
+    fn turns_off_at_or_above_the_band() {
+        assert_eq!(
+            decide(92.0, Status::On, &mut None, 0),
+            AutoRhDecision::Apply(Status::Off)
+        );
+        assert_eq!(
+            decide(99.0, Status::On, &mut None, 0),
+            AutoRhDecision::Apply(Status::Off)
+        );
+    }
+
+    #[test]
+    fn holds_the_status_inside_the_band() {
+        // Rising (still misting) and falling (still resting) both keep going.
+        assert_eq!(
+            decide(90.0, Status::On, &mut None, 0),
+            AutoRhDecision::Apply(Status::On)
+        );
+        assert_eq!(
+            decide(90.0, Status::Off, &mut None, 0),
+            AutoRhDecision::Apply(Status::Off)
+        );
+    }
+
+    #[test]
+    fn starts_off_inside_the_band_without_a_status() {
+        let mut state = Some(AutoRhState::new(Status::On, 0));
+
+        let decision = auto_rh_decide(Some(90.0), false, BAND, None, &mut state, 0, 0);
+
+        assert_eq!(decision, AutoRhDecision::Apply(Status::Off));
+        assert_eq!(state, None);
+    }
+
+    #[test]
+    fn hard_floor_overrides_the_band() {
+        let mut state = None;
+
+        let decision = auto_rh_decide(
+            Some(95.0),
+            true,
+            BAND,
+            Some(Status::Off),
+            &mut state,
+            0,
+            MIN_DURATION_MS,
+        );
+
+        assert_eq!(decision, AutoRhDecision::Apply(Status::On));
+    }
+
+    #[test]
+    fn flapping_guard_holds_back_a_quick_change() {
+        let mut state = None;
+
+        assert_eq!(
+            decide(85.0, Status::Off, &mut state, 1_000),
+            AutoRhDecision::Apply(Status::On)
+        );
+        assert_eq!(state, Some(AutoRhState::new(Status::On, 1_000)));
+
+        // Back above the band before the minimum duration is up.
+        assert_eq!(
+            decide(95.0, Status::On, &mut state, 1_000 + MIN_DURATION_MS - 1),
+            AutoRhDecision::Guarded
+        );
+        assert_eq!(state, Some(AutoRhState::new(Status::On, 1_000)));
+    }
+
+    #[test]
+    fn flapping_guard_allows_a_change_after_the_minimum_duration() {
+        let mut state = Some(AutoRhState::new(Status::On, 1_000));
+
+        assert_eq!(
+            decide(95.0, Status::On, &mut state, 1_000 + MIN_DURATION_MS),
+            AutoRhDecision::Apply(Status::Off)
+        );
+        assert_eq!(
+            state,
+            Some(AutoRhState::new(Status::Off, 1_000 + MIN_DURATION_MS))
+        );
+
+        // The next change is guarded again from there.
+        assert_eq!(
+            decide(85.0, Status::Off, &mut state, 1_000 + MIN_DURATION_MS + 1),
+            AutoRhDecision::Guarded
+        );
+    }
+
+    #[test]
+    fn flapping_guard_times_across_the_uptime_wrap() {
+        // The ms uptime wraps after ~49.7 days.
+        let start = u32::MAX - 1_000;
+        let mut state = Some(AutoRhState::new(Status::On, start));
+
+        assert_eq!(
+            decide(
+                95.0,
+                Status::On,
+                &mut state,
+                start.wrapping_add(MIN_DURATION_MS - 1)
+            ),
+            AutoRhDecision::Guarded
+        );
+        assert_eq!(
+            decide(
+                95.0,
+                Status::On,
+                &mut state,
+                start.wrapping_add(MIN_DURATION_MS)
+            ),
+            AutoRhDecision::Apply(Status::Off)
+        );
+    }
+
+    #[test]
+    fn flapping_guard_resets_when_the_status_changed_elsewhere() {
+        // i.e. a manual mode in between, the next auto change isn't held back.
+        let mut state = Some(AutoRhState::new(Status::On, 1_000));
+
+        assert_eq!(
+            decide(85.0, Status::Off, &mut state, 1_001),
+            AutoRhDecision::Apply(Status::On)
+        );
+        assert_eq!(state, Some(AutoRhState::new(Status::On, 1_001)));
+    }
+
+    #[test]
+    fn missing_metrics_faults_and_clears_the_guard() {
+        let mut state = Some(AutoRhState::new(Status::On, 1_000));
+
+        let decision = auto_rh_decide(
+            None,
+            false,
+            BAND,
+            Some(Status::On),
+            &mut state,
+            2_000,
+            MIN_DURATION_MS,
+        );
+
+        assert_eq!(decision, AutoRhDecision::Fault);
+        assert_eq!(state, None);
+    }
+
+    #[test]
+    fn missing_metrics_faults_even_below_the_hard_floor() {
+        let decision = auto_rh_decide(
+            None,
+            true,
+            BAND,
+            Some(Status::On),
+            &mut None,
+            0,
+            MIN_DURATION_MS,
+        );
+
+        assert_eq!(decision, AutoRhDecision::Fault);
+    }
+
+    /// Pin state and how often it was written.
+    #[derive(Default)]
+    struct MockOutput {
+        on: bool,
+        writes: usize,
+    }
+
+    impl PowerOutput for MockOutput {
+        type Error = ();
+
+        fn is_on(&mut self) -> Result<bool, ()> {
+            Ok(self.on)
+        }
+
+        fn set_on(&mut self) -> Result<(), ()> {
+            self.on = true;
+            self.writes += 1;
+            Ok(())
+        }
+
+        fn set_off(&mut self) -> Result<(), ()> {
+            self.on = false;
+            self.writes += 1;
+            Ok(())
+        }
+    }
+
+    /// As the mister task applies a status, returns whether it was steady.
+    fn apply(status: Status, current: &mut Option<Status>, output: &mut MockOutput) -> bool {
+        let changed = *current != Some(status);
+        if status_steady(status, changed, false, false, output).unwrap() {
+            return true;
+        }
+
+        drive_output(status, false, output).unwrap();
+        let _ = current.insert(status);
+
+        false
+    }
+
+    #[test]
+    fn repeated_samples_do_no_writes() {
+        let mut output = MockOutput::default();
+        let mut current = None;
+        let mut state = None;
+
+        for i in 0..100 {
+            let decision = auto_rh_decide(
+                Some(85.0),
+                false,
+                BAND,
+                current,
+                &mut state,
+                i * 1000,
+                MIN_DURATION_MS,
+            );
+            assert_eq!(decision, AutoRhDecision::Apply(Status::On));
+
+            let steady = apply(Status::On, &mut current, &mut output);
+            assert_eq!(steady, i > 0);
+        }
+
+        assert!(output.on);
+        assert_eq!(output.writes, 1);
+    }
+
+    #[test]
+    fn repeated_off_does_no_writes() {
+        let mut output = MockOutput::default();
+        let mut current = Some(Status::Off);
+
+        for _ in 0..100 {
+            assert!(apply(Status::Off, &mut current, &mut output));
+        }
+
+        assert_eq!(output.writes, 0);
+    }
+
+    #[test]
+    fn pin_out_of_step_is_driven_again() {
+        let mut output = MockOutput::default();
+        let mut current = None;
+
+        assert!(!apply(Status::On, &mut current, &mut output));
+        assert_eq!(output.writes, 1);
+
+        // Dropped underneath the status.
+        output.on = false;
+        assert!(!apply(Status::On, &mut current, &mut output));
+        assert!(output.on);
+        assert_eq!(output.writes, 2);
+
+        assert!(apply(Status::On, &mut current, &mut output));
+        assert_eq!(output.writes, 2);
+    }
+
+    #[test]
+    fn change_is_never_steady() {
+        let mut output = MockOutput::default();
+
+        // The pin already matches, but the status still has to be published.
+        assert!(!status_steady(Status::Off, true, false, false, &mut output).unwrap());
+        drive_output(Status::Off, false, &mut output).unwrap();
+        assert_eq!(output.writes, 0);
+    }
+
+    #[test]
+    fn fault_is_never_steady() {
+        let mut output = MockOutput::default();
+
+        assert!(!status_steady(Status::Fault, false, false, false, &mut output).unwrap());
+        // A fault reason left over from a previous 'Fault' still has to be cleared.
+        assert!(!status_steady(Status::Off, false, true, false, &mut output).unwrap());
+
+        // Already off, so repeated faults don't write either.
+        for _ in 0..10 {
+            drive_output(Status::Fault, false, &mut output).unwrap();
+        }
+        assert_eq!(output.writes, 0);
+    }
+
+    #[test]
+    fn burst_off_phase_keeps_the_pin_low() {
+        let mut output = MockOutput::default();
+
+        assert!(status_steady(Status::On, false, false, true, &mut output).unwrap());
+        drive_output(Status::On, true, &mut output).unwrap();
+        assert!(!output.on);
+        assert_eq!(output.writes, 0);
+    }
+}
@@ -0,0 +1,122 @@
+//! Config update fields that can be cleared (`Option<Option<T>>`), a missing field keeps the
+//! current value and a null clears it.
+
+use serde::{Deserialize, Deserializer};
+
+/// For `#[serde(default, deserialize_with = "...")]`, a present field (even null) is `Some` so
+/// only a missing one falls back to `None`.
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Applies an update's field over `current` (unset keeps it, null clears it).
+pub fn merge<T>(current: &mut Option<T>, update: Option<Option<T>>) {
+    if let Some(val) = update {
+        *current = val;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Update {
+        #[serde(default, deserialize_with = "deserialize")]
+        webhook_url: Option<Option<String>>,
+        #[serde(default, deserialize_with = "deserialize")]
+        telemetry_port: Option<Option<u16>>,
+        #[serde(default, deserialize_with = "deserialize")]
+        mister_rh_hard_floor: Option<Option<f32>>,
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct Current {
+        webhook_url: Option<String>,
+        telemetry_port: Option<u16>,
+        mister_rh_hard_floor: Option<f32>,
+    }
+
+    const CURRENT: Current = Current {
+        webhook_url: None,
+        telemetry_port: Some(9000),
+        mister_rh_hard_floor: Some(80.0),
+    };
+
+    fn apply(json: &str) -> Current {
+        let update: Update = serde_json::from_str(json).unwrap();
+
+        let mut cfg = CURRENT.clone();
+        merge(&mut cfg.webhook_url, update.webhook_url);
+        merge(&mut cfg.telemetry_port, update.telemetry_port);
+        merge(&mut cfg.mister_rh_hard_floor, update.mister_rh_hard_floor);
+
+        cfg
+    }
+
+    #[test]
+    fn missing_field_is_unset() {
+        let update: Update = serde_json::from_str("{}").unwrap();
+
+        assert_eq!(update.webhook_url, None);
+        assert_eq!(update.telemetry_port, None);
+    }
+
+    #[test]
+    fn null_field_is_cleared() {
+        let update: Update = serde_json::from_str(r#"{"telemetry_port": null}"#).unwrap();
+
+        assert_eq!(update.telemetry_port, Some(None));
+        assert_eq!(update.webhook_url, None);
+    }
+
+    #[test]
+    fn empty_update_keeps_everything() {
+        assert_eq!(apply("{}"), CURRENT);
+    }
+
+    #[test]
+    fn partial_update_keeps_other_fields() {
+        let cfg = apply(r#"{"webhook_url": "http://10.0.0.2/hook"}"#);
+
+        assert_eq!(cfg.webhook_url.as_deref(), Some("http://10.0.0.2/hook"));
+        assert_eq!(cfg.telemetry_port, Some(9000));
+        assert_eq!(cfg.mister_rh_hard_floor, Some(80.0));
+    }
+
+    #[test]
+    fn null_clears_only_that_field() {
+        let cfg = apply(r#"{"telemetry_port": null}"#);
+
+        assert_eq!(cfg.telemetry_port, None);
+        assert_eq!(cfg.mister_rh_hard_floor, Some(80.0));
+
+        let cfg = apply(r#"{"mister_rh_hard_floor": null, "telemetry_port": 9100}"#);
+
+        assert_eq!(cfg.mister_rh_hard_floor, None);
+        assert_eq!(cfg.telemetry_port, Some(9100));
+    }
+
+    #[test]
+    fn cleared_field_round_trips_as_null() {
+        // As persisted, every field is present so a cleared one stays cleared on reload.
+        let persisted = Update {
+            webhook_url: Some(None),
+            telemetry_port: Some(Some(9000)),
+            mister_rh_hard_floor: Some(None),
+        };
+        let json = serde_json::to_string(&persisted).unwrap();
+
+        assert_eq!(
+            json,
+            r#"{"webhook_url":null,"telemetry_port":9000,"mister_rh_hard_floor":null}"#
+        );
+        assert_eq!(serde_json::from_str::<Update>(&json).unwrap(), persisted);
+    }
+}
@@ -0,0 +1,509 @@
+//! The auto schedule state machine (Initial -> Pending -> Running -> next step).
+//!
+//! Pure (no timers, statics or pubsub), the firmware stores the state and acts on the action.
+
+use serde::Serialize;
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize)]
+pub enum AutoScheduleMode {
+    Initial,
+    Pending,
+    Running,
+    // Finished the last step (without looping), holding its conditions.
+    Holding,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct AutoScheduleState {
+    pub mode: AutoScheduleMode,
+    pub idx: usize,
+    // Time the schedule was activated, i.e. entered 'Pending' (used to enforce
+    // `max_wait_secs` when the rh fails to rise/fall into the band).
+    pub start_time: u32,
+    // Time the humidity for the schedule was first met to begin countdown.
+    pub run_start_time: u32,
+    // The rh band was entered at some point during this step.
+    pub target_reached: bool,
+    // Sticky warning, a step ended without ever reaching its band (e.g. an empty reservoir),
+    // cleared once a band is next reached.
+    pub target_unreached: bool,
+}
+
+impl AutoScheduleState {
+    fn new(mode: AutoScheduleMode, idx: usize, start_time: u32, run_start_time: u32) -> Self {
+        Self {
+            mode,
+            idx,
+            start_time,
+            run_start_time,
+            target_reached: false,
+            target_unreached: false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.mode = AutoScheduleMode::Initial;
+        self.idx = 0;
+        self.start_time = 0;
+        self.run_start_time = 0;
+        self.target_reached = false;
+        self.target_unreached = false;
+    }
+
+    /// Resets when `idx` is past the end of a schedule of `len` steps, returns true if it did.
+    pub fn clamp(&mut self, len: usize) -> bool {
+        if matches!(self.mode, AutoScheduleMode::Initial) || self.idx < len {
+            return false;
+        }
+
+        self.reset();
+        true
+    }
+
+    /// Activated (entered 'Pending') at `now`.
+    pub fn pending(idx: usize, now: u32) -> Self {
+        Self::new(AutoScheduleMode::Pending, idx, now, 0)
+    }
+
+    /// Keeps the sticky warnings from the previous step.
+    pub fn carry(mut self, prev: &AutoScheduleState) -> Self {
+        self.target_unreached = prev.target_unreached;
+        self
+    }
+
+    // The ms uptime wraps after ~49.7 days.
+    pub fn total_ms_at(&self, now: u32) -> u32 {
+        now.wrapping_sub(self.start_time)
+    }
+
+    pub fn running_ms_at(&self, now: u32) -> u32 {
+        now.wrapping_sub(self.run_start_time)
+    }
+}
+
+impl Default for AutoScheduleState {
+    fn default() -> Self {
+        Self::new(AutoScheduleMode::Initial, 0, 0, 0)
+    }
+}
+
+/// The active step as `advance` needs it (with the config's rh adjustments already applied).
+#[derive(Clone, Copy, Debug)]
+pub struct StepTarget {
+    pub rh_on: f32,
+    pub rh_off: f32,
+    pub run_secs: u32,
+    pub max_wait_secs: Option<u32>,
+    pub hold_until_target: bool,
+}
+
+/// The shape of the schedule, `len` steps (wrapping to the first after the last when `looping`).
+#[derive(Clone, Copy, Debug)]
+pub struct Program {
+    pub len: usize,
+    pub looping: bool,
+}
+
+/// Transition applied by `advance`.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum AutoScheduleAction {
+    None,
+    // Entered 'Running' (the countdown began).
+    Running,
+    // Moved on to (and activated) the step.
+    Started(usize),
+    // Finished the last step without looping (now 'Holding').
+    Completed,
+    // No metrics to check against (the state is unchanged).
+    Fault,
+}
+
+/// Works out the next auto schedule state for the current `rh` at `now`, `step` being the
+/// active one (`state.idx`).
+pub fn advance(
+    state: &AutoScheduleState,
+    step: &StepTarget,
+    program: Program,
+    rh: Option<f32>,
+    now: u32,
+) -> (AutoScheduleState, AutoScheduleAction) {
+    let Some(rh) = rh else {
+        return (state.clone(), AutoScheduleAction::Fault);
+    };
+
+    let rh_on = step.rh_on;
+    let rh_off = step.rh_off;
+    let in_band = rh >= rh_on && rh <= rh_off;
+
+    let mut tracked = state.clone();
+    if in_band {
+        tracked.target_reached = true;
+        tracked.target_unreached = false;
+    }
+    let state = &tracked;
+
+    let timed_out = match step.max_wait_secs {
+        Some(max_wait_secs) => state.total_ms_at(now) >= max_wait_secs * 1000,
+        None => false,
+    };
+
+    match state.mode {
+        AutoScheduleMode::Pending => {
+            let should_run = if step.hold_until_target {
+                // The target is checked while 'Running' (no countdown).
+                log::info!("Mister auto schedule ('{}') now 'Running' [holding until rh '{}' >= '{}' && <= '{}']",
+                    state.idx, rh, rh_on, rh_off);
+
+                true
+            } else if in_band {
+                log::info!(
+                    "Mister auto schedule ('{}') now 'Running' [rh '{}' >= '{}' && <= '{}']",
+                    state.idx,
+                    rh,
+                    rh_on,
+                    rh_off
+                );
+
+                true
+            } else if timed_out {
+                log::warn!("Mister auto schedule ('{}') now 'Running' [time-out waiting for rh '{}' >= '{}' && <= '{}']",
+                    state.idx, rh, rh_on, rh_off);
+
+                true
+            } else {
+                false
+            };
+
+            if !should_run {
+                return (state.clone(), AutoScheduleAction::None);
+            }
+
+            let mut new_state = state.clone();
+            new_state.run_start_time = now;
+            new_state.mode = AutoScheduleMode::Running;
+
+            (new_state, AutoScheduleAction::Running)
+        }
+        AutoScheduleMode::Running => {
+            let advance = if step.hold_until_target {
+                if in_band {
+                    log::info!(
+                        "Mister auto schedule ('{}') reached target [rh '{}' >= '{}' && <= '{}']",
+                        state.idx,
+                        rh,
+                        rh_on,
+                        rh_off
+                    );
+
+                    true
+                } else if timed_out {
+                    log::warn!("Mister auto schedule ('{}') advancing [time-out waiting for rh '{}' >= '{}' && <= '{}']",
+                        state.idx, rh, rh_on, rh_off);
+
+                    true
+                } else {
+                    false
+                }
+            } else {
+                state.running_ms_at(now) >= step.run_secs * 1000
+            };
+
+            if !advance {
+                return (state.clone(), AutoScheduleAction::None);
+            }
+
+            let (mut new_state, action) = next(state, program, now);
+            if !state.target_reached {
+                log::warn!("Mister auto schedule ('{}') ended without reaching its target [rh '{}' >= '{}' && <= '{}']",
+                    state.idx, rh, rh_on, rh_off);
+
+                new_state.target_unreached = true;
+            }
+
+            (new_state, action)
+        }
+        AutoScheduleMode::Initial | AutoScheduleMode::Holding => {
+            (state.clone(), AutoScheduleAction::None)
+        }
+    }
+}
+
+/// Moves on to the next step (wrapping when looping, otherwise holding on the last).
+pub fn next(
+    state: &AutoScheduleState,
+    program: Program,
+    now: u32,
+) -> (AutoScheduleState, AutoScheduleAction) {
+    if program.len >= state.idx + 2 {
+        (
+            AutoScheduleState::pending(state.idx + 1, now).carry(state),
+            AutoScheduleAction::Started(state.idx + 1),
+        )
+    } else if program.looping {
+        (
+            AutoScheduleState::pending(0, now).carry(state),
+            AutoScheduleAction::Started(0),
+        )
+    } else {
+        let mut new_state = state.clone();
+        new_state.mode = AutoScheduleMode::Holding;
+
+        (new_state, AutoScheduleAction::Completed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A 90% step (band 88 to 92), running for 60s once reached, waiting up to 30s for it.
+    const STEP: StepTarget = StepTarget {
+        rh_on: 88.0,
+        rh_off: 92.0,
+        run_secs: 60,
+        max_wait_secs: Some(30),
+        hold_until_target: false,
+    };
+    const HOLD_STEP: StepTarget = StepTarget {
+        hold_until_target: true,
+        ..STEP
+    };
+    const PROGRAM: Program = Program {
+        len: 3,
+        looping: false,
+    };
+    const LOOPING: Program = Program {
+        looping: true,
+        ..PROGRAM
+    };
+    const IN_BAND: Option<f32> = Some(90.0);
+    const BELOW_BAND: Option<f32> = Some(80.0);
+
+    fn running(idx: usize, start_time: u32, run_start_time: u32) -> AutoScheduleState {
+        let mut state = AutoScheduleState::pending(idx, start_time);
+        state.mode = AutoScheduleMode::Running;
+        state.run_start_time = run_start_time;
+        state.target_reached = true;
+        state
+    }
+
+    #[test]
+    fn pending_runs_once_in_band() {
+        let state = AutoScheduleState::pending(0, 1000);
+
+        let (new_state, action) = advance(&state, &STEP, PROGRAM, IN_BAND, 2000);
+
+        assert_eq!(action, AutoScheduleAction::Running);
+        assert_eq!(new_state.mode, AutoScheduleMode::Running);
+        assert_eq!(new_state.run_start_time, 2000);
+        assert!(new_state.target_reached);
+    }
+
+    #[test]
+    fn pending_waits_out_of_band() {
+        let state = AutoScheduleState::pending(0, 1000);
+
+        let (new_state, action) = advance(&state, &STEP, PROGRAM, BELOW_BAND, 2000);
+
+        assert_eq!(action, AutoScheduleAction::None);
+        assert_eq!(new_state, state);
+    }
+
+    #[test]
+    fn pending_runs_after_max_wait() {
+        let state = AutoScheduleState::pending(0, 1000);
+
+        let (_, action) = advance(&state, &STEP, PROGRAM, BELOW_BAND, 1000 + 29_999);
+        assert_eq!(action, AutoScheduleAction::None);
+
+        let (new_state, action) = advance(&state, &STEP, PROGRAM, BELOW_BAND, 1000 + 30_000);
+        assert_eq!(action, AutoScheduleAction::Running);
+        assert_eq!(new_state.mode, AutoScheduleMode::Running);
+        assert!(!new_state.target_reached);
+    }
+
+    #[test]
+    fn pending_times_out_across_the_uptime_wrap() {
+        let start = u32::MAX - 1_000;
+        let state = AutoScheduleState::pending(0, start);
+
+        let (_, action) = advance(
+            &state,
+            &STEP,
+            PROGRAM,
+            BELOW_BAND,
+            start.wrapping_add(29_999),
+        );
+        assert_eq!(action, AutoScheduleAction::None);
+
+        let (_, action) = advance(
+            &state,
+            &STEP,
+            PROGRAM,
+            BELOW_BAND,
+            start.wrapping_add(30_000),
+        );
+        assert_eq!(action, AutoScheduleAction::Running);
+    }
+
+    #[test]
+    fn pending_without_max_wait_never_times_out() {
+        let step = StepTarget {
+            max_wait_secs: None,
+            ..STEP
+        };
+        let state = AutoScheduleState::pending(0, 0);
+
+        let (_, action) = advance(&state, &step, PROGRAM, BELOW_BAND, u32::MAX);
+
+        assert_eq!(action, AutoScheduleAction::None);
+    }
+
+    #[test]
+    fn running_advances_after_run_secs() {
+        let state = running(0, 1000, 2000);
+
+        let (_, action) = advance(&state, &STEP, PROGRAM, IN_BAND, 2000 + 59_999);
+        assert_eq!(action, AutoScheduleAction::None);
+
+        let (new_state, action) = advance(&state, &STEP, PROGRAM, IN_BAND, 2000 + 60_000);
+        assert_eq!(action, AutoScheduleAction::Started(1));
+        assert_eq!(new_state, AutoScheduleState::pending(1, 62_000));
+    }
+
+    #[test]
+    fn running_counts_down_across_the_uptime_wrap() {
+        let start = u32::MAX - 1_000;
+        let state = running(0, start, start);
+
+        let (_, action) = advance(&state, &STEP, PROGRAM, IN_BAND, start.wrapping_add(59_999));
+        assert_eq!(action, AutoScheduleAction::None);
+
+        let (_, action) = advance(&state, &STEP, PROGRAM, IN_BAND, start.wrapping_add(60_000));
+        assert_eq!(action, AutoScheduleAction::Started(1));
+    }
+
+    #[test]
+    fn last_step_wraps_to_first_when_looping() {
+        let state = running(2, 1000, 2000);
+
+        let (new_state, action) = advance(&state, &STEP, LOOPING, IN_BAND, 62_000);
+
+        assert_eq!(action, AutoScheduleAction::Started(0));
+        assert_eq!(new_state, AutoScheduleState::pending(0, 62_000));
+    }
+
+    #[test]
+    fn last_step_holds_without_looping() {
+        let state = running(2, 1000, 2000);
+
+        let (new_state, action) = advance(&state, &STEP, PROGRAM, IN_BAND, 62_000);
+        assert_eq!(action, AutoScheduleAction::Completed);
+        assert_eq!(new_state.mode, AutoScheduleMode::Holding);
+        assert_eq!(new_state.idx, 2);
+
+        // Holding stays put.
+        let (held, action) = advance(&new_state, &STEP, PROGRAM, BELOW_BAND, u32::MAX);
+        assert_eq!(action, AutoScheduleAction::None);
+        assert_eq!(held, new_state);
+    }
+
+    #[test]
+    fn hold_until_target_advances_on_reaching_band() {
+        let state = AutoScheduleState::pending(0, 1000);
+
+        // Runs straight away, without a countdown.
+        let (state, action) = advance(&state, &HOLD_STEP, PROGRAM, BELOW_BAND, 1500);
+        assert_eq!(action, AutoScheduleAction::Running);
+
+        let (state, action) = advance(&state, &HOLD_STEP, PROGRAM, BELOW_BAND, 2000);
+        assert_eq!(action, AutoScheduleAction::None);
+
+        let (new_state, action) = advance(&state, &HOLD_STEP, PROGRAM, IN_BAND, 3000);
+        assert_eq!(action, AutoScheduleAction::Started(1));
+        assert!(!new_state.target_unreached);
+    }
+
+    #[test]
+    fn hold_until_target_advances_after_max_wait() {
+        let state = AutoScheduleState::pending(0, 1000);
+        let (state, _) = advance(&state, &HOLD_STEP, PROGRAM, BELOW_BAND, 1500);
+
+        let (new_state, action) = advance(&state, &HOLD_STEP, PROGRAM, BELOW_BAND, 31_000);
+
+        assert_eq!(action, AutoScheduleAction::Started(1));
+        assert!(new_state.target_unreached);
+    }
+
+    #[test]
+    fn unreached_target_is_sticky_until_a_band_is_reached() {
+        // Timed out waiting, then ran its course, without ever reaching the band.
+        let state = AutoScheduleState::pending(0, 0);
+        let (state, _) = advance(&state, &STEP, PROGRAM, BELOW_BAND, 30_000);
+        let (state, action) = advance(&state, &STEP, PROGRAM, BELOW_BAND, 90_000);
+        assert_eq!(action, AutoScheduleAction::Started(1));
+        assert!(state.target_unreached);
+
+        // Carried into the next step while still out of band.
+        let (state, _) = advance(&state, &STEP, PROGRAM, BELOW_BAND, 91_000);
+        assert!(state.target_unreached);
+
+        let (state, _) = advance(&state, &STEP, PROGRAM, IN_BAND, 92_000);
+        assert!(!state.target_unreached);
+    }
+
+    #[test]
+    fn missing_metrics_faults_without_changing_state() {
+        let state = running(1, 1000, 2000);
+
+        let (new_state, action) = advance(&state, &STEP, PROGRAM, None, u32::MAX);
+
+        assert_eq!(action, AutoScheduleAction::Fault);
+        assert_eq!(new_state, state);
+    }
+
+    #[test]
+    fn initial_does_nothing() {
+        let state = AutoScheduleState::default();
+
+        let (new_state, action) = advance(&state, &STEP, PROGRAM, IN_BAND, 1000);
+
+        assert_eq!(action, AutoScheduleAction::None);
+        assert_eq!(new_state.mode, AutoScheduleMode::Initial);
+    }
+
+    #[test]
+    fn reset_returns_to_initial() {
+        // As on a mode change.
+        let mut state = running(2, 1000, 2000);
+        state.target_unreached = true;
+
+        state.reset();
+
+        assert_eq!(state, AutoScheduleState::default());
+    }
+
+    #[test]
+    fn clamp_resets_past_the_end() {
+        let mut state = running(2, 1000, 2000);
+        assert!(!state.clamp(3));
+        assert_eq!(state.idx, 2);
+
+        assert!(state.clamp(2));
+        assert_eq!(state, AutoScheduleState::default());
+    }
+
+    #[test]
+    fn next_moves_on_and_carries_warnings() {
+        let mut state = running(0, 1000, 2000);
+        state.target_unreached = true;
+
+        let (new_state, action) = next(&state, PROGRAM, 5000);
+
+        assert_eq!(action, AutoScheduleAction::Started(1));
+        assert_eq!(new_state.mode, AutoScheduleMode::Pending);
+        assert_eq!(new_state.start_time, 5000);
+        assert!(new_state.target_unreached);
+        assert!(!new_state.target_reached);
+    }
+}
@@ -0,0 +1,138 @@
+//! A value that's only ever replaced whole, readers get either the old or the new one (never one
+//! part way through being written).
+
+use alloc::sync::Arc;
+
+use spin::RwLock;
+
+pub struct SwapCell<T> {
+    value: RwLock<Arc<T>>,
+}
+
+impl<T> SwapCell<T> {
+    pub fn new(value: Arc<T>) -> Self {
+        Self {
+            value: RwLock::new(value),
+        }
+    }
+
+    /// The current value, a later `store` doesn't change what's already been loaded.
+    pub fn load(&self) -> Arc<T> {
+        self.value.read().clone()
+    }
+
+    /// Replaces the value, every `load` from when it returns gets the new one.
+    pub fn store(&self, value: Arc<T>) {
+        *self.value.write() = value;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::thread;
+
+    use super::*;
+
+    /// Every field carries the same generation, a mix of two would show as a mismatch.
+    #[derive(Debug)]
+    struct Instance {
+        generation: u32,
+        rh: f32,
+        schedule: Vec<u32>,
+        name: String,
+    }
+
+    impl Instance {
+        fn new(generation: u32) -> Self {
+            Self {
+                generation,
+                rh: generation as f32,
+                schedule: vec![generation; 16],
+                name: generation.to_string(),
+            }
+        }
+
+        fn assert_consistent(&self) {
+            assert_eq!(self.rh, self.generation as f32);
+            assert!(self.schedule.iter().all(|v| *v == self.generation));
+            assert_eq!(self.name, self.generation.to_string());
+        }
+    }
+
+    #[test]
+    fn load_keeps_its_value_after_a_store() {
+        let cell = SwapCell::new(Arc::new(Instance::new(1)));
+
+        let loaded = cell.load();
+        cell.store(Arc::new(Instance::new(2)));
+
+        assert_eq!(loaded.generation, 1);
+        loaded.assert_consistent();
+        assert_eq!(cell.load().generation, 2);
+    }
+
+    #[test]
+    fn load_never_observes_a_partial_instance() {
+        const GENERATIONS: u32 = 2_000;
+
+        let cell = Arc::new(SwapCell::new(Arc::new(Instance::new(0))));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let cell = cell.clone();
+                let done = done.clone();
+                thread::spawn(move || {
+                    let mut last = 0;
+                    while !done.load(Ordering::Acquire) {
+                        let inst = cell.load();
+                        inst.assert_consistent();
+
+                        // Only ever moves forward (a single writer).
+                        assert!(inst.generation >= last);
+                        last = inst.generation;
+                    }
+                })
+            })
+            .collect();
+
+        for generation in 1..=GENERATIONS {
+            cell.store(Arc::new(Instance::new(generation)));
+        }
+        done.store(true, Ordering::Release);
+
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(cell.load().generation, GENERATIONS);
+    }
+
+    #[test]
+    fn store_is_visible_to_whoever_it_signals() {
+        // As `Config::apply` swaps the instance before publishing a reset, the task acting on
+        // the reset must `load` the new one.
+        for _ in 0..100 {
+            let cell = Arc::new(SwapCell::new(Arc::new(Instance::new(0))));
+            let signalled = Arc::new(AtomicU32::new(0));
+
+            let reader = {
+                let cell = cell.clone();
+                let signalled = signalled.clone();
+                thread::spawn(move || loop {
+                    let generation = signalled.load(Ordering::Acquire);
+                    if generation > 0 {
+                        assert_eq!(cell.load().generation, generation);
+                        break;
+                    }
+                    std::hint::spin_loop();
+                })
+            };
+
+            cell.store(Arc::new(Instance::new(1)));
+            signalled.store(1, Ordering::Release);
+
+            reader.join().unwrap();
+        }
+    }
+}
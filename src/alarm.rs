@@ -0,0 +1,152 @@
+use embassy_executor::Spawner;
+use embassy_futures::select::{select, Either};
+use embassy_sync::pubsub::WaitResult;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_hal::digital::OutputPin;
+use esp_hal::gpio::{GpioPin, Output, PushPull, Unknown};
+use spin::RwLock;
+
+use crate::config::{Config, ConfigInstance};
+use crate::error::{map_embassy_pub_sub_err, map_embassy_spawn_err, map_infallible_err, Result};
+use crate::lag;
+use crate::lag::LagChannel;
+use crate::mister::{Status, StatusChangedSubscriber, STATUS, STATUS_CHANGED_CHANNEL};
+use crate::sensor::metrics_age_ms;
+use crate::utils::get_time_ms;
+
+const BUZZER_GPIO_PIN: u8 = 33;
+// ESP32 GPIO_OUT1_W1TC_REG (write 1 to clear output bit, for GPIO32-39).
+const GPIO_OUT1_W1TC_REG: u32 = 0x3ff4_4018;
+// Beep pattern while sounding (on then off, repeating).
+const ALARM_BEEP_MS: u32 = 500;
+
+static ALARM_ON: RwLock<bool> = RwLock::new(false);
+static MUTED_UNTIL: RwLock<Option<Instant>> = RwLock::new(None);
+
+pub(crate) fn init(
+    cfg: Config,
+    buzzer_pin: GpioPin<Unknown, BUZZER_GPIO_PIN>,
+    spawner: &Spawner,
+) -> Result<()> {
+    spawner
+        .spawn(alarm_task(
+            cfg,
+            buzzer_pin,
+            STATUS_CHANGED_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+        ))
+        .map_err(map_embassy_spawn_err)
+}
+
+#[embassy_executor::task]
+async fn alarm_task(
+    cfg: Config,
+    buzzer_pin: GpioPin<Unknown, BUZZER_GPIO_PIN>,
+    mut status_changed_sub: StatusChangedSubscriber,
+) {
+    let mut buzzer_pin = buzzer_pin.into_push_pull_output();
+    if let Err(e) = buzzer_pin.set_low().map_err(map_infallible_err) {
+        log::warn!("Failed to drive buzzer pin off at startup: {:?}", e);
+    }
+
+    let mut fault_since: Option<u32> = None;
+
+    loop {
+        if let Err(e) = alarm_task_poll(
+            cfg.load().as_ref(),
+            &mut buzzer_pin,
+            &mut status_changed_sub,
+            &mut fault_since,
+        )
+        .await
+        {
+            log::warn!("alarm task poll failed: {:?}", e);
+
+            // Some sleep to avoid thrashing.
+            Timer::after(Duration::from_millis(5000)).await;
+        }
+    }
+}
+
+async fn alarm_task_poll(
+    cfg: &ConfigInstance,
+    buzzer_pin: &mut GpioPin<Output<PushPull>, BUZZER_GPIO_PIN>,
+    status_changed_sub: &mut StatusChangedSubscriber,
+    fault_since: &mut Option<u32>,
+) -> Result<()> {
+    // Woken by a status change (to start/stop promptly) or to step the beep pattern.
+    let next_step_ms = ALARM_BEEP_MS - get_time_ms() % ALARM_BEEP_MS;
+    match select(
+        status_changed_sub.next_message(),
+        Timer::after(Duration::from_millis(next_step_ms as u64)),
+    )
+    .await
+    {
+        Either::First(WaitResult::Lagged(count)) => {
+            log::warn!("alarm status subscriber lagged by {} messages", count);
+            lag::record(LagChannel::MisterStatusChanged);
+
+            // Ignore (the status is read below regardless).
+        }
+        Either::First(WaitResult::Message(_)) | Either::Second(_) => {}
+    }
+
+    let now = get_time_ms();
+    if matches!(STATUS.read().as_ref(), Some(&Status::Fault)) {
+        fault_since.get_or_insert(now);
+    } else {
+        let _ = fault_since.take();
+    }
+
+    let faulted = fault_since.is_some_and(|since| now.wrapping_sub(since) >= cfg.alarm_delay_ms);
+    // No reading yet counts from boot.
+    let sensor_out = cfg.sensor_enabled && metrics_age_ms().unwrap_or(now) >= cfg.alarm_delay_ms;
+
+    let sounding = (faulted || sensor_out) && !is_muted();
+    if sounding != is_alarm_on() {
+        if sounding {
+            log::warn!(
+                "Alarm: On [fault: {}, sensor outage: {}]",
+                faulted,
+                sensor_out
+            );
+        } else {
+            log::info!("Alarm: Off");
+        }
+
+        *ALARM_ON.write() = sounding;
+    }
+
+    let beep = sounding && (now / ALARM_BEEP_MS) % 2 == 0;
+    if beep {
+        buzzer_pin.set_high().map_err(map_infallible_err)
+    } else {
+        buzzer_pin.set_low().map_err(map_infallible_err)
+    }
+}
+
+/// Silences the alarm for `secs` (it sounds again after if the condition persists).
+pub(crate) fn mute(secs: u32) {
+    log::info!("Alarm muted for {} seconds", secs);
+
+    let _ = MUTED_UNTIL
+        .write()
+        .insert(Instant::now() + Duration::from_secs(secs as u64));
+}
+
+fn is_muted() -> bool {
+    matches!(*MUTED_UNTIL.read(), Some(until) if until > Instant::now())
+}
+
+/// The alarm condition is active (and not muted), whether or not mid-beep.
+pub(crate) fn is_alarm_on() -> bool {
+    *ALARM_ON.read()
+}
+
+/// Last resort silencing of the buzzer (i.e. from the panic/exception halt path).
+pub(crate) fn force_off() {
+    unsafe {
+        core::ptr::write_volatile(GPIO_OUT1_W1TC_REG as *mut u32, 1 << (BUZZER_GPIO_PIN - 32));
+    }
+}
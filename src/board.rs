@@ -0,0 +1,33 @@
+//! Pin assignments for the supported board layouts (selected with a `board-*` feature).
+//!
+//! The HAL pin types are const-generic over the pin number, so the numbers here and the pins
+//! taken by the macros must change together (a mismatch fails to compile).
+
+// Reference board.
+#[cfg(not(feature = "board-devkit"))]
+pub(crate) const MISTER_POWER_GPIO_PIN: u8 = 17;
+#[cfg(not(feature = "board-devkit"))]
+pub(crate) const STATUS_LED_GPIO_PIN: u8 = 22;
+
+// ESP32 DevKitC, the on-board LED is used for the status.
+#[cfg(feature = "board-devkit")]
+pub(crate) const MISTER_POWER_GPIO_PIN: u8 = 16;
+#[cfg(feature = "board-devkit")]
+pub(crate) const STATUS_LED_GPIO_PIN: u8 = 2;
+
+/// Takes the `(mister power, status LED)` pins for the board from `io.pins`.
+#[cfg(not(feature = "board-devkit"))]
+macro_rules! mister_pins {
+    ($pins:expr) => {
+        ($pins.gpio17, $pins.gpio22)
+    };
+}
+
+#[cfg(feature = "board-devkit")]
+macro_rules! mister_pins {
+    ($pins:expr) => {
+        ($pins.gpio16, $pins.gpio2)
+    };
+}
+
+pub(crate) use mister_pins;
@@ -1,25 +1,71 @@
+use alloc::vec::Vec;
+
 use embassy_executor::Spawner;
+use embassy_futures::select::{select, Either};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::pubsub::{PubSubChannel, Publisher, Subscriber, WaitResult};
-use embassy_time::{Duration, Timer};
-use esp_hal::reset::software_reset;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_storage::{ReadStorage, Storage};
+use esp_hal::macros::ram;
+use esp_hal::reset::{get_reset_reason, software_reset};
+use esp_storage::FlashStorage;
+use serde::Serialize;
+use spin::RwLock;
 
 use crate::config::{Config, ConfigInstance};
 use crate::error::{map_embassy_pub_sub_err, map_embassy_spawn_err, Result};
+#[cfg(feature = "heater")]
+use crate::heater;
+use crate::lag::LagChannel;
+use crate::{lag, mister};
+
+// Boot time bookkeeping has its own sector (0xa000 to 0xafff), a write erases and rewrites the
+// whole sector so a power loss mid-write can't take the config (0x9000 to 0x9fff) with it.
+pub(crate) const BOOT_FLASH_SECTOR_ADDR: u32 = 0xa000;
+const RESET_LOG_FLASH_ADDR: u32 = BOOT_FLASH_SECTOR_ADDR;
+// Boot counter (u32) followed by the circular log of entries.
+const RESET_LOG_HEADER_LEN: usize = 4;
+const RESET_LOG_LEN: usize = 8;
+// Boot number (u32) and reset reason code (u8).
+const RESET_LOG_ENTRY_LEN: usize = 5;
+const RESET_LOG_BYTES: usize = RESET_LOG_HEADER_LEN + (RESET_LOG_LEN * RESET_LOG_ENTRY_LEN);
+// Unstable boot counter (u8) after the reset log.
+const UNSTABLE_BOOTS_FLASH_ADDR: u32 = RESET_LOG_FLASH_ADDR + RESET_LOG_BYTES as u32;
+// Up this long and the boot is stable (clearing the unstable boot counter).
+const STABLE_BOOT_SECS: u64 = 60;
+// Marks `REQUESTED_RESET` as set (RTC memory holds garbage after a power loss).
+const REQUESTED_RESET_MAGIC: u32 = 0x5245_5153;
+
+// Set just before a requested reset (`ChipControlAction::Reset`), survives the software reset.
+#[ram(rtc_fast, uninitialized)]
+static mut REQUESTED_RESET: u32 = 0;
+
+// Most recent first, including this boot.
+static RESET_HISTORY: RwLock<Vec<ResetRecord>> = RwLock::new(Vec::new());
+// Booted into safe mode (after `safe_mode_boot_count` unstable boots).
+static SAFE_MODE: RwLock<bool> = RwLock::new(false);
 
 pub(crate) type ChipControlPublisher =
-    Publisher<'static, CriticalSectionRawMutex, ChipControlAction, 1, 1, 2>;
-type ChipControlSubscriber =
-    Subscriber<'static, CriticalSectionRawMutex, ChipControlAction, 1, 1, 2>;
+    Publisher<'static, CriticalSectionRawMutex, ChipControlAction, 1, 2, 3>;
+pub(crate) type ChipControlSubscriber =
+    Subscriber<'static, CriticalSectionRawMutex, ChipControlAction, 1, 2, 3>;
 pub(crate) static CHIP_CONTROL_CHANNEL: PubSubChannel<
     CriticalSectionRawMutex,
     ChipControlAction,
     1,
-    1,
     2,
+    3,
 > = PubSubChannel::new();
 
 pub(crate) fn init(cfg: Config, spawner: &Spawner) -> Result<()> {
+    let mut storage = FlashStorage::new();
+    record_boot(&mut storage);
+    record_unstable_boot(&mut storage, cfg.load().safe_mode_boot_count);
+
+    spawner
+        .spawn(stable_boot_task())
+        .map_err(map_embassy_spawn_err)?;
+
     spawner
         .spawn(chip_control_task(
             cfg.clone(),
@@ -33,7 +79,7 @@ pub(crate) fn init(cfg: Config, spawner: &Spawner) -> Result<()> {
 #[embassy_executor::task]
 async fn chip_control_task(cfg: Config, mut chip_control_sub: ChipControlSubscriber) {
     loop {
-        if let Err(e) = chip_control_task_poll(cfg.load().as_ref(), &mut chip_control_sub).await {
+        if let Err(e) = chip_control_task_poll(&cfg, &mut chip_control_sub).await {
             log::warn!("chip control task poll failed: {:?}", e);
 
             // Some sleep to avoid thrashing.
@@ -43,28 +89,247 @@ async fn chip_control_task(cfg: Config, mut chip_control_sub: ChipControlSubscri
 }
 
 async fn chip_control_task_poll(
-    cfg: &ConfigInstance,
+    cfg: &Config,
     chip_control_sub: &mut ChipControlSubscriber,
 ) -> Result<()> {
     match chip_control_sub.next_message().await {
         WaitResult::Lagged(count) => {
             log::warn!("chip control subscriber lagged by {} messages", count);
+            lag::record(LagChannel::ChipControl);
 
             // Ignore
             Ok(())
         }
         WaitResult::Message(action) => match action {
             ChipControlAction::Reset => {
+                let cfg = cfg.load();
+
                 log::warn!("chip will reset in {} seconds ...", cfg.reset_wait_secs);
-                Timer::after(Duration::from_secs(cfg.reset_wait_secs as u64)).await;
-                software_reset();
+                if reset_wait(cfg.as_ref(), chip_control_sub).await {
+                    // Not a crash, so the next boot isn't counted as unstable.
+                    set_requested_reset(true);
+                    reset_now()
+                }
+
+                log::warn!("chip reset cancelled");
+                Ok(())
+            }
+            ChipControlAction::FactoryReset => {
+                log::warn!("factory reset, restoring the default config ...");
+
+                // Publishes the reset (handled on the next poll).
+                cfg.reset()
+            }
+            ChipControlAction::CancelReset => {
+                log::warn!("no pending chip reset to cancel");
+                Ok(())
+            }
+            ChipControlAction::RestartNetwork => {
+                // Handled by the WIFI connection task.
                 Ok(())
             }
         },
     }
 }
 
+/// Clears the unstable boot counter once the boot has been up for `STABLE_BOOT_SECS`.
+#[embassy_executor::task]
+async fn stable_boot_task() {
+    Timer::after(Duration::from_secs(STABLE_BOOT_SECS)).await;
+
+    let mut storage = FlashStorage::new();
+    let mut bytes = [0u8; 1];
+    if let Err(e) = storage.read(UNSTABLE_BOOTS_FLASH_ADDR, &mut bytes) {
+        log::warn!("Failed to read unstable boot counter from flash: {:?}", e);
+        return;
+    }
+    // Already clear (the usual case), saves a sector rewrite.
+    if bytes[0] == 0 {
+        return;
+    }
+
+    if let Err(e) = storage.write(UNSTABLE_BOOTS_FLASH_ADDR, &[0]) {
+        log::warn!("Failed to clear unstable boot counter in flash: {:?}", e);
+    }
+}
+
+/// Waits out `reset_wait_secs`, `false` if the reset was cancelled in the meantime.
+async fn reset_wait(cfg: &ConfigInstance, chip_control_sub: &mut ChipControlSubscriber) -> bool {
+    let reset_at = Instant::now() + Duration::from_secs(cfg.reset_wait_secs as u64);
+
+    loop {
+        match select(Timer::at(reset_at), chip_control_sub.next_message()).await {
+            Either::First(_) => return true,
+            Either::Second(WaitResult::Message(ChipControlAction::CancelReset)) => return false,
+            // Anything else (i.e. another reset) doesn't change the countdown.
+            Either::Second(_) => {}
+        }
+    }
+}
+
+/// Turns the mister (and heater) off and resets immediately (without waiting).
+///
+/// Counts as an unstable boot unless flagged as requested first (i.e. heap exhaustion counts).
+pub(crate) fn reset_now() -> ! {
+    mister::force_off();
+    #[cfg(feature = "heater")]
+    heater::force_off();
+    software_reset();
+
+    loop {
+        core::hint::spin_loop()
+    }
+}
+
+pub(crate) fn reset_history() -> Vec<ResetRecord> {
+    RESET_HISTORY.read().clone()
+}
+
+/// The mister, sensor and controls aren't started (so the API can be used to fix a crash loop).
+pub(crate) fn is_safe_mode() -> bool {
+    *SAFE_MODE.read()
+}
+
+/// Appends this boot's reset reason to the (circular) reset log in flash.
+fn record_boot(storage: &mut FlashStorage) {
+    let reason = get_reset_reason().map(|r| r as u8).unwrap_or(0);
+
+    let mut bytes = [0u8; RESET_LOG_BYTES];
+    if let Err(e) = storage.read(RESET_LOG_FLASH_ADDR, &mut bytes) {
+        log::warn!("Failed to read reset log from flash: {:?}", e);
+        bytes = [0xff; RESET_LOG_BYTES];
+    }
+
+    // Erased flash reads back as 0xff.
+    let boot = match read_u32(&bytes) {
+        u32::MAX => 0,
+        last_boot => last_boot + 1,
+    };
+
+    let entry = RESET_LOG_HEADER_LEN + (boot as usize % RESET_LOG_LEN) * RESET_LOG_ENTRY_LEN;
+    bytes[..RESET_LOG_HEADER_LEN].copy_from_slice(&boot.to_be_bytes());
+    bytes[entry..entry + 4].copy_from_slice(&boot.to_be_bytes());
+    bytes[entry + 4] = reason;
+
+    if let Err(e) = storage.write(RESET_LOG_FLASH_ADDR, &bytes) {
+        log::warn!("Failed to persist reset log to flash: {:?}", e);
+    }
+
+    log::info!(
+        "Reset reason: {} [boot: {}]",
+        reset_reason_name(reason),
+        boot
+    );
+
+    let mut history: Vec<ResetRecord> = bytes[RESET_LOG_HEADER_LEN..]
+        .chunks(RESET_LOG_ENTRY_LEN)
+        .map(|entry| ResetRecord {
+            boot: read_u32(entry),
+            reason: reset_reason_name(entry[4]),
+        })
+        // Unused (erased) entries, or left over from before a log wipe.
+        .filter(|r| r.boot <= boot)
+        .collect();
+    history.sort_unstable_by(|a, b| b.boot.cmp(&a.boot));
+
+    *RESET_HISTORY.write() = history;
+}
+
+/// Counts boots in a row that didn't become stable, entering safe mode at `safe_mode_boot_count`.
+///
+/// Requested resets (config changes, `/reset`, the reset button) don't count, so they never trip
+/// it, any other software reset (i.e. heap exhaustion) does.
+fn record_unstable_boot(storage: &mut FlashStorage, safe_mode_boot_count: u8) {
+    let requested = is_requested_reset();
+    set_requested_reset(false);
+
+    let mut bytes = [0u8; 1];
+    if let Err(e) = storage.read(UNSTABLE_BOOTS_FLASH_ADDR, &mut bytes) {
+        log::warn!("Failed to read unstable boot counter from flash: {:?}", e);
+        return;
+    }
+
+    // Erased flash reads back as 0xff.
+    let mut unstable_boots = match bytes[0] {
+        u8::MAX => 0,
+        count => count,
+    };
+
+    let reason = get_reset_reason().map(|r| r as u8).unwrap_or(0);
+    if !(requested && is_software_reset(reason)) {
+        unstable_boots = unstable_boots.saturating_add(1).min(u8::MAX - 1);
+
+        if let Err(e) = storage.write(UNSTABLE_BOOTS_FLASH_ADDR, &[unstable_boots]) {
+            log::warn!("Failed to persist unstable boot counter to flash: {:?}", e);
+        }
+    }
+
+    if safe_mode_boot_count > 0 && unstable_boots >= safe_mode_boot_count {
+        log::warn!(
+            "Safe mode: {} unstable boots in a row, the mister, sensor and controls are disabled",
+            unstable_boots
+        );
+
+        *SAFE_MODE.write() = true;
+    }
+}
+
 #[derive(Clone)]
 pub(crate) enum ChipControlAction {
     Reset,
+    // Restores the default config, then resets.
+    FactoryReset,
+    // Aborts a pending reset (only during the `reset_wait_secs` countdown).
+    CancelReset,
+    RestartNetwork,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ResetRecord {
+    // Boot number (there is no wall clock to timestamp with).
+    boot: u32,
+    reason: &'static str,
+}
+
+// Utils
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// A reset by the firmware (i.e. `reset_now`) rather than a watchdog or power loss.
+fn is_software_reset(code: u8) -> bool {
+    matches!(code, 0x03 | 0x0c)
+}
+
+fn is_requested_reset() -> bool {
+    unsafe {
+        core::ptr::read_volatile(core::ptr::addr_of!(REQUESTED_RESET)) == REQUESTED_RESET_MAGIC
+    }
+}
+
+fn set_requested_reset(requested: bool) {
+    let val = if requested { REQUESTED_RESET_MAGIC } else { 0 };
+
+    unsafe { core::ptr::write_volatile(core::ptr::addr_of_mut!(REQUESTED_RESET), val) }
+}
+
+/// Name of an ESP32 (PRO CPU) reset reason code.
+fn reset_reason_name(code: u8) -> &'static str {
+    match code {
+        0x01 => "ChipPowerOn",
+        0x03 => "CoreSw",
+        0x05 => "CoreDeepSleep",
+        0x06 => "CoreSdio",
+        0x07 => "CoreMwdt0",
+        0x08 => "CoreMwdt1",
+        0x09 => "CoreRtcWdt",
+        0x0b => "Cpu0Mwdt0",
+        0x0c => "Cpu0Sw",
+        0x0d => "Cpu0RtcWdt",
+        0x0e => "Cpu1Cpu0",
+        0x0f => "SysBrownOut",
+        0x10 => "SysRtcWdt",
+        _ => "Unknown",
+    }
 }
@@ -0,0 +1,239 @@
+use alloc::format;
+
+use embassy_executor::Spawner;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pubsub::{PubSubChannel, Publisher};
+use embassy_time::{Duration, Timer};
+use embedded_hal::i2c::I2c;
+use embedded_hal_bus::i2c::RefCellDevice;
+use esp_hal::i2c::I2C;
+use esp_hal::peripherals::I2C0;
+use serde::Serialize;
+use spin::RwLock;
+
+use crate::config::Config;
+use crate::error::{map_embassy_pub_sub_err, map_embassy_spawn_err, sensor_fault, Result};
+use crate::sensor::SensorBus;
+
+static SCD40_I2C_ADDR: u8 = 0x62;
+static SCD40_CMD_START_PERIODIC_MEASUREMENT: u16 = 0x21b1;
+static SCD40_CMD_STOP_PERIODIC_MEASUREMENT: u16 = 0x3f86;
+static SCD40_CMD_GET_DATA_READY_STATUS: u16 = 0xe4b8;
+static SCD40_CMD_READ_MEASUREMENT: u16 = 0xec05;
+static SCD40_CMD_REINIT: u16 = 0x3646;
+static MAX_ATTEMPTS: u8 = 5;
+
+pub(crate) static CO2_METRICS: RwLock<Option<Co2Metrics>> = RwLock::new(None);
+
+pub(crate) static CO2_CHANNEL: PubSubChannel<CriticalSectionRawMutex, Option<Co2Metrics>, 1, 1, 1> =
+    PubSubChannel::new();
+
+pub(crate) fn init(cfg: Config, bus: &'static SensorBus, spawner: &Spawner) -> Result<()> {
+    spawner
+        .spawn(emitter(
+            cfg,
+            bus,
+            CO2_CHANNEL.publisher().map_err(map_embassy_pub_sub_err)?,
+        ))
+        .map_err(map_embassy_spawn_err)?;
+
+    Ok(())
+}
+
+#[embassy_executor::task]
+async fn emitter(
+    cfg: Config,
+    bus: &'static SensorBus,
+    publisher: Publisher<'static, CriticalSectionRawMutex, Option<Co2Metrics>, 1, 1, 1>,
+) {
+    loop {
+        let mut dev = Scd40::new(bus.device());
+
+        match dev.start().await {
+            Ok(_) => loop {
+                match emitter_poll(&cfg, &mut dev, &publisher).await {
+                    Ok(reload) => {
+                        if reload {
+                            log::warn!("Reloading co2 sensor device");
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        log::warn!("Co2 sensor emitter poll failed: {:?}", e);
+                    }
+                }
+            },
+            Err(e) => {
+                log::warn!("Failed to start co2 sensor device: {:?}", e);
+                publisher.publish_immediate(None);
+
+                Timer::after(Duration::from_millis(cfg.load().co2_delay_err_ms as u64)).await;
+            }
+        }
+    }
+}
+
+async fn emitter_poll(
+    cfg: &Config,
+    dev: &mut Scd40<RefCellDevice<'static, I2C<'static, I2C0>>>,
+    publisher: &Publisher<'static, CriticalSectionRawMutex, Option<Co2Metrics>, 1, 1, 1>,
+) -> Result<bool> {
+    let cfg = cfg.load();
+
+    let mut msg: Option<Co2Metrics> = None;
+    for attempt in 1..(MAX_ATTEMPTS + 1) {
+        match dev.read().await {
+            Ok(Some(co2_ppm)) => {
+                log::debug!("Co2 Sensor - CO2: {} ppm", co2_ppm);
+
+                let _ = msg.insert(Co2Metrics { co2_ppm });
+                break;
+            }
+            Ok(None) => {
+                // No new measurement yet, keep the last one.
+                Timer::after(Duration::from_millis(cfg.co2_delay_ms as u64)).await;
+                return Ok(false);
+            }
+            Err(e) => {
+                log::error!(
+                    "Failed to read from co2 sensor: {:?} [attempt {} of {}]",
+                    e,
+                    attempt,
+                    MAX_ATTEMPTS
+                );
+            }
+        }
+
+        if attempt == MAX_ATTEMPTS {
+            break;
+        } else if attempt == (MAX_ATTEMPTS - 1) {
+            if let Err(e) = dev.reset().await {
+                log::error!("Failed to send reset command to co2 sensor: {:?}", e);
+            } else {
+                log::warn!("Sent reset command to co2 sensor");
+            }
+        } else {
+            Timer::after(Duration::from_millis(100)).await;
+        }
+    }
+
+    let failed = !msg.is_some();
+    match CO2_METRICS.write() {
+        mut wr => {
+            *wr = msg.clone();
+        }
+    }
+
+    publisher.publish_immediate(msg);
+
+    if failed {
+        Timer::after(Duration::from_millis(cfg.co2_delay_err_ms as u64)).await;
+
+        // Re-create device.
+        return Ok(true);
+    }
+
+    Timer::after(Duration::from_millis(cfg.co2_delay_ms as u64)).await;
+
+    Ok(false)
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Co2Metrics {
+    pub(crate) co2_ppm: u16,
+}
+
+/// Minimal SCD40 driver (periodic measurement mode only).
+struct Scd40<I> {
+    i2c: I,
+}
+
+impl<I> Scd40<I>
+where
+    I: I2c,
+{
+    fn new(i2c: I) -> Self {
+        Self { i2c }
+    }
+
+    async fn start(&mut self) -> Result<()> {
+        // The sensor may still be measuring from before a chip reset.
+        self.write_cmd(SCD40_CMD_STOP_PERIODIC_MEASUREMENT)?;
+        Timer::after(Duration::from_millis(500)).await;
+
+        self.write_cmd(SCD40_CMD_START_PERIODIC_MEASUREMENT)
+    }
+
+    async fn read(&mut self) -> Result<Option<u16>> {
+        let [status] = self
+            .read_words::<1>(SCD40_CMD_GET_DATA_READY_STATUS)
+            .await?;
+        if status & 0x07ff == 0 {
+            return Ok(None);
+        }
+
+        let [co2_ppm, _, _] = self.read_words::<3>(SCD40_CMD_READ_MEASUREMENT).await?;
+
+        Ok(Some(co2_ppm))
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.write_cmd(SCD40_CMD_STOP_PERIODIC_MEASUREMENT)?;
+        Timer::after(Duration::from_millis(500)).await;
+
+        self.write_cmd(SCD40_CMD_REINIT)?;
+        Timer::after(Duration::from_millis(30)).await;
+
+        self.write_cmd(SCD40_CMD_START_PERIODIC_MEASUREMENT)
+    }
+
+    fn write_cmd(&mut self, cmd: u16) -> Result<()> {
+        self.i2c
+            .write(SCD40_I2C_ADDR, &cmd.to_be_bytes())
+            .map_err(|e| sensor_fault(format!("failed to write scd40 command: {:?}", e)))
+    }
+
+    async fn read_words<const N: usize>(&mut self, cmd: u16) -> Result<[u16; N]> {
+        self.write_cmd(cmd)?;
+        Timer::after(Duration::from_millis(1)).await;
+
+        // Each word is followed by a CRC byte.
+        let mut buf = [0u8; 9];
+        let buf = &mut buf[..N * 3];
+        self.i2c
+            .read(SCD40_I2C_ADDR, buf)
+            .map_err(|e| sensor_fault(format!("failed to read scd40 response: {:?}", e)))?;
+
+        let mut words = [0u16; N];
+        for (i, chunk) in buf.chunks(3).enumerate() {
+            if crc8(&chunk[..2]) != chunk[2] {
+                return Err(sensor_fault(format!(
+                    "scd40 response CRC mismatch for command: {:#06x}",
+                    cmd
+                )));
+            }
+
+            words[i] = u16::from_be_bytes([chunk[0], chunk[1]]);
+        }
+
+        Ok(words)
+    }
+}
+
+// Utils
+
+fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0xff_u8;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x31
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
@@ -2,19 +2,94 @@ use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use alloc::{format, vec};
+use core::fmt::Debug;
+use core::ops::DerefMut;
 
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Instant, Timer};
+#[cfg(feature = "sdcard")]
+use embedded_hal::delay::DelayNs;
+#[cfg(feature = "sdcard")]
+use embedded_hal::spi::SpiDevice;
+#[cfg(feature = "sdcard")]
+use embedded_sdmmc::{SdCard, TimeSource, Timestamp, VolumeIdx, VolumeManager};
 use embedded_storage::{ReadStorage, Storage};
+use esp_hal::efuse::Efuse;
 use esp_storage::FlashStorage;
-use serde::{Deserialize, Serialize};
+use fungi_core::flash::{FlashRecord, FlashRecordError};
+use fungi_core::mister::RhBand;
+use fungi_core::nullable;
+use fungi_core::swap::SwapCell;
+use serde::{Deserialize, Serialize, Serializer};
 use spin::RwLock;
 
 use crate::chip_control;
 use crate::chip_control::{ChipControlAction, ChipControlPublisher};
-use crate::error::{general_fault, map_embassy_pub_sub_err, Result};
+use crate::error::{
+    bad_request_err, general_fault, invalid_config_err, map_embassy_pub_sub_err,
+    map_embassy_spawn_err, Error, Result,
+};
+use crate::logger;
+use crate::logger::LogLevel;
+use crate::mister::Mode as MisterMode;
+use crate::network::api::{API_HTTP_BUFFER_LEN, API_PORT};
+use crate::network::webhook::WebhookUrl;
 
-const CONFIG_LEN_FLASH_ADDR: u32 = 0x9200;
-const CONFIG_DATA_FLASH_ADDR: u32 = 0x9202;
-const MAX_CONFIG_DATA_LEN: usize = (16_usize.pow(2) * 8) - 2; // To 0x9900
+// Length (u16) at 0x9200, followed by the data.
+const CONFIG_FLASH_RECORD: FlashRecord = FlashRecord::new(0x9200, MAX_CONFIG_DATA_LEN);
+const MAX_CONFIG_DATA_LEN: usize = (16_usize.pow(2) * 8) - 2; // To 0x9a00
+const SENSOR_I2C_KHZ_MIN: u32 = 1;
+const SENSOR_I2C_KHZ_MAX: u32 = 400;
+const DEVICE_NAME_MAX_LEN: usize = 32;
+// A week, well within the (ms) uptime the interval is compared against.
+const SENSOR_HEATER_INTERVAL_SECS_MAX: u32 = 7 * 24 * 60 * 60;
+// Plausible heater targets (always Celsius), catches a Fahrenheit value entered by mistake.
+#[cfg(feature = "heater")]
+const HEATER_TARGET_TEMP_MIN_C: f32 = 5.0;
+#[cfg(feature = "heater")]
+const HEATER_TARGET_TEMP_MAX_C: f32 = 40.0;
+// A gauge fits 6 chars (i.e. '100.0%'), so there's only room for one decimal place.
+const DISPLAY_GAUGE_DECIMALS_MAX: u8 = 1;
+static CONFIG_WRITE_POLL_MS: u64 = 250;
+// FAT 8.3 names (no long file name support).
+#[cfg(feature = "sdcard")]
+const SD_CONFIG_FILES: [(&str, SdConfigFormat); 2] = [
+    ("CONFIG.CBR", SdConfigFormat::Cbor),
+    ("CONFIG.JSN", SdConfigFormat::Json),
+];
+#[cfg(feature = "sdcard")]
+const SD_CONFIG_MAX_LEN: usize = 4096;
+
+// Built-in auto schedule programs (rh, run secs, max wait secs), see `SchedulePreset`.
+const SCHEDULE_PRESET_GENERIC: [(f32, u32, Option<u32>); 6] = [
+    (85.00, 60 * 2, Some(60 * 5)),
+    (88.00, 60 * 3, Some(60)),
+    (90.00, 60 * 4, Some(60)),
+    (92.00, 60 * 4, Some(60)),
+    (85.00, 60 * 2, Some(60 * 5)),
+    (80.00, 60 * 5, Some(60)),
+];
+// Oyster fruits best at a consistently high RH with short dips for fresh air.
+const SCHEDULE_PRESET_OYSTER: [(f32, u32, Option<u32>); 4] = [
+    (90.00, 60 * 3, Some(60 * 5)),
+    (92.00, 60 * 4, Some(60)),
+    (95.00, 60 * 4, Some(60)),
+    (88.00, 60 * 2, Some(60 * 5)),
+];
+// Shiitake prefers a lower RH with a longer drier phase.
+const SCHEDULE_PRESET_SHIITAKE: [(f32, u32, Option<u32>); 4] = [
+    (85.00, 60 * 3, Some(60 * 5)),
+    (88.00, 60 * 4, Some(60)),
+    (90.00, 60 * 3, Some(60)),
+    (80.00, 60 * 5, Some(60)),
+];
+#[cfg(all(
+    feature = "schedule-preset-oyster",
+    feature = "schedule-preset-shiitake"
+))]
+compile_error!(
+    "only one of the `schedule-preset-oyster` and `schedule-preset-shiitake` features can be set"
+);
 
 type FlashStorageArc = Arc<RwLock<FlashStorage>>;
 
@@ -26,100 +101,319 @@ macro_rules! schedule {
 
 #[derive(Clone)]
 pub(crate) struct Config {
-    instance: Arc<RwLock<Option<Arc<ConfigInstance>>>>,
+    instance: Arc<SwapCell<ConfigInstance>>,
     chip_control_pub: Arc<ChipControlPublisher>,
     flash_storage: FlashStorageArc,
+    // Update waiting out `config_write_debounce_ms` before being written to flash.
+    pending_write: Arc<RwLock<Option<PendingConfigWrite>>>,
+    // Nothing has been persisted to flash yet (fresh device or after a config reset).
+    first_boot: bool,
 }
 
 impl Config {
-    pub(crate) fn new() -> Result<Self> {
-        let mut flash_storage = Arc::new(RwLock::new(FlashStorage::new()));
-        let inst = revive_from_flash(&mut flash_storage, ConfigInstance::default())?;
+    /// Loads from flash (or defaults) unless an override (e.g. from the SD card) is given.
+    ///
+    /// A persisted config that can't be loaded (i.e. from an incompatible firmware) is reset so
+    /// the device always boots, rather than failing on it every boot.
+    pub(crate) fn new(override_inst: Option<ConfigInstance>) -> Result<Self> {
+        let flash_storage = Arc::new(RwLock::new(FlashStorage::new()));
+        let (inst, first_boot) = match override_inst {
+            Some(inst) => (inst, false),
+            None => match revive_from_flash(
+                flash_storage.write().deref_mut(),
+                ConfigInstance::default(),
+            ) {
+                Ok(Some(inst)) => (inst, false),
+                Ok(None) => (ConfigInstance::default(), true),
+                Err(e) => {
+                    log::error!("Failed to load config from flash, using defaults: {:?}", e);
+
+                    if let Err(e) = reset_config_flash(flash_storage.write().deref_mut()) {
+                        log::error!("Failed to reset config in flash: {:?}", e);
+                    }
+
+                    (ConfigInstance::default(), true)
+                }
+            },
+        };
 
         Ok(Self {
-            instance: Arc::new(RwLock::new(Some(Arc::new(inst)))),
+            instance: Arc::new(SwapCell::new(Arc::new(inst))),
             chip_control_pub: Arc::new(
                 chip_control::CHIP_CONTROL_CHANNEL
                     .publisher()
                     .map_err(map_embassy_pub_sub_err)?,
             ),
             flash_storage,
+            pending_write: Arc::new(RwLock::new(None)),
+            first_boot,
         })
     }
 
+    pub(crate) fn first_boot(&self) -> bool {
+        self.first_boot
+    }
+
     pub(crate) fn load(&self) -> Arc<ConfigInstance> {
-        self.instance
-            .read()
-            .as_ref()
-            .expect("failed to unwrap Config instance - should NEVER happen")
-            .clone()
+        self.instance.load()
     }
 
+    /// Swaps in a whole instance (a `SwapCell`, readers `load` either the old or the new one).
+    ///
+    /// Must happen before any reset is published for it, so nothing acting on the reset can
+    /// `load` the instance being replaced.
     fn update(&self, new: Arc<ConfigInstance>) -> Result<()> {
-        let _ = self.instance.write().insert(new);
+        self.instance.store(new);
 
         Ok(())
     }
 
-    pub(crate) fn apply(&self, update: MutableConfigInstance) -> Result<()> {
-        persist_to_flash(&self.flash_storage, &update)?;
+    /// Applies (and persists) an update, with debouncing the flash write (and the reset that
+    /// follows) is deferred until updates stop but the new config is live straight away.
+    ///
+    /// Fields the update leaves unset keep their current value (not the default), so a partial
+    /// update only changes what it sets (a null clears an optional field). Only changes to fields
+    /// read at boot reset the chip (`true` when one is due).
+    pub(crate) fn apply(&self, update: MutableConfigInstance) -> Result<bool> {
+        let (new, bytes) = prepare_update(&self.load(), &update)?;
+        // A reset still owed by an earlier (superseded) pending write isn't dropped.
+        let reset = requires_reset(&self.load(), &new)? || self.has_pending_reset();
 
-        let mut new = ConfigInstance::default();
-        if let Err(e) = update.populate(&mut new) {
-            let _ = reset_config_flash(&self.flash_storage);
+        let debounce_ms = self.load().config_write_debounce_ms;
+        if debounce_ms == 0 {
+            persist_to_flash(self.flash_storage.write().deref_mut(), &bytes)?;
+        } else {
+            let _ = self.pending_write.write().insert(PendingConfigWrite {
+                bytes,
+                write_at: Instant::now() + Duration::from_millis(debounce_ms as u64),
+                reset,
+            });
+        }
+
+        // The logger has no handle on the config, so it's told.
+        logger::set_level(new.log_level);
+
+        self.update(Arc::new(new))?;
+
+        // Only after the swap (see `update`), a debounced write resets from `commit` later.
+        if reset && debounce_ms == 0 {
+            self.chip_control_pub
+                .publish_immediate(ChipControlAction::Reset);
+        }
+
+        Ok(reset)
+    }
+
+    pub(crate) fn has_pending_write(&self) -> bool {
+        self.pending_write.read().is_some()
+    }
+
+    /// The pending write will reset the chip once committed.
+    pub(crate) fn has_pending_reset(&self) -> bool {
+        self.pending_write
+            .read()
+            .as_ref()
+            .is_some_and(|pending| pending.reset)
+    }
+
+    /// Writes a debounced update to flash now, `false` if there was nothing pending.
+    pub(crate) fn commit(&self) -> Result<bool> {
+        let pending = match self.pending_write.write().take() {
+            Some(pending) => pending,
+            None => return Ok(false),
+        };
+
+        if let Err(e) = persist_to_flash(self.flash_storage.write().deref_mut(), &pending.bytes) {
+            // Retried by the writer task (unless superseded by a newer update).
+            let _ = self.pending_write.write().get_or_insert(pending);
             return Err(e);
         }
 
-        self.chip_control_pub
-            .publish_immediate(ChipControlAction::Reset);
+        if pending.reset {
+            self.chip_control_pub
+                .publish_immediate(ChipControlAction::Reset);
+        }
+
+        Ok(true)
+    }
+
+    /// Replaces a single auto schedule step (then applies like a full update).
+    pub(crate) fn apply_schedule_step(&self, idx: usize, step: MisterAutoSchedule) -> Result<bool> {
+        let mut update = MutableConfigInstance::from(self.load().as_ref());
+        let schedule = update.mister_auto_schedule.get_or_insert_with(Vec::new);
+
+        match schedule.get_mut(idx) {
+            Some(existing) => *existing = step,
+            None => {
+                return Err(bad_request_err(format!(
+                    "mister auto schedule index out of range: {} (len: {})",
+                    idx,
+                    schedule.len()
+                )))
+            }
+        }
+
+        self.apply(update)
+    }
+
+    /// Replaces the sensor RH calibration adjustment (then applies like a full update).
+    pub(crate) fn apply_sensor_calibration_rh_adj(&self, adj: f32) -> Result<bool> {
+        let mut update = MutableConfigInstance::from(self.load().as_ref());
+        update.sensor_calibration_rh_adj = Some(Some(adj));
+
+        self.apply(update)
+    }
+
+    /// Replaces the auto schedule with a built-in program (then applies like a full update).
+    pub(crate) fn apply_schedule_preset(&self, preset: SchedulePreset) -> Result<bool> {
+        let mut update = MutableConfigInstance::from(self.load().as_ref());
+        update.mister_auto_schedule = Some(preset.schedule());
+
+        self.apply(update)
+    }
+
+    /// Sets the log level (then applies like a full update).
+    pub(crate) fn apply_log_level(&self, level: LogLevel) -> Result<bool> {
+        let mut update = MutableConfigInstance::from(self.load().as_ref());
+        update.log_level = Some(Some(level));
+
+        self.apply(update)
+    }
+
+    /// Updates the calibration adjustments (`None` leaves one as is), they're read every loop
+    /// so don't reset the chip themselves.
+    pub(crate) fn apply_calibration(
+        &self,
+        sensor_rh_adj: Option<f32>,
+        on_rh_adj: Option<f32>,
+        off_rh_adj: Option<f32>,
+    ) -> Result<()> {
+        let mut update = MutableConfigInstance::from(self.load().as_ref());
+        if let Some(adj) = sensor_rh_adj {
+            update.sensor_calibration_rh_adj = Some(Some(adj));
+        }
+        if let Some(adj) = on_rh_adj {
+            update.mister_auto_on_rh_adj = Some(Some(adj));
+        }
+        if let Some(adj) = off_rh_adj {
+            update.mister_auto_off_rh_adj = Some(Some(adj));
+        }
+
+        self.apply(update).map(|_| ())
+    }
+
+    /// Runs the same checks as `apply` without persisting or resetting.
+    pub(crate) fn validate(&self, update: &MutableConfigInstance) -> Result<()> {
+        prepare_update(&self.load(), update).map(|_| ())
+    }
+
+    /// The config as persisted to flash (CBOR), for a backup to `import` elsewhere.
+    pub(crate) fn export(&self) -> Result<Vec<u8>> {
+        serialize_config(&MutableConfigInstance::from(self.load().as_ref()))
+    }
+
+    /// Applies an `export` (then applies like a full update).
+    pub(crate) fn import(&self, bytes: &[u8]) -> Result<bool> {
+        let update: MutableConfigInstance = ciborium::from_reader(bytes)
+            .map_err(|e| bad_request_err(format!("failed to parse config CBOR: {:?}", e)))?;
+
+        self.apply(update)
+    }
 
-        self.update(Arc::new(new))
+    pub(crate) fn flash_usage(&self) -> Result<ConfigFlashUsage> {
+        let bytes = serialize_config(&MutableConfigInstance::from(self.load().as_ref()))?;
+
+        Ok(ConfigFlashUsage {
+            used_bytes: bytes.len(),
+            max_bytes: MAX_CONFIG_DATA_LEN,
+        })
     }
 
     pub(crate) fn reset(&self) -> Result<()> {
-        reset_config_flash(&self.flash_storage)?;
+        // Would otherwise be written over the reset config.
+        let _ = self.pending_write.write().take();
+        reset_config_flash(self.flash_storage.write().deref_mut())?;
+
+        self.update(Arc::new(ConfigInstance::default()))?;
 
         self.chip_control_pub
             .publish_immediate(ChipControlAction::Reset);
 
-        self.update(Arc::new(ConfigInstance::default()))
+        Ok(())
     }
 }
 
-fn revive_from_flash(
-    flash_storage: &FlashStorageArc,
-    mut inst: ConfigInstance,
-) -> Result<ConfigInstance> {
-    let mut bytes = [0u8; 2];
-    let mut storage = flash_storage.write();
+pub(crate) fn init(cfg: Config, spawner: &Spawner) -> Result<()> {
+    spawner
+        .spawn(config_write_task(cfg))
+        .map_err(map_embassy_spawn_err)
+}
+
+/// Commits debounced updates once they've been quiet for `config_write_debounce_ms`.
+#[embassy_executor::task]
+async fn config_write_task(cfg: Config) {
+    loop {
+        Timer::after(Duration::from_millis(CONFIG_WRITE_POLL_MS)).await;
 
-    // Read config length
-    storage
-        .read(CONFIG_LEN_FLASH_ADDR, &mut bytes)
-        .map_err(|e| {
-            general_fault(format!(
-                "Failed to load config len field from flash storage: {:?}",
-                e
-            ))
-        })?;
+        let due = match cfg.pending_write.read().as_ref() {
+            Some(pending) => pending.write_at <= Instant::now(),
+            None => false,
+        };
 
-    let len = u16::from_be_bytes(bytes);
-    if len == u16::MAX {
-        // No persisted config.
-        return Ok(inst);
+        if due {
+            if let Err(e) = cfg.commit() {
+                log::warn!("Failed to commit debounced config write: {:?}", e);
+            }
+        }
     }
+}
 
-    let mut bytes = vec![0u8; len as usize];
+/// Merges `update` over `current`, the result is persisted whole so the flash copy carries the
+/// earlier values too.
+fn prepare_update(
+    current: &ConfigInstance,
+    update: &MutableConfigInstance,
+) -> Result<(ConfigInstance, Vec<u8>)> {
+    let mut new = current.clone();
+    update.clone().populate(&mut new)?;
+    new.validate()?;
 
-    // Read config data
-    storage
-        .read(CONFIG_DATA_FLASH_ADDR, &mut bytes)
-        .map_err(|e| {
-            general_fault(format!(
-                "Failed to load config data field from flash storage: {:?}",
-                e
-            ))
-        })?;
+    // Check the size up front so nothing is written for an oversized config.
+    let bytes = serialize_config(&MutableConfigInstance::from(&new))?;
+    if bytes.len() > MAX_CONFIG_DATA_LEN {
+        return Err(invalid_config_err(format!(
+            "schedule too large: {} of {} bytes",
+            bytes.len(),
+            MAX_CONFIG_DATA_LEN
+        )));
+    }
+
+    Ok((new, bytes))
+}
+
+/// Whether `new` changes a field that's only read at boot, anything not listed as read live in
+/// `MutableConfigInstance::with_live_from` counts (so a new field resets until classified).
+fn requires_reset(current: &ConfigInstance, new: &ConfigInstance) -> Result<bool> {
+    let current = MutableConfigInstance::from(current);
+    let new = MutableConfigInstance::from(new).with_live_from(&current);
+
+    Ok(serialize_config(&new)? != serialize_config(&current)?)
+}
+
+// The flash helpers are generic over the storage traits so they aren't tied to `FlashStorage`.
+
+/// `None` when no config has been persisted (i.e. first boot).
+fn revive_from_flash<S>(storage: &mut S, mut inst: ConfigInstance) -> Result<Option<ConfigInstance>>
+where
+    S: ReadStorage,
+    S::Error: Debug,
+{
+    let Some(bytes) = CONFIG_FLASH_RECORD
+        .read(storage)
+        .map_err(|e| map_config_flash_err("load", e))?
+    else {
+        return Ok(None);
+    };
 
     log::info!("Loaded config data from flash [{} bytes]", bytes.len());
 
@@ -131,13 +425,105 @@ fn revive_from_flash(
     })?;
 
     data.populate(&mut inst)?;
-    Ok(inst)
+    Ok(Some(inst))
+}
+
+/// Loads a config override from the SD card (`None` falls back to flash).
+///
+/// A missing card/file or invalid config is logged and ignored.
+#[cfg(feature = "sdcard")]
+pub(crate) fn revive_from_sd<S, D>(spi: S, delay: D) -> Option<ConfigInstance>
+where
+    S: SpiDevice<u8>,
+    D: DelayNs,
+{
+    let data = match read_sd_config(spi, delay) {
+        Ok(Some(data)) => data,
+        Ok(None) => {
+            log::info!("No config found on SD card, using flash");
+            return None;
+        }
+        Err(e) => {
+            log::info!("Unable to read config from SD card, using flash: {:?}", e);
+            return None;
+        }
+    };
+
+    let mut inst = ConfigInstance::default();
+    if let Err(e) = data.populate(&mut inst).and_then(|_| inst.validate()) {
+        log::warn!("Ignoring invalid config on SD card: {:?}", e);
+        return None;
+    }
+
+    log::info!("Using config from SD card (overrides flash)");
+
+    Some(inst)
 }
 
-fn persist_to_flash(
-    flash_storage: &FlashStorageArc,
-    mutable_cfg: &MutableConfigInstance,
-) -> Result<()> {
+#[cfg(feature = "sdcard")]
+fn read_sd_config<S, D>(spi: S, delay: D) -> Result<Option<MutableConfigInstance>>
+where
+    S: SpiDevice<u8>,
+    D: DelayNs,
+{
+    let mut volume_mgr = VolumeManager::new(SdCard::new(spi, delay), SdNoTimeSource);
+    let mut volume = volume_mgr
+        .open_volume(VolumeIdx(0))
+        .map_err(|e| general_fault(format!("failed to open SD card volume: {:?}", e)))?;
+    let mut root_dir = volume
+        .open_root_dir()
+        .map_err(|e| general_fault(format!("failed to open SD card root dir: {:?}", e)))?;
+
+    for (name, format) in SD_CONFIG_FILES {
+        let mut file = match root_dir.open_file_in_dir(name, embedded_sdmmc::Mode::ReadOnly) {
+            Ok(file) => file,
+            Err(embedded_sdmmc::Error::NotFound) => continue,
+            Err(e) => {
+                return Err(general_fault(format!(
+                    "failed to open '{}' on SD card: {:?}",
+                    name, e
+                )))
+            }
+        };
+
+        let len = file.length() as usize;
+        if len > SD_CONFIG_MAX_LEN {
+            return Err(invalid_config_err(format!(
+                "'{}' on SD card too large: {} of {} bytes",
+                name, len, SD_CONFIG_MAX_LEN
+            )));
+        }
+
+        let mut bytes = vec![0u8; len];
+        let mut read = 0;
+        while !file.is_eof() && read < len {
+            read += file
+                .read(&mut bytes[read..])
+                .map_err(|e| general_fault(format!("failed to read '{}': {:?}", name, e)))?;
+        }
+
+        log::info!(
+            "Loaded config data from SD card '{}' [{} bytes]",
+            name,
+            read
+        );
+
+        let data: MutableConfigInstance = match format {
+            SdConfigFormat::Cbor => ciborium::from_reader(&bytes[..read]).map_err(|e| {
+                invalid_config_err(format!("failed to deserialize '{}': {:?}", name, e))
+            })?,
+            SdConfigFormat::Json => serde_json::from_slice(&bytes[..read]).map_err(|e| {
+                invalid_config_err(format!("failed to deserialize '{}': {:?}", name, e))
+            })?,
+        };
+
+        return Ok(Some(data));
+    }
+
+    Ok(None)
+}
+
+fn serialize_config(mutable_cfg: &MutableConfigInstance) -> Result<Vec<u8>> {
     let mut bytes = Vec::new();
     ciborium::into_writer(mutable_cfg, &mut bytes).map_err(|e| {
         general_fault(format!(
@@ -146,16 +532,17 @@ fn persist_to_flash(
         ))
     })?;
 
-    if bytes.len() > MAX_CONFIG_DATA_LEN {
-        return Err(general_fault(format!(
-            "Failed to serialize config data read for storage - max bytes exceeded: '{}' > '{}'",
-            bytes.len(),
-            MAX_CONFIG_DATA_LEN
-        )));
-    }
+    Ok(bytes)
+}
 
-    write_config_len_to_flash(flash_storage, bytes.len() as u16)?;
-    write_config_data_to_flash(flash_storage, &bytes)?;
+fn persist_to_flash<S>(storage: &mut S, bytes: &[u8]) -> Result<()>
+where
+    S: Storage,
+    S::Error: Debug,
+{
+    CONFIG_FLASH_RECORD
+        .write(storage, bytes)
+        .map_err(|e| map_config_flash_err("write", e))?;
 
     log::info!(
         "Wrote config data to flash [{} bytes of {} max]",
@@ -166,54 +553,136 @@ fn persist_to_flash(
     Ok(())
 }
 
-fn reset_config_flash(flash_storage: &FlashStorageArc) -> Result<()> {
-    write_config_len_to_flash(flash_storage, u16::MAX)
+fn reset_config_flash<S>(storage: &mut S) -> Result<()>
+where
+    S: Storage,
+    S::Error: Debug,
+{
+    CONFIG_FLASH_RECORD
+        .clear(storage)
+        .map_err(|e| map_config_flash_err("reset", e))
 }
 
-fn write_config_len_to_flash(flash_storage: &FlashStorageArc, cfg_len: u16) -> Result<()> {
-    let mut flash_storage = flash_storage.write();
-
-    flash_storage
-        .write(CONFIG_LEN_FLASH_ADDR, cfg_len.to_be_bytes().as_ref())
-        .map_err(|e| {
-            general_fault(format!(
-                "Failed to write config len field to flash storage: {:?}",
-                e
-            ))
-        })
+fn map_config_flash_err<E: Debug>(action: &str, e: FlashRecordError<E>) -> Error {
+    match e {
+        FlashRecordError::Storage(e) => general_fault(format!(
+            "Failed to {} config in flash storage: {:?}",
+            action, e
+        )),
+        FlashRecordError::TooLarge { len, max_len } => invalid_config_err(format!(
+            "config too large for flash: {} of {} bytes",
+            len, max_len
+        )),
+    }
 }
 
-fn write_config_data_to_flash(flash_storage: &FlashStorageArc, cfg_data: &[u8]) -> Result<()> {
-    let mut flash_storage = flash_storage.write();
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct ConfigFlashUsage {
+    pub(crate) used_bytes: usize,
+    pub(crate) max_bytes: usize,
+}
 
-    flash_storage
-        .write(CONFIG_DATA_FLASH_ADDR, cfg_data)
-        .map_err(|e| {
-            general_fault(format!(
-                "Failed to write config data field to flash storage: {:?}",
-                e
-            ))
-        })
+struct PendingConfigWrite {
+    bytes: Vec<u8>,
+    write_at: Instant,
+    // Only updates that aren't live need the reset once written.
+    reset: bool,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Serialize)]
 pub(crate) struct ConfigInstance {
+    pub(crate) device_name: Option<String>,
     pub(crate) wifi_ssid: String,
+    #[serde(serialize_with = "serialize_masked")]
     pub(crate) wifi_password: String,
+    pub(crate) wifi_eap_identity: Option<String>,
+    pub(crate) wifi_eap_username: Option<String>,
+    #[serde(serialize_with = "serialize_masked_opt")]
+    pub(crate) wifi_eap_password: Option<String>,
+    pub(crate) wifi_reconnect_max_ms: u32,
+    pub(crate) webhook_url: Option<String>,
+    pub(crate) telemetry_port: Option<u16>,
     pub(crate) display_enabled: bool,
+    pub(crate) display_contrast: u8,
+    pub(crate) display_invert: bool,
+    pub(crate) display_mirror_enabled: bool,
+    pub(crate) display_min_redraw_ms: u32,
+    pub(crate) display_status_hold_ms: u32,
+    pub(crate) display_temp_decimals: u8,
+    pub(crate) display_rh_decimals: u8,
+    pub(crate) display_mode_label: bool,
+    pub(crate) temp_unit: TemperatureUnit,
     pub(crate) network_enabled: bool,
     pub(crate) sensor_enabled: bool,
+    pub(crate) status_led_enabled: bool,
+    pub(crate) status_led_fault_blink_ms: u32,
     pub(crate) sensor_driver: SensorDriver,
+    pub(crate) sensor_i2c_khz: u32,
+    pub(crate) sensor_precision: SensorPrecision,
     pub(crate) sensor_delay_ms: u32,
+    pub(crate) sensor_delay_max_ms: u32,
     pub(crate) sensor_delay_err_ms: u32,
+    pub(crate) sensor_delay_err_max_ms: u32,
+    pub(crate) sensor_read_timeout_ms: u32,
+    pub(crate) sensor_warmup_reads: u8,
+    pub(crate) sensor_temp_min: f32,
+    pub(crate) sensor_temp_max: f32,
+    pub(crate) sensor_heater_interval_secs: u32,
     pub(crate) sensor_calibration_rh_adj: Option<f32>,
+    #[cfg(feature = "mock-sensor")]
+    pub(crate) sensor_mock_rh_mid: f32,
+    #[cfg(feature = "mock-sensor")]
+    pub(crate) sensor_mock_rh_amplitude: f32,
+    #[cfg(feature = "mock-sensor")]
+    pub(crate) sensor_mock_temp_mid: f32,
+    #[cfg(feature = "mock-sensor")]
+    pub(crate) sensor_mock_temp_amplitude: f32,
+    #[cfg(feature = "mock-sensor")]
+    pub(crate) sensor_mock_period_secs: u32,
+    #[cfg(feature = "scd40")]
+    pub(crate) co2_enabled: bool,
+    #[cfg(feature = "scd40")]
+    pub(crate) co2_delay_ms: u32,
+    #[cfg(feature = "scd40")]
+    pub(crate) co2_delay_err_ms: u32,
     pub(crate) controls_min_press_ms: u32,
     pub(crate) controls_min_hold_ms: u32,
+    pub(crate) controls_debounce_ms: u32,
+    pub(crate) controls_button_active_low: bool,
+    #[cfg(feature = "mist-trigger")]
+    pub(crate) controls_mist_trigger_secs: u32,
+    #[cfg(feature = "reset-button")]
+    pub(crate) controls_factory_reset_hold_ms: u32,
+    #[cfg(feature = "mister-soft-start")]
+    pub(crate) mister_soft_start_ms: u32,
     pub(crate) mister_auto_schedule: Vec<MisterAutoSchedule>,
+    pub(crate) mister_auto_schedule_loop: bool,
     pub(crate) mister_auto_on_rh_adj: Option<f32>,
     pub(crate) mister_auto_off_rh_adj: Option<f32>,
     pub(crate) mister_auto_duration_min_ms: u32,
+    pub(crate) mister_rh_hard_floor: Option<f32>,
+    pub(crate) mister_auto_prime_secs: u32,
+    pub(crate) mister_burst_on_ms: u32,
+    pub(crate) mister_burst_off_ms: u32,
+    pub(crate) mister_startup_delay_ms: u32,
+    pub(crate) auto_fault_fallback_count: u32,
+    pub(crate) auto_metrics_fault_grace_ms: u32,
+    #[cfg(feature = "heater")]
+    pub(crate) heater_temp_hysteresis: f32,
+    #[cfg(feature = "buzzer")]
+    pub(crate) alarm_delay_ms: u32,
+    #[cfg(feature = "ldr")]
+    pub(crate) display_ldr_dark_threshold: u16,
+    pub(crate) persist_mode: bool,
+    pub(crate) default_mode: MisterMode,
     pub(crate) reset_wait_secs: u32,
+    pub(crate) safe_mode_boot_count: u8,
+    pub(crate) config_write_debounce_ms: u32,
+    pub(crate) log_level: Option<LogLevel>,
+    pub(crate) api_max_body_bytes: usize,
+    pub(crate) api_rate_limit_burst: u32,
+    pub(crate) api_rate_limit_refill_ms: u32,
+    pub(crate) api_keep_alive_secs: u32,
 }
 
 impl ConfigInstance {
@@ -239,113 +708,705 @@ impl ConfigInstance {
             None => rh,
         }
     }
+
+    pub(crate) fn mister_burst_enabled(&self) -> bool {
+        self.mister_burst_on_ms > 0 && self.mister_burst_off_ms > 0
+    }
+
+    /// The configured device name, or one derived from the MAC (i.e. 'fungi-a1b2c3').
+    pub(crate) fn device_name(&self) -> String {
+        match self.device_name.as_deref().filter(|name| !name.is_empty()) {
+            Some(name) => name.to_string(),
+            None => {
+                let mac = Efuse::read_base_mac_address();
+                format!("fungi-{:02x}{:02x}{:02x}", mac[3], mac[4], mac[5])
+            }
+        }
+    }
+
+    pub(crate) fn wifi_eap_enabled(&self) -> bool {
+        self.wifi_eap_username.is_some() && self.wifi_eap_password.is_some()
+    }
+
+    /// Total run time of the whole auto schedule program.
+    pub(crate) fn mister_auto_schedule_total_secs(&self) -> u32 {
+        self.mister_auto_schedule.iter().map(|s| s.run_secs).sum()
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        if let Some(name) = self.device_name.as_deref().filter(|name| !name.is_empty()) {
+            if name.len() > DEVICE_NAME_MAX_LEN
+                || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            {
+                return Err(invalid_config_err(format!(
+                    "device_name must be at most {} letters, digits or '-' (got '{}')",
+                    DEVICE_NAME_MAX_LEN, name
+                )));
+            }
+        }
+
+        if self.sensor_i2c_khz < SENSOR_I2C_KHZ_MIN || self.sensor_i2c_khz > SENSOR_I2C_KHZ_MAX {
+            return Err(invalid_config_err(format!(
+                "sensor_i2c_khz must be between {} and {} (got {})",
+                SENSOR_I2C_KHZ_MIN, SENSOR_I2C_KHZ_MAX, self.sensor_i2c_khz
+            )));
+        }
+
+        if self.sensor_heater_interval_secs > SENSOR_HEATER_INTERVAL_SECS_MAX {
+            return Err(invalid_config_err(format!(
+                "sensor_heater_interval_secs must be at most {} (got {})",
+                SENSOR_HEATER_INTERVAL_SECS_MAX, self.sensor_heater_interval_secs
+            )));
+        }
+
+        if self.sensor_temp_min >= self.sensor_temp_max {
+            return Err(invalid_config_err(format!(
+                "sensor_temp_min must be less than sensor_temp_max (got {} and {})",
+                self.sensor_temp_min, self.sensor_temp_max
+            )));
+        }
+
+        if self.display_temp_decimals > DISPLAY_GAUGE_DECIMALS_MAX
+            || self.display_rh_decimals > DISPLAY_GAUGE_DECIMALS_MAX
+        {
+            return Err(invalid_config_err(format!(
+                "display decimals must be at most {} (got temp: {}, rh: {})",
+                DISPLAY_GAUGE_DECIMALS_MAX, self.display_temp_decimals, self.display_rh_decimals
+            )));
+        }
+
+        if !self.sensor_driver.is_available() {
+            return Err(invalid_config_err(format!(
+                "sensor_driver {:?} is not supported by this firmware (available: {:?})",
+                self.sensor_driver,
+                SensorDriver::available()
+            )));
+        }
+
+        if self.mister_auto_schedule.is_empty() {
+            return Err(invalid_config_err(
+                "mister auto schedule must have at least one entry".to_string(),
+            ));
+        }
+
+        for (idx, sched) in self.mister_auto_schedule.iter().enumerate() {
+            if !(0.0..=100.0).contains(&sched.rh) {
+                return Err(invalid_config_err(format!(
+                    "mister auto schedule '{}' has an out of range RH: {}",
+                    idx, sched.rh
+                )));
+            }
+
+            #[cfg(feature = "heater")]
+            if let Some(target_temp) = sched.target_temp {
+                if !(HEATER_TARGET_TEMP_MIN_C..=HEATER_TARGET_TEMP_MAX_C).contains(&target_temp) {
+                    return Err(invalid_config_err(format!(
+                        "mister auto schedule '{}' has an implausible target temp: {} \
+                        (always Celsius regardless of temp_unit, must be between {} and {})",
+                        idx, target_temp, HEATER_TARGET_TEMP_MIN_C, HEATER_TARGET_TEMP_MAX_C
+                    )));
+                }
+            }
+
+            #[cfg(feature = "heater")]
+            if sched.target_temp.is_some() && sched.temp_hysteresis(self) <= 0.0 {
+                return Err(invalid_config_err(format!(
+                    "mister auto schedule '{}' has a non-positive temp hysteresis: {}",
+                    idx,
+                    sched.temp_hysteresis(self)
+                )));
+            }
+
+            let rh_on = sched.on_rh(self);
+            let rh_off = sched.off_rh(self);
+
+            if RhBand::new(rh_on, rh_off).is_inverted() {
+                return Err(invalid_config_err(format!(
+                    "mister auto schedule '{}' has an inverted RH band (on: {} >= off: {}), \
+                    check the on/off RH adjustments",
+                    idx, rh_on, rh_off
+                )));
+            }
+        }
+
+        if let Some(url) = self.webhook_url.as_deref().filter(|url| !url.is_empty()) {
+            WebhookUrl::parse(url)?;
+        }
+
+        if self.telemetry_port == Some(API_PORT) {
+            return Err(invalid_config_err(format!(
+                "telemetry port {} is already used by the API",
+                API_PORT
+            )));
+        }
+
+        Ok(())
+    }
 }
 
 impl Default for ConfigInstance {
     fn default() -> Self {
         Self {
+            // Identifies the unit in logs and the API, derived from the MAC when unset.
+            device_name: None,
             wifi_ssid: env!("SSID").to_string(),
             wifi_password: env!("PASSWORD").to_string(),
+            // WPA2-Enterprise (PEAP/TTLS) is used instead of PSK when a username and password
+            // are given, the identity falls back to the username.
+            wifi_eap_identity: option_env!("EAP_IDENTITY").map(|v| v.to_string()),
+            wifi_eap_username: option_env!("EAP_USERNAME").map(|v| v.to_string()),
+            wifi_eap_password: option_env!("EAP_PASSWORD").map(|v| v.to_string()),
+            // Failed connection attempts back off exponentially up to this.
+            wifi_reconnect_max_ms: 120000,
+            // POSTed to on mode/status changes (`http://<ipv4>[:port][/path]`).
+            webhook_url: None,
+            // Streams each sensor reading as a JSON line to a single TCP client, 0 disables.
+            telemetry_port: None,
             display_enabled: true,
+            display_contrast: u8::MAX,
+            display_invert: false,
+            // Second panel on the display bus (at the alternate address), showing the same screen.
+            display_mirror_enabled: false,
+            // Changes within this window are coalesced into the next redraw.
+            display_min_redraw_ms: 100,
+            // The mister status is only shown once stable for this long (0 shows immediately).
+            display_status_hold_ms: 0,
+            // Decimal places shown on the gauges (whole degrees are rounded up).
+            display_temp_decimals: 0,
+            display_rh_decimals: 1,
+            // Labels the manual (on/off) modes beside the mister status, as auto is.
+            display_mode_label: false,
+            // API output only, everything internal (and every temp in the config, i.e. heater
+            // targets) stays in Celsius.
+            temp_unit: TemperatureUnit::default(),
             network_enabled: true,
             sensor_enabled: true,
+            status_led_enabled: true,
+            status_led_fault_blink_ms: 400,
             sensor_driver: SensorDriver::default(),
+            sensor_i2c_khz: 1,
+            // Measurement repeatability (SHT40 only), lower is quicker with less self-heating.
+            sensor_precision: SensorPrecision::High,
             sensor_delay_ms: 500,
+            // Backoff limit while readings are stable (equal to the base disables it).
+            sensor_delay_max_ms: 500,
             sensor_delay_err_ms: 10000,
+            // Backoff limit while reads keep failing (the device is recreated after each),
+            // doubling from `sensor_delay_err_ms` plus some jitter.
+            sensor_delay_err_max_ms: 60000,
+            // Reads taking longer than this are discarded (and count as a failed attempt).
+            sensor_read_timeout_ms: 1000,
+            sensor_warmup_reads: 0,
+            // Plausible temp range (always Celsius, the sensors' rated range), a reading outside
+            // it counts as a failed attempt.
+            sensor_temp_min: -40.0,
+            sensor_temp_max: 85.0,
+            // Heater (de-fogging) cycle, 0 disables (HDC1080 only).
+            sensor_heater_interval_secs: 0,
             // Adjust for SHT45 which seems to be way higher than the others.
             sensor_calibration_rh_adj: Some(5.0),
+            // Mock readings drift along a sine wave (mid +/- amplitude) over the period.
+            #[cfg(feature = "mock-sensor")]
+            sensor_mock_rh_mid: 88.0,
+            #[cfg(feature = "mock-sensor")]
+            sensor_mock_rh_amplitude: 6.0,
+            #[cfg(feature = "mock-sensor")]
+            sensor_mock_temp_mid: 22.0,
+            #[cfg(feature = "mock-sensor")]
+            sensor_mock_temp_amplitude: 1.0,
+            #[cfg(feature = "mock-sensor")]
+            sensor_mock_period_secs: 60 * 10,
+            #[cfg(feature = "scd40")]
+            co2_enabled: true,
+            // SCD40 only produces a new measurement every 5 seconds.
+            #[cfg(feature = "scd40")]
+            co2_delay_ms: 5000,
+            #[cfg(feature = "scd40")]
+            co2_delay_err_ms: 10000,
             controls_min_press_ms: 100,
             controls_min_hold_ms: 500,
-            mister_auto_schedule: vec![
-                schedule![85.00, 60 * 2, Some(60 * 5)],
-                schedule![88.00, 60 * 3, Some(60)],
-                schedule![90.00, 60 * 4, Some(60)],
-                schedule![92.00, 60 * 4, Some(60)],
-                schedule![85.00, 60 * 2, Some(60 * 5)],
-                schedule![80.00, 60 * 5, Some(60)],
-            ],
+            controls_debounce_ms: 250,
+            // Button wired to ground with a pull-up (instead of to 3.3V with a pull-down).
+            controls_button_active_low: false,
+            // Length of the manual run started by the mist trigger input.
+            #[cfg(feature = "mist-trigger")]
+            controls_mist_trigger_secs: 30,
+            // Holding the reset button this long restores the default config (shorter resets).
+            #[cfg(feature = "reset-button")]
+            controls_factory_reset_hold_ms: 10000,
+            // Duty ramp (0 to 100%) when switching the mister on, 0 switches straight to full.
+            #[cfg(feature = "mister-soft-start")]
+            mister_soft_start_ms: 1000,
+            // Generic unless a `schedule-preset-*` feature selects another.
+            mister_auto_schedule: SchedulePreset::DEFAULT.schedule(),
+            // When false the program holds on the last step rather than wrapping.
+            mister_auto_schedule_loop: true,
             mister_auto_on_rh_adj: Some(-0.5),
             mister_auto_off_rh_adj: Some(0.5),
             mister_auto_duration_min_ms: 10000,
+            // Safety net, Auto forces 'On' below this rh regardless of the schedule band.
+            mister_rh_hard_floor: None,
+            // Run on switching into Auto (unless the rh is already above the step's on threshold)
+            // to reach it sooner, 0 disables.
+            mister_auto_prime_secs: 0,
+            // Cycle the pin while 'On' (e.g. 5000/15000), either being 0 means continuous.
+            mister_burst_on_ms: 0,
+            mister_burst_off_ms: 0,
+            // The pin is held 'Off' for this long at boot before the restored mode is applied.
+            mister_startup_delay_ms: 500,
+            // Consecutive sensor faults in Auto before dropping to (persisted) 'Off', 0 disables.
+            auto_fault_fallback_count: 0,
+            // Missing metrics in Auto hold the current status this long before faulting (riding
+            // out a sensor blip), 0 faults straight away.
+            auto_metrics_fault_grace_ms: 0,
+            #[cfg(feature = "heater")]
+            heater_temp_hysteresis: 0.5,
+            // A 'Fault' status (or no sensor reading) lasting this long sounds the buzzer.
+            #[cfg(feature = "buzzer")]
+            alarm_delay_ms: 60000,
+            // Raw (12 bit) LDR reading below which the panel is switched off (an LDR from 3V3 to
+            // GPIO34, with 10K to GND, reads lower in the dark).
+            #[cfg(feature = "ldr")]
+            display_ldr_dark_threshold: 300,
+            // Restore the mode after a reset, when off the mode is RAM only (booting into
+            // `default_mode`).
+            persist_mode: true,
+            // Booted into when there's no restored mode (i.e. fresh flash or not persisting).
+            default_mode: MisterMode::Auto,
             reset_wait_secs: 5,
+            // Boots in a row that didn't stay up for a minute (excluding our own resets) before
+            // booting into safe mode (network and API only, no mister control), 0 disables.
+            safe_mode_boot_count: 5,
+            // Updates are live immediately but only written to flash (then reset) once no more
+            // have arrived for this long, 0 writes every update straight away.
+            config_write_debounce_ms: 0,
+            // Overrides the build time level (`ESP_LOGLEVEL`), unset keeps it.
+            log_level: None,
+            // Must leave room for the headers in the http buffer (a quarter of it).
+            api_max_body_bytes: API_HTTP_BUFFER_LEN / 4 * 3,
+            // Mutating requests allowed back to back, then one more per refill interval
+            // (0 disables the limit).
+            api_rate_limit_burst: 10,
+            api_rate_limit_refill_ms: 1000,
+            // Idle time a client's connection is kept open for its next request, 0 closes after
+            // every response. There's a single worker, so a kept-alive client holds off others.
+            api_keep_alive_secs: 0,
         }
     }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub(crate) struct MutableConfigInstance {
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub(crate) device_name: Option<Option<String>>,
+    pub(crate) display_contrast: Option<u8>,
+    pub(crate) display_invert: Option<bool>,
+    pub(crate) display_mirror_enabled: Option<bool>,
+    pub(crate) display_temp_decimals: Option<u8>,
+    pub(crate) display_rh_decimals: Option<u8>,
+    pub(crate) display_mode_label: Option<bool>,
+    pub(crate) temp_unit: Option<TemperatureUnit>,
     pub(crate) sensor_driver: Option<SensorDriver>,
-    pub(crate) sensor_calibration_rh_adj: Option<f32>,
+    pub(crate) sensor_i2c_khz: Option<u32>,
+    pub(crate) sensor_precision: Option<SensorPrecision>,
+    pub(crate) sensor_heater_interval_secs: Option<u32>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub(crate) sensor_calibration_rh_adj: Option<Option<f32>>,
     pub(crate) mister_auto_schedule: Option<Vec<MisterAutoSchedule>>,
-    pub(crate) mister_auto_on_rh_adj: Option<f32>,
-    pub(crate) mister_auto_off_rh_adj: Option<f32>,
+    pub(crate) mister_auto_schedule_loop: Option<bool>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub(crate) mister_auto_on_rh_adj: Option<Option<f32>>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub(crate) mister_auto_off_rh_adj: Option<Option<f32>>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub(crate) mister_rh_hard_floor: Option<Option<f32>>,
+    pub(crate) mister_auto_prime_secs: Option<u32>,
+    pub(crate) auto_fault_fallback_count: Option<u32>,
+    pub(crate) auto_metrics_fault_grace_ms: Option<u32>,
+    pub(crate) default_mode: Option<MisterMode>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub(crate) webhook_url: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub(crate) telemetry_port: Option<Option<u16>>,
+    pub(crate) config_write_debounce_ms: Option<u32>,
+    #[serde(default, deserialize_with = "nullable::deserialize")]
+    pub(crate) log_level: Option<Option<LogLevel>>,
 }
 
 impl MutableConfigInstance {
     #[allow(dead_code)]
     pub(crate) fn new() -> Self {
         Self {
+            device_name: None,
+            display_contrast: None,
+            display_invert: None,
+            display_mirror_enabled: None,
+            display_temp_decimals: None,
+            display_rh_decimals: None,
+            display_mode_label: None,
+            temp_unit: None,
             sensor_driver: None,
+            sensor_i2c_khz: None,
+            sensor_precision: None,
+            sensor_heater_interval_secs: None,
             sensor_calibration_rh_adj: None,
             mister_auto_schedule: None,
+            mister_auto_schedule_loop: None,
             mister_auto_on_rh_adj: None,
             mister_auto_off_rh_adj: None,
+            mister_rh_hard_floor: None,
+            mister_auto_prime_secs: None,
+            auto_fault_fallback_count: None,
+            auto_metrics_fault_grace_ms: None,
+            default_mode: None,
+            webhook_url: None,
+            telemetry_port: None,
+            config_write_debounce_ms: None,
+            log_level: None,
         }
     }
 
     pub(crate) fn populate(mut self, cfg: &mut ConfigInstance) -> Result<()> {
+        nullable::merge(&mut cfg.device_name, self.device_name.take());
+        if let Some(val) = self.display_contrast.take() {
+            cfg.display_contrast = val;
+        }
+        if let Some(val) = self.display_invert.take() {
+            cfg.display_invert = val;
+        }
+        if let Some(val) = self.display_mirror_enabled.take() {
+            cfg.display_mirror_enabled = val;
+        }
+        if let Some(val) = self.display_temp_decimals.take() {
+            cfg.display_temp_decimals = val;
+        }
+        if let Some(val) = self.display_rh_decimals.take() {
+            cfg.display_rh_decimals = val;
+        }
+        if let Some(val) = self.display_mode_label.take() {
+            cfg.display_mode_label = val;
+        }
+        if let Some(val) = self.temp_unit.take() {
+            cfg.temp_unit = val;
+        }
         if let Some(val) = self.sensor_driver.take() {
             cfg.sensor_driver = val;
         }
-        if let Some(val) = self.sensor_calibration_rh_adj.take() {
-            cfg.sensor_calibration_rh_adj = Some(val);
+        if let Some(val) = self.sensor_i2c_khz.take() {
+            cfg.sensor_i2c_khz = val;
+        }
+        if let Some(val) = self.sensor_precision.take() {
+            cfg.sensor_precision = val;
+        }
+        if let Some(val) = self.sensor_heater_interval_secs.take() {
+            cfg.sensor_heater_interval_secs = val;
         }
+        nullable::merge(
+            &mut cfg.sensor_calibration_rh_adj,
+            self.sensor_calibration_rh_adj.take(),
+        );
         if let Some(val) = self.mister_auto_schedule.take() {
             cfg.mister_auto_schedule = val;
         }
-        if let Some(val) = self.mister_auto_on_rh_adj.take() {
-            cfg.mister_auto_on_rh_adj = Some(val);
+        if let Some(val) = self.mister_auto_schedule_loop.take() {
+            cfg.mister_auto_schedule_loop = val;
         }
-        if let Some(val) = self.mister_auto_off_rh_adj.take() {
-            cfg.mister_auto_off_rh_adj = Some(val);
+        nullable::merge(
+            &mut cfg.mister_auto_on_rh_adj,
+            self.mister_auto_on_rh_adj.take(),
+        );
+        nullable::merge(
+            &mut cfg.mister_auto_off_rh_adj,
+            self.mister_auto_off_rh_adj.take(),
+        );
+        nullable::merge(
+            &mut cfg.mister_rh_hard_floor,
+            self.mister_rh_hard_floor.take(),
+        );
+        if let Some(val) = self.mister_auto_prime_secs.take() {
+            cfg.mister_auto_prime_secs = val;
         }
+        if let Some(val) = self.auto_fault_fallback_count.take() {
+            cfg.auto_fault_fallback_count = val;
+        }
+        if let Some(val) = self.auto_metrics_fault_grace_ms.take() {
+            cfg.auto_metrics_fault_grace_ms = val;
+        }
+        if let Some(val) = self.default_mode.take() {
+            cfg.default_mode = val;
+        }
+        nullable::merge(&mut cfg.webhook_url, self.webhook_url.take());
+        nullable::merge(&mut cfg.telemetry_port, self.telemetry_port.take());
+        if let Some(val) = self.config_write_debounce_ms.take() {
+            cfg.config_write_debounce_ms = val;
+        }
+        nullable::merge(&mut cfg.log_level, self.log_level.take());
 
         Ok(())
     }
+
+    /// Takes the fields read live (every loop or request) from `other`, leaving those that need
+    /// a reset to apply.
+    fn with_live_from(mut self, other: &MutableConfigInstance) -> Self {
+        let other = other.clone();
+
+        self.display_temp_decimals = other.display_temp_decimals;
+        self.display_rh_decimals = other.display_rh_decimals;
+        self.display_mode_label = other.display_mode_label;
+        self.temp_unit = other.temp_unit;
+        self.sensor_heater_interval_secs = other.sensor_heater_interval_secs;
+        self.sensor_calibration_rh_adj = other.sensor_calibration_rh_adj;
+        self.mister_auto_schedule = other.mister_auto_schedule;
+        self.mister_auto_schedule_loop = other.mister_auto_schedule_loop;
+        self.mister_auto_on_rh_adj = other.mister_auto_on_rh_adj;
+        self.mister_auto_off_rh_adj = other.mister_auto_off_rh_adj;
+        self.mister_rh_hard_floor = other.mister_rh_hard_floor;
+        self.mister_auto_prime_secs = other.mister_auto_prime_secs;
+        self.auto_fault_fallback_count = other.auto_fault_fallback_count;
+        self.auto_metrics_fault_grace_ms = other.auto_metrics_fault_grace_ms;
+        // Only read at boot, but it's the mode for the *next* boot so there's no need to reset.
+        self.default_mode = other.default_mode;
+        self.webhook_url = other.webhook_url;
+        self.config_write_debounce_ms = other.config_write_debounce_ms;
+        self.log_level = other.log_level;
+
+        self
+    }
 }
 
 impl From<&ConfigInstance> for MutableConfigInstance {
     fn from(value: &ConfigInstance) -> Self {
         Self {
+            device_name: Some(value.device_name.clone()),
+            display_contrast: Some(value.display_contrast),
+            display_invert: Some(value.display_invert),
+            display_mirror_enabled: Some(value.display_mirror_enabled),
+            display_temp_decimals: Some(value.display_temp_decimals),
+            display_rh_decimals: Some(value.display_rh_decimals),
+            display_mode_label: Some(value.display_mode_label),
+            temp_unit: Some(value.temp_unit),
             sensor_driver: Some(value.sensor_driver.clone()),
-            sensor_calibration_rh_adj: value.sensor_calibration_rh_adj.clone(),
+            sensor_i2c_khz: Some(value.sensor_i2c_khz),
+            sensor_precision: Some(value.sensor_precision),
+            sensor_heater_interval_secs: Some(value.sensor_heater_interval_secs),
+            sensor_calibration_rh_adj: Some(value.sensor_calibration_rh_adj),
             mister_auto_schedule: Some(value.mister_auto_schedule.clone()),
-            mister_auto_on_rh_adj: value.mister_auto_on_rh_adj.clone(),
-            mister_auto_off_rh_adj: value.mister_auto_off_rh_adj.clone(),
+            mister_auto_schedule_loop: Some(value.mister_auto_schedule_loop),
+            mister_auto_on_rh_adj: Some(value.mister_auto_on_rh_adj),
+            mister_auto_off_rh_adj: Some(value.mister_auto_off_rh_adj),
+            mister_rh_hard_floor: Some(value.mister_rh_hard_floor),
+            mister_auto_prime_secs: Some(value.mister_auto_prime_secs),
+            auto_fault_fallback_count: Some(value.auto_fault_fallback_count),
+            auto_metrics_fault_grace_ms: Some(value.auto_metrics_fault_grace_ms),
+            default_mode: Some(value.default_mode),
+            webhook_url: Some(value.webhook_url.clone()),
+            telemetry_port: Some(value.telemetry_port),
+            config_write_debounce_ms: Some(value.config_write_debounce_ms),
+            log_level: Some(value.log_level),
         }
     }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub(crate) struct MisterAutoSchedule {
+    // Phase label (e.g. 'PINNING'), shown on the display in place of the rh when it fits.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) name: Option<String>,
     pub(crate) rh: f32,
     pub(crate) run_secs: u32,
     pub(crate) max_wait_secs: Option<u32>,
+    // Per-entry hysteresis (falls back to the global adjustments when absent).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) on_adj: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) off_adj: Option<f32>,
+    // Advance once the RH band is reached (instead of after `run_secs`), `max_wait_secs`
+    // forces the advance if it never is.
+    #[serde(default)]
+    pub(crate) hold_until_target: bool,
+    // Heater band is target +/- hysteresis (falling back to `heater_temp_hysteresis`), no target
+    // keeps the heater off for the step. Always Celsius (`temp_unit` only applies to output).
+    #[cfg(feature = "heater")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) target_temp: Option<f32>,
+    #[cfg(feature = "heater")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) temp_hysteresis: Option<f32>,
 }
 
 impl MisterAutoSchedule {
     pub(crate) fn new(rh: f32, run_secs: u32, max_wait_secs: Option<u32>) -> Self {
         Self {
+            name: None,
             rh,
             run_secs,
             max_wait_secs,
+            on_adj: None,
+            off_adj: None,
+            hold_until_target: false,
+            #[cfg(feature = "heater")]
+            target_temp: None,
+            #[cfg(feature = "heater")]
+            temp_hysteresis: None,
+        }
+    }
+
+    #[cfg(feature = "heater")]
+    pub(crate) fn temp_hysteresis(&self, cfg: &ConfigInstance) -> f32 {
+        self.temp_hysteresis.unwrap_or(cfg.heater_temp_hysteresis)
+    }
+
+    pub(crate) fn on_rh(&self, cfg: &ConfigInstance) -> f32 {
+        match self.on_adj {
+            Some(adj) => self.rh + adj,
+            None => cfg.mister_auto_on_rh(self.rh),
+        }
+    }
+
+    pub(crate) fn off_rh(&self, cfg: &ConfigInstance) -> f32 {
+        match self.off_adj {
+            Some(adj) => self.rh + adj,
+            None => cfg.mister_auto_off_rh(self.rh),
         }
     }
 }
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub(crate) enum SensorDriver {
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub(crate) enum SchedulePreset {
+    Generic,
+    Oyster,
+    Shiitake,
+}
+
+impl SchedulePreset {
+    #[cfg(feature = "schedule-preset-oyster")]
+    pub(crate) const DEFAULT: SchedulePreset = SchedulePreset::Oyster;
+    #[cfg(feature = "schedule-preset-shiitake")]
+    pub(crate) const DEFAULT: SchedulePreset = SchedulePreset::Shiitake;
+    #[cfg(not(any(
+        feature = "schedule-preset-oyster",
+        feature = "schedule-preset-shiitake"
+    )))]
+    pub(crate) const DEFAULT: SchedulePreset = SchedulePreset::Generic;
+
+    pub(crate) fn schedule(&self) -> Vec<MisterAutoSchedule> {
+        let steps: &[(f32, u32, Option<u32>)] = match self {
+            SchedulePreset::Generic => &SCHEDULE_PRESET_GENERIC,
+            SchedulePreset::Oyster => &SCHEDULE_PRESET_OYSTER,
+            SchedulePreset::Shiitake => &SCHEDULE_PRESET_SHIITAKE,
+        };
+
+        steps
+            .iter()
+            .map(|(rh, run_secs, max_wait_secs)| schedule![*rh, *run_secs, *max_wait_secs])
+            .collect()
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) enum SensorPrecision {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) enum TemperatureUnit {
     #[default]
+    Celsius,
+    Fahrenheit,
+}
+
+impl TemperatureUnit {
+    pub(crate) fn convert_celsius(&self, temp: f32) -> f32 {
+        match self {
+            TemperatureUnit::Celsius => temp,
+            TemperatureUnit::Fahrenheit => temp * 9_f32 / 5_f32 + 32_f32,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) enum SensorDriver {
     SHT40,
     HDC1080,
+    #[cfg(feature = "mock-sensor")]
+    Mock,
+}
+
+impl Default for SensorDriver {
+    /// SHT40, unless only the HDC1080 driver was built.
+    fn default() -> Self {
+        if cfg!(feature = "hdc1080") && !cfg!(feature = "sht40") {
+            SensorDriver::HDC1080
+        } else {
+            SensorDriver::SHT40
+        }
+    }
+}
+
+impl SensorDriver {
+    /// Whether the firmware was built with this driver (see the Cargo features).
+    pub(crate) fn is_available(&self) -> bool {
+        match self {
+            SensorDriver::SHT40 => cfg!(feature = "sht40"),
+            SensorDriver::HDC1080 => cfg!(feature = "hdc1080"),
+            #[cfg(feature = "mock-sensor")]
+            SensorDriver::Mock => true,
+        }
+    }
+
+    pub(crate) fn available() -> Vec<SensorDriver> {
+        let mut drivers = vec![SensorDriver::SHT40, SensorDriver::HDC1080];
+        #[cfg(feature = "mock-sensor")]
+        drivers.push(SensorDriver::Mock);
+
+        drivers.retain(|d| d.is_available());
+        drivers
+    }
+}
+
+#[cfg(feature = "sdcard")]
+#[derive(Copy, Clone)]
+enum SdConfigFormat {
+    Cbor,
+    Json,
+}
+
+// The config is only read, so file timestamps don't matter.
+#[cfg(feature = "sdcard")]
+struct SdNoTimeSource;
+
+#[cfg(feature = "sdcard")]
+impl TimeSource for SdNoTimeSource {
+    fn get_timestamp(&self) -> Timestamp {
+        Timestamp {
+            year_since_1970: 0,
+            zero_indexed_month: 0,
+            zero_indexed_day: 0,
+            hours: 0,
+            minutes: 0,
+            seconds: 0,
+        }
+    }
+}
+
+// Utils
+
+fn serialize_masked<S: Serializer>(
+    _: &String,
+    serializer: S,
+) -> core::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str("********")
+}
+
+fn serialize_masked_opt<S: Serializer>(
+    value: &Option<String>,
+    serializer: S,
+) -> core::result::Result<S::Ok, S::Error> {
+    match value {
+        Some(_) => serializer.serialize_str("********"),
+        None => serializer.serialize_none(),
+    }
 }
@@ -4,19 +4,31 @@ use embassy_executor::Spawner;
 use embassy_futures::select::{select, Either};
 use embassy_time::{Duration, Timer};
 use embedded_hal_async::digital::Wait;
-use esp_hal::gpio::{GpioPin, Input, PullDown, Unknown};
+use esp_hal::gpio::{GpioPin, Input, PullDown, PullUp, Unknown};
 use esp_hal::prelude::*;
 
+#[cfg(feature = "reset-button")]
+use crate::chip_control::{ChipControlAction, ChipControlPublisher, CHIP_CONTROL_CHANNEL};
 use crate::config::{Config, ConfigInstance};
-use crate::display::{ChangeMode as DisplayChangeMode, ChangeModePublisher, Mode};
+use crate::diag::DiagTask;
+use crate::display::{ChangeMode as DisplayChangeMode, ChangeModePublisher};
 use crate::error::{map_embassy_pub_sub_err, map_embassy_spawn_err, map_infallible_err, Result};
 use crate::mister::{
     ChangeMode as MisterChangeMode, ChangeModePublisher as MisterChangeModePublisher,
 };
 use crate::utils::get_time_ms;
-use crate::{display, mister};
+use crate::{diag, display, mister};
 
 const MODE_BUTTON_GPIO_PIN: u8 = 21;
+#[cfg(feature = "mist-trigger")]
+const MIST_TRIGGER_GPIO_PIN: u8 = 4;
+#[cfg(feature = "reset-button")]
+const RESET_BUTTON_GPIO_PIN: u8 = 13;
+// Status LED flashes acknowledging the reset button.
+#[cfg(feature = "reset-button")]
+const RESET_BUTTON_RESET_FLASHES: u8 = 1;
+#[cfg(feature = "reset-button")]
+const RESET_BUTTON_FACTORY_RESET_FLASHES: u8 = 3;
 
 pub(crate) fn init(
     cfg: Config,
@@ -42,6 +54,40 @@ pub(crate) fn init(
     Ok(())
 }
 
+#[cfg(feature = "mist-trigger")]
+pub(crate) fn init_mist_trigger(
+    cfg: Config,
+    mist_trigger: GpioPin<Unknown, MIST_TRIGGER_GPIO_PIN>,
+    spawner: &Spawner,
+) -> Result<()> {
+    spawner
+        .spawn(mist_trigger_task(
+            cfg,
+            mist_trigger,
+            mister::CHANGE_MODE_CHANNEL
+                .publisher()
+                .map_err(map_embassy_pub_sub_err)?,
+        ))
+        .map_err(map_embassy_spawn_err)
+}
+
+#[cfg(feature = "reset-button")]
+pub(crate) fn init_reset_button(
+    cfg: Config,
+    reset_btn: GpioPin<Unknown, RESET_BUTTON_GPIO_PIN>,
+    spawner: &Spawner,
+) -> Result<()> {
+    spawner
+        .spawn(reset_button_task(
+            cfg,
+            reset_btn,
+            CHIP_CONTROL_CHANNEL
+                .publisher()
+                .map_err(map_embassy_pub_sub_err)?,
+        ))
+        .map_err(map_embassy_spawn_err)
+}
+
 #[embassy_executor::task]
 async fn controls_task(
     cfg: Config,
@@ -49,7 +95,7 @@ async fn controls_task(
     mut display_change_mode_pub: ChangeModePublisher,
     mut mister_change_mode_pub: MisterChangeModePublisher,
 ) {
-    let mut mode_btn = mode_btn.into_pull_down_input();
+    let mut mode_btn = Button::new(mode_btn, cfg.load().controls_button_active_low);
 
     loop {
         if let Err(e) = controls_task_poll(
@@ -67,14 +113,26 @@ async fn controls_task(
 
 async fn controls_task_poll(
     cfg: Arc<ConfigInstance>,
-    mode_btn: &mut GpioPin<Input<PullDown>, MODE_BUTTON_GPIO_PIN>,
+    mode_btn: &mut Button<MODE_BUTTON_GPIO_PIN>,
     display_change_mode_pub: &mut ChangeModePublisher,
     mister_change_mode_pub: &mut MisterChangeModePublisher,
 ) -> Result<()> {
-    mode_btn
-        .wait_for_rising_edge()
-        .await
-        .map_err(map_infallible_err)?;
+    mode_btn.wait_for_press().await?;
+    // Spans the press itself (i.e. a long hold is a long handling).
+    let _handling = diag::Handling::start(DiagTask::Controls);
+
+    // A press of either kind while the panel is off (in the dark) only wakes it, rather than
+    // changing the mister mode unseen.
+    #[cfg(feature = "ldr")]
+    if display::is_panel_off() {
+        log::info!("Mode button activated, waking the display ...");
+
+        display_change_mode_pub.publish_immediate(DisplayChangeMode::wake());
+        mode_btn.wait_for_released().await?;
+        Timer::after(Duration::from_millis(cfg.controls_debounce_ms as u64)).await;
+
+        return Ok(());
+    }
 
     log::info!("Mode button activated ...");
 
@@ -83,13 +141,13 @@ async fn controls_task_poll(
     loop {
         // Detect initial press threshold
         let _ = select(
-            wait_for_low_of_ms(mode_btn, cfg.controls_min_press_ms),
+            wait_for_release_of_ms(mode_btn, cfg.controls_min_press_ms),
             Timer::after(Duration::from_millis(cfg.controls_min_hold_ms as u64)),
         )
         .await;
 
         // Determine result (or if long press active)
-        if mode_btn.is_high().map_err(map_infallible_err)? {
+        if mode_btn.is_pressed()? {
             if get_time_ms() - start_ms >= cfg.controls_min_hold_ms {
                 handle_mode_button_event(
                     ButtonState::Held,
@@ -97,7 +155,7 @@ async fn controls_task_poll(
                     mister_change_mode_pub,
                 )
                 .await?;
-                wait_for_low_of_ms(mode_btn, 300).await?;
+                wait_for_release_of_ms(mode_btn, 300).await?;
                 handle_mode_button_event(
                     ButtonState::Released,
                     display_change_mode_pub,
@@ -120,18 +178,136 @@ async fn controls_task_poll(
         }
     }
 
+    // Cooldown before re-arming (ignores any contact bounce after the event).
+    Timer::after(Duration::from_millis(cfg.controls_debounce_ms as u64)).await;
+
     Ok(())
 }
 
-async fn wait_for_low_of_ms(
-    mode_btn: &mut GpioPin<Input<PullDown>, MODE_BUTTON_GPIO_PIN>,
+#[cfg(feature = "mist-trigger")]
+#[embassy_executor::task]
+async fn mist_trigger_task(
+    cfg: Config,
+    mist_trigger: GpioPin<Unknown, MIST_TRIGGER_GPIO_PIN>,
+    mut mister_change_mode_pub: MisterChangeModePublisher,
+) {
+    let mut mist_trigger = mist_trigger.into_pull_down_input();
+
+    loop {
+        if let Err(e) =
+            mist_trigger_task_poll(cfg.load(), &mut mist_trigger, &mut mister_change_mode_pub).await
+        {
+            log::warn!("Failed to handle mist trigger task poll: {:?}", e);
+        }
+    }
+}
+
+#[cfg(feature = "mist-trigger")]
+async fn mist_trigger_task_poll(
+    cfg: Arc<ConfigInstance>,
+    mist_trigger: &mut GpioPin<Input<PullDown>, MIST_TRIGGER_GPIO_PIN>,
+    mister_change_mode_pub: &mut MisterChangeModePublisher,
+) -> Result<()> {
+    mist_trigger
+        .wait_for_rising_edge()
+        .await
+        .map_err(map_infallible_err)?;
+
+    // Still held after the minimum press (i.e. not noise or contact bounce).
+    Timer::after(Duration::from_millis(cfg.controls_min_press_ms as u64)).await;
+    if mist_trigger.is_high().map_err(map_infallible_err)? {
+        log::info!("Mist trigger activated ...");
+
+        mister_change_mode_pub
+            .publish_immediate(MisterChangeMode::run(cfg.controls_mist_trigger_secs));
+    }
+
+    // Re-arm once released (a held switch doesn't retrigger).
+    mist_trigger
+        .wait_for_low()
+        .await
+        .map_err(map_infallible_err)?;
+    Timer::after(Duration::from_millis(cfg.controls_debounce_ms as u64)).await;
+
+    Ok(())
+}
+
+#[cfg(feature = "reset-button")]
+#[embassy_executor::task]
+async fn reset_button_task(
+    cfg: Config,
+    reset_btn: GpioPin<Unknown, RESET_BUTTON_GPIO_PIN>,
+    mut chip_control_pub: ChipControlPublisher,
+) {
+    let mut reset_btn = Button::new(reset_btn, cfg.load().controls_button_active_low);
+
+    loop {
+        if let Err(e) =
+            reset_button_task_poll(cfg.load(), &mut reset_btn, &mut chip_control_pub).await
+        {
+            log::warn!("Failed to handle reset button task poll: {:?}", e);
+        }
+    }
+}
+
+/// A press resets the chip, holding for `controls_factory_reset_hold_ms` factory resets it.
+#[cfg(feature = "reset-button")]
+async fn reset_button_task_poll(
+    cfg: Arc<ConfigInstance>,
+    reset_btn: &mut Button<RESET_BUTTON_GPIO_PIN>,
+    chip_control_pub: &mut ChipControlPublisher,
+) -> Result<()> {
+    reset_btn.wait_for_press().await?;
+
+    // Still held after the minimum press (i.e. not noise or contact bounce).
+    Timer::after(Duration::from_millis(cfg.controls_min_press_ms as u64)).await;
+    if !reset_btn.is_pressed()? {
+        return Ok(());
+    }
+
+    log::info!("Reset button activated ...");
+
+    let hold_ms = cfg
+        .controls_factory_reset_hold_ms
+        .saturating_sub(cfg.controls_min_press_ms);
+
+    match select(
+        reset_btn.wait_for_released(),
+        Timer::after(Duration::from_millis(hold_ms as u64)),
+    )
+    .await
+    {
+        Either::First(_) => {
+            log::info!("Reset button event: reset");
+
+            mister::flash_status_led(RESET_BUTTON_RESET_FLASHES);
+            chip_control_pub.publish_immediate(ChipControlAction::Reset);
+        }
+        Either::Second(_) => {
+            log::info!("Reset button event: factory reset");
+
+            mister::flash_status_led(RESET_BUTTON_FACTORY_RESET_FLASHES);
+            chip_control_pub.publish_immediate(ChipControlAction::FactoryReset);
+
+            reset_btn.wait_for_released().await?;
+        }
+    }
+
+    Timer::after(Duration::from_millis(cfg.controls_debounce_ms as u64)).await;
+
+    Ok(())
+}
+
+/// Waits until the button has stayed released for `duration_ms`.
+async fn wait_for_release_of_ms<const PIN: u8>(
+    btn: &mut Button<PIN>,
     duration_ms: u32,
 ) -> Result<()> {
     loop {
-        mode_btn.wait_for_low().await.map_err(map_infallible_err)?;
+        btn.wait_for_released().await?;
 
         match select(
-            mode_btn.wait_for_rising_edge(),
+            btn.wait_for_press(),
             Timer::after(Duration::from_millis(duration_ms as u64)),
         )
         .await
@@ -162,10 +338,11 @@ async fn handle_mode_button_event(
             mister_change_mode_pub.publish_immediate(MisterChangeMode::default());
         }
         ButtonState::Held => {
-            display_change_mode_pub.publish_immediate(DisplayChangeMode::new(Some(Mode::Info)));
+            // Cycle to the next display screen.
+            display_change_mode_pub.publish_immediate(DisplayChangeMode::default());
         }
         ButtonState::Released => {
-            display_change_mode_pub.publish_immediate(DisplayChangeMode::new(None));
+            // Stay on the selected screen.
         }
     }
 
@@ -174,6 +351,48 @@ async fn handle_mode_button_event(
 
 // Models
 
+/// A button input, either active-high (pull-down) or active-low (pull-up) wiring.
+enum Button<const PIN: u8> {
+    ActiveHigh(GpioPin<Input<PullDown>, PIN>),
+    ActiveLow(GpioPin<Input<PullUp>, PIN>),
+}
+
+impl<const PIN: u8> Button<PIN> {
+    fn new(btn: GpioPin<Unknown, PIN>, active_low: bool) -> Self {
+        if active_low {
+            Self::ActiveLow(btn.into_pull_up_input())
+        } else {
+            Self::ActiveHigh(btn.into_pull_down_input())
+        }
+    }
+
+    /// Waits for the edge of a press.
+    async fn wait_for_press(&mut self) -> Result<()> {
+        match self {
+            Self::ActiveHigh(pin) => pin.wait_for_rising_edge().await,
+            Self::ActiveLow(pin) => pin.wait_for_falling_edge().await,
+        }
+        .map_err(map_infallible_err)
+    }
+
+    /// Waits until the button is released (returns straight away if it already is).
+    async fn wait_for_released(&mut self) -> Result<()> {
+        match self {
+            Self::ActiveHigh(pin) => pin.wait_for_low().await,
+            Self::ActiveLow(pin) => pin.wait_for_high().await,
+        }
+        .map_err(map_infallible_err)
+    }
+
+    fn is_pressed(&mut self) -> Result<bool> {
+        match self {
+            Self::ActiveHigh(pin) => pin.is_high(),
+            Self::ActiveLow(pin) => pin.is_low(),
+        }
+        .map_err(map_infallible_err)
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 enum ButtonState {
     Pressed,
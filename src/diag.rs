@@ -0,0 +1,135 @@
+//! Task loop timing diagnostics (only collected with the `diag` feature).
+
+#[cfg(feature = "diag")]
+use core::sync::atomic::{AtomicU32, Ordering};
+
+#[cfg(feature = "diag")]
+use serde::Serialize;
+
+#[cfg(feature = "diag")]
+use crate::utils::get_time_ms;
+
+#[cfg(feature = "diag")]
+static MISTER_LOOP: LoopStats = LoopStats::new();
+#[cfg(feature = "diag")]
+static SENSOR_LOOP: LoopStats = LoopStats::new();
+#[cfg(feature = "diag")]
+static DISPLAY_LOOP: LoopStats = LoopStats::new();
+#[cfg(feature = "diag")]
+static CONTROLS_LOOP: LoopStats = LoopStats::new();
+
+/// Times a task's handling of an event, from when it's started (once the event fired) until it's
+/// dropped, so the wait for the event isn't counted (a no-op without `diag`).
+pub(crate) struct Handling {
+    #[cfg(feature = "diag")]
+    task: DiagTask,
+    #[cfg(feature = "diag")]
+    started_at: u32,
+}
+
+impl Handling {
+    pub(crate) fn start(task: DiagTask) -> Self {
+        #[cfg(not(feature = "diag"))]
+        let _ = task;
+
+        Self {
+            #[cfg(feature = "diag")]
+            task,
+            #[cfg(feature = "diag")]
+            started_at: get_time_ms(),
+        }
+    }
+}
+
+#[cfg(feature = "diag")]
+impl Drop for Handling {
+    fn drop(&mut self) {
+        self.task
+            .stats()
+            .record(get_time_ms().wrapping_sub(self.started_at));
+    }
+}
+
+#[cfg(feature = "diag")]
+pub(crate) fn loop_times() -> LoopTimes {
+    LoopTimes {
+        mister: MISTER_LOOP.snapshot(),
+        sensor: SENSOR_LOOP.snapshot(),
+        display: DISPLAY_LOOP.snapshot(),
+        controls: CONTROLS_LOOP.snapshot(),
+    }
+}
+
+// Models
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum DiagTask {
+    Mister,
+    Sensor,
+    Display,
+    Controls,
+}
+
+#[cfg(feature = "diag")]
+impl DiagTask {
+    fn stats(&self) -> &'static LoopStats {
+        match self {
+            DiagTask::Mister => &MISTER_LOOP,
+            DiagTask::Sensor => &SENSOR_LOOP,
+            DiagTask::Display => &DISPLAY_LOOP,
+            DiagTask::Controls => &CONTROLS_LOOP,
+        }
+    }
+}
+
+#[cfg(feature = "diag")]
+struct LoopStats {
+    count: AtomicU32,
+    total_ms: AtomicU32,
+    max_ms: AtomicU32,
+}
+
+#[cfg(feature = "diag")]
+impl LoopStats {
+    const fn new() -> Self {
+        Self {
+            count: AtomicU32::new(0),
+            total_ms: AtomicU32::new(0),
+            max_ms: AtomicU32::new(0),
+        }
+    }
+
+    fn record(&self, elapsed_ms: u32) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.total_ms.fetch_add(elapsed_ms, Ordering::Relaxed);
+        self.max_ms.fetch_max(elapsed_ms, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> LoopTime {
+        let count = self.count.load(Ordering::Relaxed);
+        let total_ms = self.total_ms.load(Ordering::Relaxed);
+
+        LoopTime {
+            count,
+            avg_ms: if count > 0 { total_ms / count } else { 0 },
+            max_ms: self.max_ms.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(feature = "diag")]
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct LoopTime {
+    count: u32,
+    avg_ms: u32,
+    max_ms: u32,
+}
+
+#[cfg(feature = "diag")]
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct LoopTimes {
+    mister: LoopTime,
+    sensor: LoopTime,
+    display: LoopTime,
+    controls: LoopTime,
+}
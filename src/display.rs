@@ -1,44 +1,76 @@
+use alloc::boxed::Box;
 use alloc::format;
 use alloc::string::{String, ToString};
+use core::cell::RefCell;
+use core::future::pending;
 
 use embassy_executor::Spawner;
-use embassy_futures::select::{select4, Either4};
+use embassy_futures::select::{select, select4, Either, Either4};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::pubsub::{PubSubChannel, Publisher, Subscriber, WaitResult};
-use embassy_time::{Duration, Timer};
+#[cfg(feature = "ldr")]
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
 use embedded_graphics::mono_font::iso_8859_1::{FONT_10X20, FONT_6X12, FONT_8X13};
 use embedded_graphics::mono_font::MonoTextStyle;
 use embedded_graphics::pixelcolor::BinaryColor;
 use embedded_graphics::prelude::*;
 use embedded_graphics::primitives::{PrimitiveStyle, PrimitiveStyleBuilder, Rectangle};
 use embedded_graphics::text::{Alignment, Text};
+#[cfg(feature = "ldr")]
+use embedded_hal_02::adc::OneShot;
+use embedded_storage::{ReadStorage, Storage};
+#[cfg(feature = "ldr")]
+use esp_hal::analog::adc::{AdcConfig, Attenuation, ADC};
 use esp_hal::clock::Clocks;
+#[cfg(feature = "ldr")]
+use esp_hal::gpio::{GpioPin, Unknown};
 use esp_hal::gpio::{InputPin, OutputPin};
 use esp_hal::i2c::I2C;
 use esp_hal::peripheral::Peripheral;
+#[cfg(feature = "ldr")]
+use esp_hal::peripherals::ADC1;
 use esp_hal::peripherals::I2C1;
+use esp_storage::FlashStorage;
 use fugit::RateExtU32;
+use fungi_core::gauge::{calculate_gauge_x, display_rh, display_temp, gauge_text};
 use num_traits::float::Float;
+use serde::{Deserialize, Serialize};
+use spin::RwLock;
 use ssd1306::mode::BufferedGraphicsMode;
 use ssd1306::prelude::*;
 use ssd1306::{I2CDisplayInterface, Ssd1306};
 
-use crate::config::Config;
+use crate::config::{Config, MisterAutoSchedule};
+use crate::diag::DiagTask;
 use crate::error::{
-    display_draw_err, map_display_err, map_embassy_pub_sub_err, map_embassy_spawn_err, Result,
+    display_draw_err, general_fault, map_display_err, map_embassy_pub_sub_err,
+    map_embassy_spawn_err, Result,
 };
+use crate::heartbeat::HeartbeatTask;
+use crate::lag::LagChannel;
 use crate::mister::{
     AutoScheduleStateOperator, Mode as MisterMode,
     ModeChangedSubscriber as MisterModeChangedSubscriber, Status as MisterStatus, Status,
     StatusChangedSubscriber as MisterStatusChangedSubscriber,
 };
 use crate::network::wifi::IP_ADDRESS;
+#[cfg(feature = "selftest")]
+use crate::selftest;
+#[cfg(feature = "selftest")]
+use crate::selftest::SelfTestCheck;
 use crate::sensor::{SensorMetrics, SensorSubscriber};
-use crate::{mister, sensor};
+use crate::{diag, heartbeat, lag, mister, sensor};
+
+const MODE_FLASH_ADDR: u32 = 0x9001;
 
 static DISPLAY_WIDTH: u32 = 128;
 static DISPLAY_HALF_WIDTH: u32 = DISPLAY_WIDTH / 2;
 static DISPLAY_HEIGHT: u32 = 64;
+static DISPLAY_PRECHARGE: u8 = 0x2;
+// Backoff between init attempts (i.e. the panel isn't connected yet).
+static DISPLAY_INIT_RETRY_MIN_MS: u64 = 1000;
+static DISPLAY_INIT_RETRY_MAX_MS: u64 = 60000;
 
 static GAUGE_LABEL_OFFSET_Y: i32 = 12;
 static GAUGE_FONT_HEIGHT: u32 = 20;
@@ -50,13 +82,48 @@ static STATUS_BOX_HEIGHT: u32 = 24;
 static STATUS_BOX_PADDING_X: u32 = 8;
 static STATUS_BOX_PADDING_Y: u32 = 8;
 static STATUS_FONT_WIDTH: u32 = 8;
-
-type ChangeModeSubscriber = Subscriber<'static, CriticalSectionRawMutex, ChangeMode, 1, 1, 1>;
+static STATUS_MAX_CHARS: usize =
+    ((DISPLAY_WIDTH - (STATUS_BOX_PADDING_X * 2)) / STATUS_FONT_WIDTH) as usize;
+
+// What was last drawn (e.g. for mirroring the panel remotely).
+pub(crate) static RENDERED: RwLock<Option<RenderedDisplay>> = RwLock::new(None);
+
+#[cfg(feature = "ldr")]
+const LDR_GPIO_PIN: u8 = 34;
+#[cfg(feature = "ldr")]
+const LDR_POLL_MS: u64 = 1000;
+// Raw counts above the dark threshold before it counts as light again (so dusk doesn't flicker).
+#[cfg(feature = "ldr")]
+const LDR_HYSTERESIS: u16 = 50;
+// How long a mode button press wakes the panel for while dark.
+#[cfg(feature = "ldr")]
+const LDR_WAKE_MS: u64 = 30000;
+
+// Whether the LDR reads dark, signalled on a change.
+#[cfg(feature = "ldr")]
+static LDR_DARK: Signal<CriticalSectionRawMutex, bool> = Signal::new();
+// The panel is switched off (in the dark), a button press then only wakes it.
+#[cfg(feature = "ldr")]
+static PANEL_OFF: RwLock<bool> = RwLock::new(false);
+
+type ChangeModeSubscriber = Subscriber<'static, CriticalSectionRawMutex, ChangeMode, 1, 1, 2>;
 pub(crate) type ChangeModePublisher =
-    Publisher<'static, CriticalSectionRawMutex, ChangeMode, 1, 1, 1>;
-pub(crate) static CHANGE_MODE_CHANNEL: PubSubChannel<CriticalSectionRawMutex, ChangeMode, 1, 1, 1> =
+    Publisher<'static, CriticalSectionRawMutex, ChangeMode, 1, 1, 2>;
+pub(crate) static CHANGE_MODE_CHANNEL: PubSubChannel<CriticalSectionRawMutex, ChangeMode, 1, 1, 2> =
     PubSubChannel::new();
 
+type MessageSubscriber = Subscriber<'static, CriticalSectionRawMutex, DisplayMessage, 1, 1, 1>;
+pub(crate) type MessagePublisher =
+    Publisher<'static, CriticalSectionRawMutex, DisplayMessage, 1, 1, 1>;
+pub(crate) static MESSAGE_CHANNEL: PubSubChannel<CriticalSectionRawMutex, DisplayMessage, 1, 1, 1> =
+    PubSubChannel::new();
+
+type DisplayDevice = Ssd1306<
+    I2CInterface<DisplayBusDevice>,
+    DisplaySize128x64,
+    BufferedGraphicsMode<DisplaySize128x64>,
+>;
+
 pub(crate) fn init<SDA, SCL>(
     cfg: Config,
     sda: impl Peripheral<P = SDA> + 'static,
@@ -70,80 +137,214 @@ where
     SCL: InputPin + OutputPin,
 {
     let i2c = I2C::new(i2c1, sda, scl, 400_u32.kHz(), &clocks);
+    let bus: &'static RefCell<I2C<'static, I2C1>> = Box::leak(Box::new(RefCell::new(i2c)));
+
+    // Nothing is sent to the panels until the task sets them up.
+    let display = new_display(I2CDisplayInterface::new(DisplayBusDevice { bus }));
+    // The mirror sits on the same bus, at the alternate address (0x3D).
+    let mirror = cfg.load().display_mirror_enabled.then(|| {
+        new_display(I2CDisplayInterface::new_alternate_address(
+            DisplayBusDevice { bus },
+        ))
+    });
+
+    spawner
+        .spawn(display_task(
+            cfg,
+            display,
+            mirror,
+            CHANGE_MODE_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+            MESSAGE_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+            sensor::CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+            mister::MODE_CHANGED_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+            mister::STATUS_CHANGED_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+        ))
+        .map_err(map_embassy_spawn_err)?;
+
+    Ok(())
+}
+
+/// Samples the LDR (only with the `ldr` feature), the display task switches the panel off while
+/// it reads dark.
+#[cfg(feature = "ldr")]
+pub(crate) fn init_ldr(
+    cfg: Config,
+    ldr_pin: GpioPin<Unknown, LDR_GPIO_PIN>,
+    adc1: ADC1,
+    spawner: &Spawner,
+) -> Result<()> {
+    spawner
+        .spawn(ldr_task(cfg, ldr_pin, adc1))
+        .map_err(map_embassy_spawn_err)
+}
+
+#[cfg(feature = "ldr")]
+pub(crate) fn is_panel_off() -> bool {
+    *PANEL_OFF.read()
+}
+
+#[cfg(feature = "ldr")]
+#[embassy_executor::task]
+async fn ldr_task(cfg: Config, ldr_pin: GpioPin<Unknown, LDR_GPIO_PIN>, adc1: ADC1) {
+    let mut adc1_config = AdcConfig::new();
+    let mut ldr_pin = adc1_config.enable_pin(ldr_pin.into_analog(), Attenuation::Attenuation11dB);
+    let mut adc1 = ADC::<ADC1>::new(adc1, adc1_config);
+
+    let mut dark = false;
+
+    loop {
+        let raw: u16 = loop {
+            match adc1.read(&mut ldr_pin) {
+                Ok(raw) => break raw,
+                // Conversion still in progress.
+                Err(_) => Timer::after(Duration::from_millis(1)).await,
+            }
+        };
 
-    let interface = I2CDisplayInterface::new(i2c);
+        let threshold = cfg.load().display_ldr_dark_threshold;
+        let now_dark = if dark {
+            raw < threshold.saturating_add(LDR_HYSTERESIS)
+        } else {
+            raw < threshold
+        };
 
-    let mut display = Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0)
-        .into_buffered_graphics_mode();
+        if now_dark != dark {
+            log::info!(
+                "Display LDR: {} [raw: {}]",
+                if now_dark { "Dark" } else { "Light" },
+                raw
+            );
 
+            dark = now_dark;
+            LDR_DARK.signal(dark);
+        }
+
+        Timer::after(Duration::from_millis(LDR_POLL_MS)).await;
+    }
+}
+
+fn new_display(interface: I2CInterface<DisplayBusDevice>) -> DisplayDevice {
+    Ssd1306::new(interface, DisplaySize128x64, DisplayRotation::Rotate0)
+        .into_buffered_graphics_mode()
+}
+
+/// Initializes the panel and draws the static labels.
+fn display_setup(cfg: &Config, display: &mut DisplayDevice) -> Result<()> {
     display.init().map_err(map_display_err)?;
 
-    log::info!("Initialized display");
+    // Changing these resets the chip, so they only need applying at init.
+    let contrast = cfg.load().display_contrast;
+    display
+        .set_brightness(Brightness::custom(DISPLAY_PRECHARGE, contrast))
+        .map_err(map_display_err)?;
+
+    let invert = cfg.load().display_invert;
+    display.set_invert(invert).map_err(map_display_err)?;
+
+    log::info!(
+        "Initialized display [contrast: {}, invert: {}]",
+        contrast,
+        invert
+    );
 
     let label_text_style = MonoTextStyle::new(&FONT_6X12, BinaryColor::On);
 
     Text::new(
         "TEMP",
-        Point::new(calculate_gauge_x(4, 6, 0), GAUGE_LABEL_OFFSET_Y),
+        Point::new(
+            calculate_gauge_x(DISPLAY_HALF_WIDTH, 4, 6, 0),
+            GAUGE_LABEL_OFFSET_Y,
+        ),
         label_text_style,
     )
-    .draw(&mut display)
+    .draw(display)
     .map_err(|e| display_draw_err(format!("{:?}", e)))?;
 
     Text::with_alignment(
         "RH",
         Point::new(
-            DISPLAY_WIDTH as i32 - calculate_gauge_x(2, 6, 0),
+            DISPLAY_WIDTH as i32 - calculate_gauge_x(DISPLAY_HALF_WIDTH, 2, 6, 0),
             GAUGE_LABEL_OFFSET_Y,
         ),
         label_text_style,
         Alignment::Right,
     )
-    .draw(&mut display)
+    .draw(display)
     .map_err(|e| display_draw_err(format!("{:?}", e)))?;
 
-    display.flush().map_err(map_display_err)?;
-
-    let mut display_renderer = DisplayRenderer::new(cfg.clone(), display, 0_f32, 0_f32);
-
-    // Initial draw
-    display_renderer.draw()?;
-
-    log::info!("Drew initial display");
-
-    spawner
-        .spawn(display_task(
-            display_renderer,
-            CHANGE_MODE_CHANNEL
-                .subscriber()
-                .map_err(map_embassy_pub_sub_err)?,
-            sensor::CHANNEL
-                .subscriber()
-                .map_err(map_embassy_pub_sub_err)?,
-            mister::MODE_CHANGED_CHANNEL
-                .subscriber()
-                .map_err(map_embassy_pub_sub_err)?,
-            mister::STATUS_CHANGED_CHANNEL
-                .subscriber()
-                .map_err(map_embassy_pub_sub_err)?,
-        ))
-        .map_err(map_embassy_spawn_err)?;
-
-    Ok(())
+    display.flush().map_err(map_display_err)
 }
 
 #[embassy_executor::task]
 async fn display_task(
-    mut display_renderer: DisplayRenderer<'static>,
+    cfg: Config,
+    mut display: DisplayDevice,
+    mirror: Option<DisplayDevice>,
     mut change_mode_sub: ChangeModeSubscriber,
+    mut message_sub: MessageSubscriber,
     mut sensor_sub: SensorSubscriber,
     mut mister_mode_changed_sub: MisterModeChangedSubscriber,
     mut mister_status_changed_sub: MisterStatusChangedSubscriber,
 ) {
+    // Keep retrying (rather than running blind), so a panel connected after boot or a flaky
+    // bus at cold boot still comes up.
+    let mut retry_ms = DISPLAY_INIT_RETRY_MIN_MS;
+    while let Err(e) = display_setup(&cfg, &mut display) {
+        log::warn!(
+            "Failed to initialize display (retrying in {}ms): {:?}",
+            retry_ms,
+            e
+        );
+        #[cfg(feature = "selftest")]
+        if retry_ms == DISPLAY_INIT_RETRY_MIN_MS {
+            selftest::record(SelfTestCheck::Display, Err(e));
+        }
+
+        Timer::after(Duration::from_millis(retry_ms)).await;
+        retry_ms = (retry_ms * 2).min(DISPLAY_INIT_RETRY_MAX_MS);
+    }
+    // Setup ends with a flush, so the panel is responding.
+    #[cfg(feature = "selftest")]
+    selftest::record(SelfTestCheck::Display, Ok(()));
+
+    // The mirror is optional, the primary panel carries on without it.
+    let mirror = mirror.and_then(|mut mirror| match display_setup(&cfg, &mut mirror) {
+        Ok(_) => Some(mirror),
+        Err(e) => {
+            log::warn!("Failed to initialize mirror display (disabled): {:?}", e);
+            None
+        }
+    });
+
+    let mut display_renderer = DisplayRenderer::new(cfg, display, mirror);
+
+    let mut storage = FlashStorage::new();
+    display_renderer.mode(load_mode(&mut storage));
+
+    // Initial draw
+    match display_renderer.draw() {
+        Ok(_) => log::info!("Drew initial display"),
+        Err(e) => log::warn!("Failed to draw initial display: {:?}", e),
+    }
+
     loop {
+        heartbeat::beat(HeartbeatTask::Display);
+
         if let Err(e) = display_task_poll(
             &mut display_renderer,
+            &mut storage,
             &mut change_mode_sub,
+            &mut message_sub,
             &mut sensor_sub,
             &mut mister_mode_changed_sub,
             &mut mister_status_changed_sub,
@@ -161,22 +362,76 @@ async fn display_task(
 
 async fn display_task_poll(
     display_renderer: &mut DisplayRenderer<'static>,
+    storage: &mut FlashStorage,
     change_mode_sub: &mut ChangeModeSubscriber,
+    message_sub: &mut MessageSubscriber,
     sensor_sub: &mut SensorSubscriber,
     mister_mode_changed_sub: &mut MisterModeChangedSubscriber,
     mister_status_changed_sub: &mut MisterStatusChangedSubscriber,
 ) -> Result<()> {
-    match select4(
-        sensor_sub.next_message(),
-        change_mode_sub.next_message(),
-        mister_mode_changed_sub.next_message(),
-        mister_status_changed_sub.next_message(),
+    let message_expires_at = display_renderer.message.as_ref().map(|m| m.expires_at);
+
+    let event = select(
+        select4(
+            sensor_sub.next_message(),
+            change_mode_sub.next_message(),
+            mister_mode_changed_sub.next_message(),
+            mister_status_changed_sub.next_message(),
+        ),
+        select4(
+            message_sub.next_message(),
+            message_expiry(message_expires_at),
+            redraw_due(display_renderer.wake_at()),
+            ldr_changed(),
+        ),
     )
-    .await
-    {
+    .await;
+    let _handling = diag::Handling::start(DiagTask::Display);
+
+    let r = match event {
+        Either::First(r) => r,
+        Either::Second(Either4::First(r)) => {
+            match r {
+                WaitResult::Lagged(count) => {
+                    log::warn!("display message subscriber lagged by {} messages", count);
+                    lag::record(LagChannel::DisplayMessage);
+
+                    // Ignore
+                    return Ok(());
+                }
+                WaitResult::Message(msg) => {
+                    display_renderer.message(msg);
+                }
+            }
+
+            return display_renderer.draw();
+        }
+        Either::Second(Either4::Second(_)) => {
+            // Message expired, revert to the normal status.
+            display_renderer.clear_message();
+
+            return display_renderer.draw();
+        }
+        Either::Second(Either4::Third(_)) => {
+            // Apply the changes held back by the redraw throttle (or status hold).
+            return display_renderer.draw();
+        }
+        #[cfg(feature = "ldr")]
+        Either::Second(Either4::Fourth(dark)) => {
+            display_renderer.dark = dark;
+
+            return display_renderer.draw();
+        }
+        // Never completes without the LDR.
+        #[cfg(not(feature = "ldr"))]
+        Either::Second(Either4::Fourth(_)) => return Ok(()),
+    };
+
+    match r {
         Either4::First(r) => match r {
             WaitResult::Lagged(count) => {
                 log::warn!("display sensor subscriber lagged by {} messages", count);
+                lag::record(LagChannel::Sensor);
 
                 // Ignore
                 return Ok(());
@@ -191,22 +446,40 @@ async fn display_task_poll(
         Either4::Second(r) => match r {
             WaitResult::Lagged(count) => {
                 log::warn!("display mode subscriber lagged by {} messages", count);
+                lag::record(LagChannel::DisplayChangeMode);
 
                 // Ignore
                 return Ok(());
             }
-            WaitResult::Message(change_mode) => match change_mode.mode {
-                Some(mode) => {
-                    display_renderer.mode(mode);
+            WaitResult::Message(change_mode) => {
+                // Any change in the dark extends the wake period.
+                #[cfg(feature = "ldr")]
+                {
+                    display_renderer.wake();
+                    if change_mode.wake_only {
+                        return display_renderer.draw();
+                    }
                 }
-                None => {
-                    display_renderer.mode(Mode::default());
+
+                // No explicit mode cycles to the next screen.
+                let mode = change_mode
+                    .mode
+                    .unwrap_or_else(|| display_renderer.mode.next());
+                let changed = display_renderer.mode != mode;
+
+                display_renderer.mode(mode);
+
+                if changed {
+                    if let Err(e) = store_mode(storage, mode) {
+                        log::warn!("Failed to persist display mode: {:?}", e);
+                    }
                 }
-            },
+            }
         },
         Either4::Third(r) => match r {
             WaitResult::Lagged(count) => {
                 log::warn!("mister mode subscriber lagged by {} messages", count);
+                lag::record(LagChannel::MisterModeChanged);
 
                 // Ignore
                 return Ok(());
@@ -218,6 +491,7 @@ async fn display_task_poll(
         Either4::Fourth(r) => match r {
             WaitResult::Lagged(count) => {
                 log::warn!("mister status subscriber lagged by {} messages", count);
+                lag::record(LagChannel::MisterStatusChanged);
 
                 // Ignore
                 return Ok(());
@@ -233,33 +507,34 @@ async fn display_task_poll(
 
 struct DisplayRenderer<'d> {
     cfg: Config,
-    display: Ssd1306<
-        I2CInterface<I2C<'d, I2C1>>,
-        DisplaySize128x64,
-        BufferedGraphicsMode<DisplaySize128x64>,
-    >,
+    display: DisplayDevice,
+    // Shows the same screen as `display`.
+    mirror: Option<DisplayDevice>,
     bg_style: PrimitiveStyle<BinaryColor>,
     text_style: MonoTextStyle<'d, BinaryColor>,
     status_text_style: MonoTextStyle<'d, BinaryColor>,
-    stale: bool,
-    temp: f32,
-    rh: f32,
+    dirty: DirtyRegions,
+    // `None` until there is a reading (or while the sensor is faulted), shown as placeholders.
+    metrics: Option<SensorMetrics>,
+    sensor_faulted: bool,
     mode: Mode,
     mister_mode: Option<MisterMode>,
     mister_status: Status,
+    pending_status: Option<PendingStatus>,
+    message: Option<ActiveMessage>,
+    last_draw_at: Option<Instant>,
+    drawn_status: Option<StatusContent>,
+    // The LDR reads dark, the panel is off unless woken (by a button press) until `woken_until`.
+    #[cfg(feature = "ldr")]
+    dark: bool,
+    #[cfg(feature = "ldr")]
+    woken_until: Option<Instant>,
+    #[cfg(feature = "ldr")]
+    panel_on: bool,
 }
 
 impl<'d> DisplayRenderer<'d> {
-    fn new(
-        cfg: Config,
-        display: Ssd1306<
-            I2CInterface<I2C<'d, I2C1>>,
-            DisplaySize128x64,
-            BufferedGraphicsMode<DisplaySize128x64>,
-        >,
-        temp: f32,
-        rh: f32,
-    ) -> Self {
+    fn new(cfg: Config, display: DisplayDevice, mirror: Option<DisplayDevice>) -> Self {
         let bg_style = PrimitiveStyleBuilder::new()
             .stroke_color(BinaryColor::Off)
             .stroke_width(1)
@@ -272,119 +547,309 @@ impl<'d> DisplayRenderer<'d> {
         Self {
             cfg,
             display,
+            mirror,
             bg_style,
             text_style,
             status_text_style,
-            stale: true,
-            temp,
-            rh,
+            dirty: DirtyRegions::all(),
+            metrics: sensor::METRICS.read().clone(),
+            sensor_faulted: false,
             mode: Mode::default(),
-            mister_mode: None,
+            mister_mode: mister::ACTIVE_MODE.read().clone(),
             mister_status: mister::STATUS.read().clone().unwrap_or(Status::Off),
+            pending_status: None,
+            message: None,
+            last_draw_at: None,
+            drawn_status: None,
+            #[cfg(feature = "ldr")]
+            dark: false,
+            #[cfg(feature = "ldr")]
+            woken_until: None,
+            #[cfg(feature = "ldr")]
+            panel_on: true,
         }
     }
 
     fn apply_sensor_msg(&mut self, msg: SensorMetrics) {
-        self.temp(msg.temp);
-        self.rh(msg.rh);
+        self.metrics(Some(msg));
+        self.sensor_faulted(false);
     }
 
+    /// The emitter only publishes `None` when the sensor failed (not when it's disabled).
     fn clear_sensor(&mut self) {
-        self.temp(0_f32);
-        self.rh(0_f32);
+        self.metrics(None);
+        self.sensor_faulted(true);
     }
 
-    fn draw(&mut self) -> Result<()> {
-        if !self.stale {
+    fn message(&mut self, msg: DisplayMessage) {
+        if msg.secs == 0 {
+            self.clear_message();
+            return;
+        }
+
+        // Truncate anything that won't fit in the status area.
+        let text: String = msg.text.chars().take(STATUS_MAX_CHARS).collect();
+
+        self.message = Some(ActiveMessage {
+            text,
+            expires_at: Instant::now() + Duration::from_secs(msg.secs as u64),
+        });
+        self.dirty.status = true
+    }
+
+    fn clear_message(&mut self) {
+        if self.message.take().is_some() {
+            self.dirty.status = true
+        }
+    }
+
+    /// When the next (throttled) redraw may happen, `None` if there's nothing to draw.
+    fn redraw_at(&self) -> Option<Instant> {
+        if !self.dirty.any() {
+            return None;
+        }
+
+        match self.last_draw_at {
+            Some(at) => {
+                Some(at + Duration::from_millis(self.cfg.load().display_min_redraw_ms as u64))
+            }
+            None => Some(Instant::now()),
+        }
+    }
+
+    /// When the task next needs to wake (throttled redraw or held status), if at all.
+    fn wake_at(&self) -> Option<Instant> {
+        let status_at = self.pending_status.as_ref().map(|p| p.apply_at);
+        // The panel goes back off when the wake period ends.
+        #[cfg(feature = "ldr")]
+        let status_at = match (
+            status_at,
+            self.woken_until.filter(|at| *at > Instant::now()),
+        ) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+
+        match (self.redraw_at(), status_at) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// Wakes the panel for `LDR_WAKE_MS` while dark.
+    #[cfg(feature = "ldr")]
+    fn wake(&mut self) {
+        if self.dark {
+            self.woken_until = Some(Instant::now() + Duration::from_millis(LDR_WAKE_MS));
+        }
+    }
+
+    /// Switches the panel (and mirror) off while dark and not woken, and back on otherwise.
+    #[cfg(feature = "ldr")]
+    fn apply_panel_power(&mut self) -> Result<()> {
+        let woken = self.woken_until.is_some_and(|at| at > Instant::now());
+        let on = !self.dark || woken;
+        if on == self.panel_on {
             return Ok(());
         }
-        self.stale = false;
 
-        // Temp
-        Rectangle::new(
-            Point::new(0, GAUGE_BOX_OFFSET_Y),
-            Size::new(DISPLAY_HALF_WIDTH, GAUGE_FONT_HEIGHT),
-        )
-        .into_styled(self.bg_style)
-        .draw(&mut self.display)
-        .map_err(|e| display_draw_err(format!("{:?}", e)))?;
+        self.display.set_display_on(on).map_err(map_display_err)?;
+        if let Some(mirror) = self.mirror.as_mut() {
+            mirror.set_display_on(on).map_err(map_display_err)?;
+        }
+        self.panel_on = on;
+        *PANEL_OFF.write() = !on;
 
-        let temp = self.temp.ceil() as u32;
+        log::info!(
+            "Display: {} [dark: {}]",
+            if on { "On" } else { "Off" },
+            self.dark
+        );
 
-        Text::new(
-            format!("{}°C", temp).as_str(),
-            Point::new(
-                calculate_gauge_x(
-                    if temp >= 10 { 4 } else { 3 },
-                    GAUGE_FONT_WIDTH,
-                    GAUGE_PULL_SIDE_PX,
-                ),
-                GAUGE_TEXT_OFFSET_Y,
-            ),
-            self.text_style,
-        )
-        .draw(&mut self.display)
-        .map_err(|e| display_draw_err(format!("{:?}", e)))?;
+        Ok(())
+    }
 
-        // RH
-        Rectangle::new(
-            Point::new(DISPLAY_HALF_WIDTH as i32, GAUGE_BOX_OFFSET_Y),
-            Size::new(DISPLAY_HALF_WIDTH, GAUGE_FONT_HEIGHT),
-        )
-        .into_styled(self.bg_style)
-        .draw(&mut self.display)
-        .map_err(|e| display_draw_err(format!("{:?}", e)))?;
+    /// Shows the held mister status once it has been stable for long enough.
+    fn apply_pending_status(&mut self) {
+        if let Some(pending) = self.pending_status.as_ref() {
+            if pending.apply_at <= Instant::now() {
+                self.mister_status = pending.status;
+                self.pending_status = None;
+                self.dirty.status = true
+            }
+        }
+    }
 
-        Text::with_alignment(
-            format!("{:.1}%", self.rh).as_str(),
-            Point::new(
-                DISPLAY_WIDTH as i32
-                    - calculate_gauge_x(
-                        if self.rh >= 10_f32 { 5 } else { 4 },
+    fn draw(&mut self) -> Result<()> {
+        self.apply_pending_status();
+        // Drawing carries on while the panel is off, so it wakes showing the current state.
+        #[cfg(feature = "ldr")]
+        self.apply_panel_power()?;
+
+        match self.redraw_at() {
+            None => return Ok(()),
+            // Too soon, stays dirty until `redraw_at`.
+            Some(at) if at > Instant::now() => return Ok(()),
+            _ => {}
+        }
+        let dirty = core::mem::take(&mut self.dirty);
+        self.last_draw_at = Some(Instant::now());
+
+        let (temp_text, temp_chars) = self.temp_gauge();
+        let (rh_text, rh_chars) = self.rh_gauge();
+
+        // Temp
+        if dirty.temp {
+            self.draw_item(
+                &Rectangle::new(
+                    Point::new(0, GAUGE_BOX_OFFSET_Y),
+                    Size::new(DISPLAY_HALF_WIDTH, GAUGE_FONT_HEIGHT),
+                )
+                .into_styled(self.bg_style),
+            )?;
+
+            self.draw_item(&Text::new(
+                temp_text.as_str(),
+                Point::new(
+                    calculate_gauge_x(
+                        DISPLAY_HALF_WIDTH,
+                        temp_chars,
                         GAUGE_FONT_WIDTH,
                         GAUGE_PULL_SIDE_PX,
                     ),
-                GAUGE_TEXT_OFFSET_Y,
-            ),
-            self.text_style,
-            Alignment::Right,
-        )
-        .draw(&mut self.display)
-        .map_err(|e| display_draw_err(format!("{:?}", e)))?;
+                    GAUGE_TEXT_OFFSET_Y,
+                ),
+                self.text_style,
+            ))?;
+        }
 
-        // Status Area
-        Rectangle::new(
-            Point::new(0, (DISPLAY_HEIGHT - STATUS_BOX_HEIGHT) as i32),
-            Size::new(DISPLAY_WIDTH, STATUS_BOX_HEIGHT),
-        )
-        .into_styled(self.bg_style)
-        .draw(&mut self.display)
-        .map_err(|e| display_draw_err(format!("{:?}", e)))?;
+        // RH
+        if dirty.rh {
+            self.draw_item(
+                &Rectangle::new(
+                    Point::new(DISPLAY_HALF_WIDTH as i32, GAUGE_BOX_OFFSET_Y),
+                    Size::new(DISPLAY_HALF_WIDTH, GAUGE_FONT_HEIGHT),
+                )
+                .into_styled(self.bg_style),
+            )?;
+
+            self.draw_item(&Text::with_alignment(
+                rh_text.as_str(),
+                Point::new(
+                    DISPLAY_WIDTH as i32
+                        - calculate_gauge_x(
+                            DISPLAY_HALF_WIDTH,
+                            rh_chars,
+                            GAUGE_FONT_WIDTH,
+                            GAUGE_PULL_SIDE_PX,
+                        ),
+                    GAUGE_TEXT_OFFSET_Y,
+                ),
+                self.text_style,
+                Alignment::Right,
+            ))?;
+        }
+
+        // Status Area (also redrawn when its content changed underneath, e.g. the schedule
+        // advanced or the IP changed).
+        let status = self.status_content();
+        let status_changed = self.drawn_status.as_ref() != Some(&status);
+
+        let _ = RENDERED.write().insert(RenderedDisplay {
+            mode: self.mode,
+            temp: temp_text,
+            rh: rh_text,
+            status_text: status.text.clone(),
+            mister_status: status.mister_status.map(mister_status_text),
+        });
+        if dirty.status || status_changed {
+            self.draw_item(
+                &Rectangle::new(
+                    Point::new(0, (DISPLAY_HEIGHT - STATUS_BOX_HEIGHT) as i32),
+                    Size::new(DISPLAY_WIDTH, STATUS_BOX_HEIGHT),
+                )
+                .into_styled(self.bg_style),
+            )?;
+
+            if let Some(text) = status.text.clone() {
+                self.draw_general_status(text)?;
+            }
+            if let Some(mister_status) = status.mister_status {
+                self.draw_mister_status(mister_status)?;
+            }
+
+            self.drawn_status = Some(status);
+        } else if !dirty.temp && !dirty.rh {
+            // Nothing changed on screen.
+            return Ok(());
+        }
+
+        // Only the changed area is sent to the display.
+        self.display.flush().map_err(map_display_err)?;
+        if let Some(mirror) = self.mirror.as_mut() {
+            // A faulty mirror doesn't hold up the primary panel.
+            if let Err(e) = mirror.flush() {
+                log::warn!("Failed to flush mirror display: {:?}", e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Temp gauge text and its width in chars (placeholder without a reading).
+    fn temp_gauge(&self) -> (String, u32) {
+        let decimals = self.cfg.load().display_temp_decimals;
+        let text = match self.metrics.as_ref() {
+            Some(m) => format!("{}°C", display_temp(m.temp, decimals)),
+            None => "--°C".to_string(),
+        };
+
+        gauge_text(text)
+    }
+
+    /// RH gauge text and its width in chars (placeholder without a reading).
+    fn rh_gauge(&self) -> (String, u32) {
+        let decimals = self.cfg.load().display_rh_decimals;
+        let text = match self.metrics.as_ref() {
+            Some(m) => format!("{}%", display_rh(m.rh, decimals)),
+            None => "--%".to_string(),
+        };
+
+        gauge_text(text)
+    }
+
+    fn status_content(&self) -> StatusContent {
+        if let Some(msg) = self.message.as_ref() {
+            return StatusContent::new(Some(msg.text.clone()), None);
+        }
 
         match self.mode {
+            // Takes over the mister screen, a stale (or missing) reading would be misleading.
+            Mode::MisterMode if self.sensor_faulted => {
+                StatusContent::new(Some("SENSOR FAULT".to_string()), None)
+            }
             Mode::MisterMode => match self.mister_mode {
                 Some(MisterMode::Auto) => {
                     let text =
                         match mister::ACTIVE_AUTO_SCHEDULE.get_schedule(self.cfg.load().as_ref()) {
-                            Some(sched) => format!("AUTO {}%", sched.rh.ceil() as u32),
+                            Some(sched) => auto_status_text(sched, self.mister_status),
                             None => "AUTO ??%".to_string(),
                         };
 
-                    self.draw_general_status(text)?;
-                    self.draw_mister_status(self.mister_status)?;
+                    StatusContent::new(Some(text), Some(self.mister_status))
+                }
+                // Reads as e.g. 'MANUAL  ON', with the actual mister status on the right.
+                Some(MisterMode::On) | Some(MisterMode::Off)
+                    if self.cfg.load().display_mode_label =>
+                {
+                    StatusContent::new(Some("MANUAL".to_string()), Some(self.mister_status))
                 }
-                Some(MisterMode::On) => self.draw_mister_status(MisterStatus::On)?,
-                Some(MisterMode::Off) => self.draw_mister_status(MisterStatus::Off)?,
-                None => {}
+                Some(MisterMode::On) => StatusContent::new(None, Some(MisterStatus::On)),
+                Some(MisterMode::Off) => StatusContent::new(None, Some(MisterStatus::Off)),
+                None => StatusContent::new(None, None),
             },
-            Mode::Info => {
-                self.draw_info()?;
-            }
+            Mode::Info => StatusContent::new(Some(info_text()), None),
         }
-
-        self.display.flush().map_err(map_display_err)?;
-
-        Ok(())
     }
 
     fn draw_general_status(&mut self, text: String) -> Result<()> {
@@ -394,89 +859,229 @@ impl<'d> DisplayRenderer<'d> {
             STATUS_BOX_PADDING_X
         };
 
-        Text::new(
+        self.draw_item(&Text::new(
             text.as_str(),
             Point::new(
                 x_offset as i32,
                 (DISPLAY_HEIGHT - STATUS_BOX_PADDING_Y) as i32,
             ),
             self.status_text_style,
-        )
-        .draw(&mut self.display)
-        .map_err(|e| display_draw_err(format!("{:?}", e)))?;
+        ))?;
 
         Ok(())
     }
 
     fn draw_mister_status(&mut self, status: MisterStatus) -> Result<()> {
-        let text = match status {
-            MisterStatus::On => "ON",
-            MisterStatus::Off => "OFF",
-            MisterStatus::Fault => "FAULT",
-        };
-
-        Text::with_alignment(
-            text,
+        self.draw_item(&Text::with_alignment(
+            mister_status_text(status),
             Point::new(
                 (DISPLAY_WIDTH - STATUS_BOX_PADDING_X) as i32,
                 (DISPLAY_HEIGHT - STATUS_BOX_PADDING_Y) as i32,
             ),
             self.status_text_style,
             Alignment::Right,
-        )
-        .draw(&mut self.display)
-        .map_err(|e| display_draw_err(format!("{:?}", e)))?;
+        ))?;
 
         Ok(())
     }
 
-    fn draw_info(&mut self) -> Result<()> {
-        let ip = match IP_ADDRESS.read().as_ref() {
-            Some(ip) => ip.to_string(),
-            None => "NO WIFI".to_string(),
-        };
+    /// Draws to the panel and the mirror (the same layout is shown on both).
+    fn draw_item<D>(&mut self, item: &D) -> Result<()>
+    where
+        D: Drawable<Color = BinaryColor>,
+    {
+        item.draw(&mut self.display)
+            .map_err(|e| display_draw_err(format!("{:?}", e)))?;
+
+        if let Some(mirror) = self.mirror.as_mut() {
+            item.draw(mirror)
+                .map_err(|e| display_draw_err(format!("{:?}", e)))?;
+        }
 
-        self.draw_general_status(ip)
+        Ok(())
     }
 
     // Accessors
 
     fn mode(&mut self, val: Mode) {
         self.mode = val;
-        self.stale = true
+        // Screen switch, force a full redraw.
+        self.dirty = DirtyRegions::all()
     }
 
     fn mister_mode(&mut self, val: Option<MisterMode>) {
         self.mister_mode = val;
-        self.stale = true
+        self.dirty.status = true
     }
 
     fn mister_status(&mut self, val: MisterStatus) {
-        self.mister_status = val;
-        self.stale = true
+        let hold_ms = self.cfg.load().display_status_hold_ms;
+        if hold_ms == 0 {
+            self.mister_status = val;
+            self.dirty.status = true;
+            return;
+        }
+
+        if val == self.mister_status {
+            // Flapped back before the hold elapsed, nothing to show.
+            self.pending_status = None;
+        } else if self.pending_status.as_ref().map(|p| p.status) != Some(val) {
+            self.pending_status = Some(PendingStatus {
+                status: val,
+                apply_at: Instant::now() + Duration::from_millis(hold_ms as u64),
+            });
+        }
+    }
+
+    // Only the displayed (rounded) value changing needs a redraw.
+
+    fn metrics(&mut self, val: Option<SensorMetrics>) {
+        let cfg = self.cfg.load();
+        let temp = |m: &SensorMetrics| display_temp(m.temp, cfg.display_temp_decimals);
+        let rh = |m: &SensorMetrics| display_rh(m.rh, cfg.display_rh_decimals);
+
+        if val.as_ref().map(temp) != self.metrics.as_ref().map(temp) {
+            self.dirty.temp = true
+        }
+        if val.as_ref().map(rh) != self.metrics.as_ref().map(rh) {
+            self.dirty.rh = true
+        }
+        self.metrics = val;
+    }
+
+    fn sensor_faulted(&mut self, val: bool) {
+        if val != self.sensor_faulted {
+            self.dirty.status = true
+        }
+        self.sensor_faulted = val;
+    }
+}
+
+/// Handle on the display bus, shared by the panel and the mirror.
+struct DisplayBusDevice {
+    bus: &'static RefCell<I2C<'static, I2C1>>,
+}
+
+impl embedded_hal_02::blocking::i2c::Write for DisplayBusDevice {
+    type Error = esp_hal::i2c::Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> core::result::Result<(), Self::Error> {
+        self.bus.borrow_mut().write(address, bytes)
     }
+}
 
-    fn temp(&mut self, val: f32) {
-        if val != self.temp {
-            self.temp = val;
-            self.stale = true
+/// Mister status waiting out `display_status_hold_ms` before being shown.
+struct PendingStatus {
+    status: MisterStatus,
+    apply_at: Instant,
+}
+
+/// Regions of the screen needing a redraw.
+#[derive(Copy, Clone, Default)]
+struct DirtyRegions {
+    temp: bool,
+    rh: bool,
+    status: bool,
+}
+
+impl DirtyRegions {
+    fn all() -> Self {
+        Self {
+            temp: true,
+            rh: true,
+            status: true,
         }
     }
 
-    fn rh(&mut self, val: f32) {
-        if val != self.rh {
-            self.rh = val;
-            self.stale = true
+    fn any(&self) -> bool {
+        self.temp || self.rh || self.status
+    }
+}
+
+/// What the status area shows (compared against what was last drawn).
+#[derive(Clone, PartialEq)]
+struct StatusContent {
+    text: Option<String>,
+    mister_status: Option<MisterStatus>,
+}
+
+impl StatusContent {
+    fn new(text: Option<String>, mister_status: Option<MisterStatus>) -> Self {
+        Self {
+            text,
+            mister_status,
         }
     }
 }
 
+fn load_mode(storage: &mut FlashStorage) -> Mode {
+    let mut bytes = [0u8; 1];
+    match storage.read(MODE_FLASH_ADDR, &mut bytes) {
+        Ok(_) => {
+            let mode_u8 = u8::from_be_bytes(bytes);
+            if mode_u8 >= Mode::min() && mode_u8 <= Mode::max() {
+                let mode = Mode::from(mode_u8);
+                log::info!("Restored previous display mode '{:?}' from flash", mode);
+                mode
+            } else {
+                Mode::default()
+            }
+        }
+        Err(_) => Mode::default(),
+    }
+}
+
+fn store_mode(storage: &mut FlashStorage, mode: Mode) -> Result<()> {
+    let mode_u8 = mode as u8;
+    storage
+        .write(MODE_FLASH_ADDR, mode_u8.to_be_bytes().as_ref())
+        .map_err(|e| {
+            general_fault(format!(
+                "Failed to persist display mode to flash storage: {:?}",
+                e
+            ))
+        })?;
+
+    log::info!("Persisted display mode '{:?}' to flash", mode);
+
+    Ok(())
+}
+
 // Models
 
-#[derive(Copy, Clone, PartialEq, Debug)]
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub(crate) enum Mode {
-    MisterMode,
-    Info,
+    MisterMode = 1,
+    Info = 2,
+}
+
+// Screens reachable by cycling (in order).
+static CYCLE_MODES: [Mode; 2] = [Mode::MisterMode, Mode::Info];
+
+impl Mode {
+    pub(crate) fn min() -> u8 {
+        1
+    }
+    pub(crate) fn max() -> u8 {
+        2
+    }
+
+    pub(crate) fn next(&self) -> Self {
+        match CYCLE_MODES.iter().position(|m| m == self) {
+            Some(idx) => CYCLE_MODES[(idx + 1) % CYCLE_MODES.len()],
+            None => CYCLE_MODES[0],
+        }
+    }
+}
+
+impl From<u8> for Mode {
+    fn from(value: u8) -> Self {
+        if value == 2 {
+            Self::Info
+        } else {
+            Self::MisterMode
+        }
+    }
 }
 
 impl Default for Mode {
@@ -488,11 +1093,26 @@ impl Default for Mode {
 #[derive(Copy, Clone)]
 pub(crate) struct ChangeMode {
     mode: Option<Mode>,
+    // Only wakes the panel (in the dark), the screen is unchanged.
+    #[cfg(feature = "ldr")]
+    wake_only: bool,
 }
 
 impl ChangeMode {
     pub(crate) fn new(mode: Option<Mode>) -> Self {
-        Self { mode }
+        Self {
+            mode,
+            #[cfg(feature = "ldr")]
+            wake_only: false,
+        }
+    }
+
+    #[cfg(feature = "ldr")]
+    pub(crate) fn wake() -> Self {
+        Self {
+            mode: None,
+            wake_only: true,
+        }
     }
 }
 
@@ -502,13 +1122,86 @@ impl Default for ChangeMode {
     }
 }
 
+#[derive(Clone)]
+pub(crate) struct DisplayMessage {
+    text: String,
+    secs: u32,
+}
+
+impl DisplayMessage {
+    pub(crate) fn new(text: String, secs: u32) -> Self {
+        Self { text, secs }
+    }
+}
+
+struct ActiveMessage {
+    text: String,
+    expires_at: Instant,
+}
+
+/// Text content of the panel as last drawn.
+#[derive(Clone, Serialize)]
+pub(crate) struct RenderedDisplay {
+    mode: Mode,
+    temp: String,
+    rh: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mister_status: Option<&'static str>,
+}
+
 // Utils
 
-fn calculate_gauge_x(chars: u32, font_width: u32, pull_side_px: u32) -> i32 {
-    let mut x = (((DISPLAY_HALF_WIDTH - (chars * font_width)) / 2) - pull_side_px) as i32;
-    if x < 0 {
-        x = 0;
+fn mister_status_text(status: MisterStatus) -> &'static str {
+    match status {
+        MisterStatus::On => "ON",
+        MisterStatus::Off => "OFF",
+        MisterStatus::Fault => "FAULT",
+    }
+}
+
+/// The step name when it fits beside the mister status, otherwise the step's rh.
+fn auto_status_text(sched: &MisterAutoSchedule, status: MisterStatus) -> String {
+    // Keep a gap before the (right aligned) mister status.
+    let max_chars = STATUS_MAX_CHARS - mister_status_text(status).len() - 1;
+
+    match sched.name.as_deref() {
+        // The font only covers ascii (so len() is also the char count).
+        Some(name) if !name.is_empty() && name.is_ascii() && name.len() <= max_chars => {
+            name.to_string()
+        }
+        _ => format!("AUTO {}%", sched.rh.ceil() as u32),
+    }
+}
+
+fn info_text() -> String {
+    match IP_ADDRESS.read().as_ref() {
+        Some(ip) => ip.to_string(),
+        None => "NO WIFI".to_string(),
     }
+}
 
-    x
+async fn redraw_due(redraw_at: Option<Instant>) {
+    match redraw_at {
+        Some(at) => Timer::at(at).await,
+        None => pending().await,
+    }
+}
+
+#[cfg(feature = "ldr")]
+async fn ldr_changed() -> bool {
+    LDR_DARK.wait().await
+}
+
+#[cfg(not(feature = "ldr"))]
+async fn ldr_changed() {
+    pending().await
+}
+
+async fn message_expiry(expires_at: Option<Instant>) {
+    match expires_at {
+        Some(at) => Timer::at(at).await,
+        None => pending().await,
+    }
 }
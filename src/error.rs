@@ -42,6 +42,19 @@ pub enum Error {
     SensorFault {
         msg: String,
     },
+    InvalidConfig {
+        msg: String,
+    },
+    BadRequest {
+        msg: String,
+    },
+    RequestTooLarge {
+        len: usize,
+        max: usize,
+    },
+    TooManyRequests {
+        retry_after_ms: u32,
+    },
 }
 
 impl fmt::Display for Error {
@@ -74,6 +87,51 @@ impl fmt::Display for Error {
             Error::SensorFault { msg } => {
                 write!(f, "Sensor fault: {:?}", msg)
             }
+            Error::InvalidConfig { msg } => {
+                write!(f, "Invalid config: {}", msg)
+            }
+            Error::BadRequest { msg } => {
+                write!(f, "Bad request: {}", msg)
+            }
+            Error::RequestTooLarge { len, max } => {
+                write!(f, "Request body too large: {} of {} bytes", len, max)
+            }
+            Error::TooManyRequests { retry_after_ms } => {
+                write!(f, "Too many requests, retry in {}ms", retry_after_ms)
+            }
+        }
+    }
+}
+
+impl Error {
+    /// Stable, machine-readable code for API clients.
+    pub(crate) fn code(&self) -> ErrorCode {
+        match self {
+            Error::GeneralFault { .. } => ErrorCode::GeneralFault,
+            Error::Infallible => ErrorCode::Infallible,
+            Error::WifiInit { .. } => ErrorCode::WifiInit,
+            Error::Wifi { .. } => ErrorCode::Wifi,
+            Error::EmbassySpawn { .. } => ErrorCode::EmbassySpawn,
+            Error::EmbassyPubSub { .. } => ErrorCode::EmbassyPubSub,
+            Error::Display { .. } => ErrorCode::Display,
+            Error::DisplayDraw { .. } => ErrorCode::DisplayDraw,
+            Error::SensorFault { .. } => ErrorCode::SensorFault,
+            Error::InvalidConfig { .. } => ErrorCode::InvalidConfig,
+            Error::BadRequest { .. } => ErrorCode::BadRequest,
+            Error::RequestTooLarge { .. } => ErrorCode::RequestTooLarge,
+            Error::TooManyRequests { .. } => ErrorCode::TooManyRequests,
+        }
+    }
+
+    pub(crate) fn status_code(&self) -> StatusCode {
+        match self {
+            Error::InvalidConfig { .. } | Error::BadRequest { .. } => StatusCode::BAD_REQUEST,
+            Error::RequestTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            Error::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Error::SensorFault { .. } | Error::WifiInit { .. } | Error::Wifi { .. } => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 }
@@ -90,15 +148,18 @@ impl IntoResponse for Error {
         connection: Connection<'_, R>,
         response_writer: W,
     ) -> core::result::Result<ResponseSent, W::Error> {
+        let status_code = self.status_code();
+
         response_writer
             .write_response(
                 connection,
                 Json(ApiError::new(
-                    StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                    status_code.as_u16(),
+                    self.code(),
                     format!("{}", self),
                 ))
                 .into_response()
-                .with_status_code(StatusCode::INTERNAL_SERVER_ERROR),
+                .with_status_code(status_code),
             )
             .await
     }
@@ -107,15 +168,39 @@ impl IntoResponse for Error {
 #[derive(Serialize, Clone)]
 pub(crate) struct ApiError {
     code: u16,
+    error_code: ErrorCode,
     message: String,
 }
 
 impl ApiError {
-    fn new(code: u16, message: String) -> Self {
-        Self { code, message }
+    pub(crate) fn new(code: u16, error_code: ErrorCode, message: String) -> Self {
+        Self {
+            code,
+            error_code,
+            message,
+        }
     }
 }
 
+// Serialized by name, existing names must not change (clients match on them).
+#[derive(Serialize, Copy, Clone, Debug)]
+pub(crate) enum ErrorCode {
+    GeneralFault,
+    Infallible,
+    WifiInit,
+    Wifi,
+    EmbassySpawn,
+    EmbassyPubSub,
+    Display,
+    DisplayDraw,
+    SensorFault,
+    InvalidConfig,
+    BadRequest,
+    RequestTooLarge,
+    NotFound,
+    TooManyRequests,
+}
+
 #[allow(dead_code)]
 pub(crate) fn general_fault(msg: String) -> Error {
     Error::GeneralFault { msg }
@@ -126,6 +211,22 @@ pub(crate) fn sensor_fault(msg: String) -> Error {
     Error::SensorFault { msg }
 }
 
+pub(crate) fn invalid_config_err(msg: String) -> Error {
+    Error::InvalidConfig { msg }
+}
+
+pub(crate) fn bad_request_err(msg: String) -> Error {
+    Error::BadRequest { msg }
+}
+
+pub(crate) fn request_too_large_err(len: usize, max: usize) -> Error {
+    Error::RequestTooLarge { len, max }
+}
+
+pub(crate) fn too_many_requests_err(retry_after_ms: u32) -> Error {
+    Error::TooManyRequests { retry_after_ms }
+}
+
 pub(crate) fn map_wifi_init_err(e: InitializationError) -> Error {
     Error::WifiInit { e }
 }
@@ -0,0 +1,151 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use embassy_executor::Spawner;
+use embassy_futures::select::{select3, Either3};
+use embassy_sync::pubsub::WaitResult;
+use embassy_time::{Duration, Timer};
+use heapless::Deque;
+use serde::Serialize;
+use spin::RwLock;
+
+use crate::error::{map_embassy_pub_sub_err, map_embassy_spawn_err, Result};
+use crate::lag;
+use crate::lag::LagChannel;
+use crate::mister::{ModeChangedSubscriber, StatusChangedSubscriber};
+use crate::sensor::SensorSubscriber;
+use crate::utils::get_time_ms;
+use crate::{mister, sensor};
+
+const EVENTS_HISTORY_LEN: usize = 32;
+
+static EVENTS: RwLock<Deque<Event, EVENTS_HISTORY_LEN>> = RwLock::new(Deque::new());
+
+pub(crate) fn init(spawner: &Spawner) -> Result<()> {
+    spawner
+        .spawn(events_task(
+            mister::MODE_CHANGED_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+            mister::STATUS_CHANGED_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+            sensor::CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+        ))
+        .map_err(map_embassy_spawn_err)
+}
+
+/// Records an event in the history ring buffer (dropping the oldest when full).
+pub(crate) fn record(kind: EventKind, detail: Option<String>) {
+    let mut events = EVENTS.write();
+    if events.is_full() {
+        let _ = events.pop_front();
+    }
+
+    let _ = events.push_back(Event {
+        kind,
+        timestamp_ms: get_time_ms(),
+        detail,
+    });
+}
+
+pub(crate) fn history() -> Vec<Event> {
+    EVENTS.read().iter().cloned().collect()
+}
+
+#[embassy_executor::task]
+async fn events_task(
+    mut mode_changed_sub: ModeChangedSubscriber,
+    mut status_changed_sub: StatusChangedSubscriber,
+    mut sensor_sub: SensorSubscriber,
+) {
+    let mut sensor_faulted = false;
+
+    loop {
+        if let Err(e) = events_task_poll(
+            &mut mode_changed_sub,
+            &mut status_changed_sub,
+            &mut sensor_sub,
+            &mut sensor_faulted,
+        )
+        .await
+        {
+            log::warn!("events task poll failed: {:?}", e);
+
+            // Some sleep to avoid thrashing.
+            Timer::after(Duration::from_millis(500)).await;
+        }
+    }
+}
+
+async fn events_task_poll(
+    mode_changed_sub: &mut ModeChangedSubscriber,
+    status_changed_sub: &mut StatusChangedSubscriber,
+    sensor_sub: &mut SensorSubscriber,
+    sensor_faulted: &mut bool,
+) -> Result<()> {
+    match select3(
+        mode_changed_sub.next_message(),
+        status_changed_sub.next_message(),
+        sensor_sub.next_message(),
+    )
+    .await
+    {
+        Either3::First(r) => match r {
+            WaitResult::Lagged(count) => {
+                log::warn!("events mode subscriber lagged by {} messages", count);
+                lag::record(LagChannel::MisterModeChanged);
+            }
+            WaitResult::Message(mode) => {
+                record(EventKind::ModeChanged, Some(format!("{}", mode)));
+            }
+        },
+        Either3::Second(r) => match r {
+            WaitResult::Lagged(count) => {
+                log::warn!("events status subscriber lagged by {} messages", count);
+                lag::record(LagChannel::MisterStatusChanged);
+            }
+            WaitResult::Message(status) => {
+                record(EventKind::StatusChanged, Some(format!("{:?}", status)));
+            }
+        },
+        Either3::Third(r) => match r {
+            WaitResult::Lagged(count) => {
+                log::warn!("events sensor subscriber lagged by {} messages", count);
+                lag::record(LagChannel::Sensor);
+            }
+            WaitResult::Message(metrics) => {
+                // Only record the transition into a fault (not every failed read).
+                if metrics.is_none() && !*sensor_faulted {
+                    record(EventKind::SensorFault, None);
+                }
+
+                *sensor_faulted = metrics.is_none();
+            }
+        },
+    }
+
+    Ok(())
+}
+
+// Models
+
+#[derive(Copy, Clone, Debug, Serialize)]
+pub(crate) enum EventKind {
+    ModeChanged,
+    StatusChanged,
+    ScheduleAdvanced,
+    TargetUnreached,
+    SensorFault,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct Event {
+    kind: EventKind,
+    timestamp_ms: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
@@ -0,0 +1,89 @@
+use core::alloc::{GlobalAlloc, Layout};
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use esp_alloc::EspHeap;
+use serde::Serialize;
+
+use crate::chip_control;
+
+#[cfg(not(feature = "large-heap"))]
+const HEAP_SIZE: usize = 64 * 1024;
+#[cfg(feature = "large-heap")]
+const HEAP_SIZE: usize = 96 * 1024;
+
+#[global_allocator]
+static ALLOCATOR: TrackedHeap = TrackedHeap::empty();
+
+pub(crate) fn init() {
+    static mut HEAP: MaybeUninit<[u8; HEAP_SIZE]> = MaybeUninit::uninit();
+
+    unsafe {
+        ALLOCATOR.heap.init(HEAP.as_mut_ptr() as *mut u8, HEAP_SIZE);
+    }
+}
+
+pub(crate) fn stats() -> HeapStats {
+    HeapStats {
+        size_bytes: HEAP_SIZE,
+        used_bytes: ALLOCATOR.heap.used(),
+        free_bytes: ALLOCATOR.heap.free(),
+        peak_used_bytes: ALLOCATOR.peak_used.load(Ordering::Relaxed),
+    }
+}
+
+/// Called instead of the default handler (which would panic and halt) when an allocation fails.
+#[alloc_error_handler]
+fn alloc_error(layout: Layout) -> ! {
+    log::error!(
+        "Heap exhausted allocating {} bytes (align {}): {:?}",
+        layout.size(),
+        layout.align(),
+        stats()
+    );
+
+    // The executor can't make progress from here, so reset right away rather than via the
+    // chip control task.
+    chip_control::reset_now()
+}
+
+/// Wraps the heap to track the peak usage.
+struct TrackedHeap {
+    heap: EspHeap,
+    peak_used: AtomicUsize,
+}
+
+impl TrackedHeap {
+    const fn empty() -> Self {
+        Self {
+            heap: EspHeap::empty(),
+            peak_used: AtomicUsize::new(0),
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for TrackedHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.heap.alloc(layout);
+        if !ptr.is_null() {
+            self.peak_used
+                .fetch_max(self.heap.used(), Ordering::Relaxed);
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.heap.dealloc(ptr, layout)
+    }
+}
+
+// Models
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct HeapStats {
+    size_bytes: usize,
+    used_bytes: usize,
+    free_bytes: usize,
+    peak_used_bytes: usize,
+}
@@ -0,0 +1,68 @@
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use serde::Serialize;
+
+use crate::error::{map_embassy_spawn_err, Result};
+
+static HEARTBEAT_LOG_INTERVAL_SECS: u64 = 30;
+
+static SENSOR_COUNT: AtomicU32 = AtomicU32::new(0);
+static MISTER_COUNT: AtomicU32 = AtomicU32::new(0);
+static DISPLAY_COUNT: AtomicU32 = AtomicU32::new(0);
+static NETWORK_COUNT: AtomicU32 = AtomicU32::new(0);
+
+pub(crate) fn init(spawner: &Spawner) -> Result<()> {
+    spawner
+        .spawn(heartbeat_task())
+        .map_err(map_embassy_spawn_err)
+}
+
+/// Records a loop iteration for the given task (counters wrap).
+pub(crate) fn beat(task: HeartbeatTask) {
+    let counter = match task {
+        HeartbeatTask::Sensor => &SENSOR_COUNT,
+        HeartbeatTask::Mister => &MISTER_COUNT,
+        HeartbeatTask::Display => &DISPLAY_COUNT,
+        HeartbeatTask::Network => &NETWORK_COUNT,
+    };
+
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn counts() -> HeartbeatCounts {
+    HeartbeatCounts {
+        sensor: SENSOR_COUNT.load(Ordering::Relaxed),
+        mister: MISTER_COUNT.load(Ordering::Relaxed),
+        display: DISPLAY_COUNT.load(Ordering::Relaxed),
+        network: NETWORK_COUNT.load(Ordering::Relaxed),
+    }
+}
+
+#[embassy_executor::task]
+async fn heartbeat_task() {
+    loop {
+        Timer::after(Duration::from_secs(HEARTBEAT_LOG_INTERVAL_SECS)).await;
+
+        log::debug!("Heartbeat: {:?}", counts());
+    }
+}
+
+// Models
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum HeartbeatTask {
+    Sensor,
+    Mister,
+    Display,
+    Network,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct HeartbeatCounts {
+    sensor: u32,
+    mister: u32,
+    display: u32,
+    network: u32,
+}
@@ -0,0 +1,125 @@
+use embassy_executor::Spawner;
+use embassy_sync::pubsub::WaitResult;
+use embassy_time::{Duration, Timer};
+use embedded_hal::digital::OutputPin;
+use esp_hal::gpio::{GpioPin, Output, PushPull, Unknown};
+use fungi_core::heater::heater_decide;
+use spin::RwLock;
+
+use crate::config::{Config, ConfigInstance};
+use crate::error::{map_embassy_pub_sub_err, map_embassy_spawn_err, map_infallible_err, Result};
+use crate::lag;
+use crate::lag::LagChannel;
+use crate::mister::{is_mode_auto, is_paused, AutoScheduleStateOperator, ACTIVE_AUTO_SCHEDULE};
+use crate::sensor;
+use crate::sensor::SensorSubscriber;
+
+const HEATER_GPIO_PIN: u8 = 23;
+// ESP32 GPIO_OUT_W1TC_REG (write 1 to clear output bit).
+const GPIO_OUT_W1TC_REG: u32 = 0x3ff4_400c;
+
+static HEATER_ON: RwLock<bool> = RwLock::new(false);
+
+pub(crate) fn init(
+    cfg: Config,
+    heater_pin: GpioPin<Unknown, HEATER_GPIO_PIN>,
+    spawner: &Spawner,
+) -> Result<()> {
+    spawner
+        .spawn(heater_task(
+            cfg,
+            heater_pin,
+            sensor::CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+        ))
+        .map_err(map_embassy_spawn_err)
+}
+
+#[embassy_executor::task]
+async fn heater_task(
+    cfg: Config,
+    heater_pin: GpioPin<Unknown, HEATER_GPIO_PIN>,
+    mut sensor_sub: SensorSubscriber,
+) {
+    // Never start energized (same as the mister).
+    let mut heater_pin = heater_pin.into_push_pull_output();
+    if let Err(e) = set_heater(&mut heater_pin, false) {
+        log::warn!("Failed to drive heater pin off at startup: {:?}", e);
+    }
+
+    loop {
+        if let Err(e) =
+            heater_task_poll(cfg.load().as_ref(), &mut heater_pin, &mut sensor_sub).await
+        {
+            log::warn!("heater task poll failed: {:?}", e);
+
+            // Some sleep to avoid thrashing.
+            Timer::after(Duration::from_millis(5000)).await;
+        }
+    }
+}
+
+async fn heater_task_poll(
+    cfg: &ConfigInstance,
+    heater_pin: &mut GpioPin<Output<PushPull>, HEATER_GPIO_PIN>,
+    sensor_sub: &mut SensorSubscriber,
+) -> Result<()> {
+    let metrics = match sensor_sub.next_message().await {
+        WaitResult::Lagged(count) => {
+            log::warn!("heater sensor subscriber lagged by {} messages", count);
+            lag::record(LagChannel::Sensor);
+
+            // Ignore
+            return Ok(());
+        }
+        WaitResult::Message(metrics) => metrics,
+    };
+
+    // Only the running auto schedule has a target (temps are Celsius, like the sensor).
+    let target = match ACTIVE_AUTO_SCHEDULE.get_schedule(cfg) {
+        Some(sched) if is_mode_auto() && !is_paused() => sched
+            .target_temp
+            .map(|target_temp| (target_temp, sched.temp_hysteresis(cfg))),
+        _ => None,
+    };
+
+    let on = match (metrics, target) {
+        (Some(metrics), Some((target_temp, hysteresis))) => {
+            heater_decide(metrics.temp, target_temp, hysteresis, is_heater_on())
+        }
+        // Nothing to heat to, or no reading to heat by (fail safe).
+        _ => false,
+    };
+
+    if on != is_heater_on() {
+        log::info!("Heater: {}", if on { "On" } else { "Off" });
+
+        set_heater(heater_pin, on)?;
+    }
+
+    Ok(())
+}
+
+fn set_heater(heater_pin: &mut GpioPin<Output<PushPull>, HEATER_GPIO_PIN>, on: bool) -> Result<()> {
+    if on {
+        heater_pin.set_high().map_err(map_infallible_err)?;
+    } else {
+        heater_pin.set_low().map_err(map_infallible_err)?;
+    }
+
+    *HEATER_ON.write() = on;
+
+    Ok(())
+}
+
+pub(crate) fn is_heater_on() -> bool {
+    *HEATER_ON.read()
+}
+
+/// Last resort power-off of the heater (i.e. from the panic/exception halt path).
+pub(crate) fn force_off() {
+    unsafe {
+        core::ptr::write_volatile(GPIO_OUT_W1TC_REG as *mut u32, 1 << HEATER_GPIO_PIN);
+    }
+}
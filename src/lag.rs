@@ -0,0 +1,70 @@
+//! Counts of pub/sub subscriber lag (messages dropped before a subscriber got to them), per
+//! channel, so a control loop falling behind shows up in `/health` rather than just the logs.
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use serde::Serialize;
+
+static SENSOR_COUNT: AtomicU32 = AtomicU32::new(0);
+static MISTER_CHANGE_MODE_COUNT: AtomicU32 = AtomicU32::new(0);
+static MISTER_MODE_CHANGED_COUNT: AtomicU32 = AtomicU32::new(0);
+static MISTER_STATUS_CHANGED_COUNT: AtomicU32 = AtomicU32::new(0);
+static MISTER_SCHEDULE_CONTROL_COUNT: AtomicU32 = AtomicU32::new(0);
+static DISPLAY_CHANGE_MODE_COUNT: AtomicU32 = AtomicU32::new(0);
+static DISPLAY_MESSAGE_COUNT: AtomicU32 = AtomicU32::new(0);
+static CHIP_CONTROL_COUNT: AtomicU32 = AtomicU32::new(0);
+
+/// Records a lag event (however many messages it dropped) on the given channel.
+pub(crate) fn record(channel: LagChannel) {
+    let counter = match channel {
+        LagChannel::Sensor => &SENSOR_COUNT,
+        LagChannel::MisterChangeMode => &MISTER_CHANGE_MODE_COUNT,
+        LagChannel::MisterModeChanged => &MISTER_MODE_CHANGED_COUNT,
+        LagChannel::MisterStatusChanged => &MISTER_STATUS_CHANGED_COUNT,
+        LagChannel::MisterScheduleControl => &MISTER_SCHEDULE_CONTROL_COUNT,
+        LagChannel::DisplayChangeMode => &DISPLAY_CHANGE_MODE_COUNT,
+        LagChannel::DisplayMessage => &DISPLAY_MESSAGE_COUNT,
+        LagChannel::ChipControl => &CHIP_CONTROL_COUNT,
+    };
+
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn counts() -> LagCounts {
+    LagCounts {
+        sensor: SENSOR_COUNT.load(Ordering::Relaxed),
+        mister_change_mode: MISTER_CHANGE_MODE_COUNT.load(Ordering::Relaxed),
+        mister_mode_changed: MISTER_MODE_CHANGED_COUNT.load(Ordering::Relaxed),
+        mister_status_changed: MISTER_STATUS_CHANGED_COUNT.load(Ordering::Relaxed),
+        mister_schedule_control: MISTER_SCHEDULE_CONTROL_COUNT.load(Ordering::Relaxed),
+        display_change_mode: DISPLAY_CHANGE_MODE_COUNT.load(Ordering::Relaxed),
+        display_message: DISPLAY_MESSAGE_COUNT.load(Ordering::Relaxed),
+        chip_control: CHIP_CONTROL_COUNT.load(Ordering::Relaxed),
+    }
+}
+
+// Models
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum LagChannel {
+    Sensor,
+    MisterChangeMode,
+    MisterModeChanged,
+    MisterStatusChanged,
+    MisterScheduleControl,
+    DisplayChangeMode,
+    DisplayMessage,
+    ChipControl,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct LagCounts {
+    sensor: u32,
+    mister_change_mode: u32,
+    mister_mode_changed: u32,
+    mister_status_changed: u32,
+    mister_schedule_control: u32,
+    display_change_mode: u32,
+    display_message: u32,
+    chip_control: u32,
+}
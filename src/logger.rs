@@ -0,0 +1,101 @@
+//! Logger (as per `esp_println::logger`) with every line prefixed by the device name.
+//!
+//! The level is set at build time but can be overridden at runtime (see `log_level`).
+
+use alloc::string::String;
+use core::str::FromStr;
+
+use esp_println::println;
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use spin::RwLock;
+
+// Set at build time (see the env section in .cargo/config.toml).
+const LOG_LEVEL: Option<&str> = option_env!("ESP_LOGLEVEL");
+
+static LOGGER: DeviceLogger = DeviceLogger;
+// Unset until the config is loaded.
+static DEVICE_NAME: RwLock<Option<String>> = RwLock::new(None);
+
+/// Installed even without a build time level (it starts 'Off') so it can be raised later.
+pub(crate) fn init() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(build_level());
+    }
+}
+
+/// Overrides the build time level, `None` restores it.
+pub(crate) fn set_level(level: Option<LogLevel>) {
+    log::set_max_level(level.map(LevelFilter::from).unwrap_or_else(build_level));
+}
+
+pub(crate) fn level() -> LogLevel {
+    LogLevel::from(log::max_level())
+}
+
+fn build_level() -> LevelFilter {
+    LOG_LEVEL
+        .and_then(|level| LevelFilter::from_str(level).ok())
+        .unwrap_or(LevelFilter::Off)
+}
+
+pub(crate) fn set_device_name(name: String) {
+    let _ = DEVICE_NAME.write().insert(name);
+}
+
+struct DeviceLogger;
+
+impl Log for DeviceLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        match DEVICE_NAME.read().as_deref() {
+            Some(name) => println!("{} [{}] - {}", record.level(), name, record.args()),
+            None => println!("{} - {}", record.level(), record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub(crate) enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(value: LogLevel) -> Self {
+        match value {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+impl From<LevelFilter> for LogLevel {
+    fn from(value: LevelFilter) -> Self {
+        match value {
+            LevelFilter::Off => LogLevel::Off,
+            LevelFilter::Error => LogLevel::Error,
+            LevelFilter::Warn => LogLevel::Warn,
+            LevelFilter::Info => LogLevel::Info,
+            LevelFilter::Debug => LogLevel::Debug,
+            LevelFilter::Trace => LogLevel::Trace,
+        }
+    }
+}
@@ -1,49 +1,63 @@
 #![no_std]
 #![no_main]
 #![feature(type_alias_impl_trait)]
+#![feature(alloc_error_handler)]
 
+#[cfg(feature = "buzzer")]
+mod alarm;
+mod board;
 pub(crate) mod chip_control;
+#[cfg(feature = "scd40")]
+pub(crate) mod co2;
 pub(crate) mod config;
 mod controls;
+mod diag;
 mod display;
 pub(crate) mod error;
+pub(crate) mod events;
+pub(crate) mod heap;
+pub(crate) mod heartbeat;
+#[cfg(feature = "heater")]
+mod heater;
+mod lag;
+mod logger;
 mod mister;
+mod mister_output;
 mod network;
+#[cfg(feature = "selftest")]
+mod selftest;
 pub(crate) mod sensor;
+mod snapshot;
 pub(crate) mod utils;
 
 extern crate alloc;
 
-use core::mem::MaybeUninit;
+use alloc::boxed::Box;
 use embassy_executor::Spawner;
+#[cfg(feature = "sdcard")]
+use embedded_hal_bus::spi::ExclusiveDevice;
 use esp_backtrace as _;
 use esp_hal::efuse::Efuse;
+#[cfg(feature = "sdcard")]
+use esp_hal::spi::{master::Spi, SpiMode};
+#[cfg(feature = "sdcard")]
+use esp_hal::Delay;
 use esp_hal::{clock::ClockControl, embassy, peripherals::Peripherals, prelude::*, IO};
 
 use crate::config::Config;
+use crate::mister_output::MisterOutput;
 use esp_hal::timer::TimerGroup;
 
-#[global_allocator]
-static ALLOCATOR: esp_alloc::EspHeap = esp_alloc::EspHeap::empty();
-
-fn init_heap() {
-    const HEAP_SIZE: usize = 64 * 1024;
-    static mut HEAP: MaybeUninit<[u8; HEAP_SIZE]> = MaybeUninit::uninit();
-
-    unsafe {
-        ALLOCATOR.init(HEAP.as_mut_ptr() as *mut u8, HEAP_SIZE);
-    }
-}
-
 #[main]
 async fn main(spawner: Spawner) {
-    init_heap();
+    heap::init();
 
     // setup logger
     // To change the log_level change the env section in .cargo/config.toml
     // or remove it and set ESP_LOGLEVEL manually before running cargo run
     // this requires a clean rebuild because of https://github.com/rust-lang/cargo/issues/10358
-    esp_println::logger::init_logger_from_env();
+    // (it can also be overridden at runtime, see `log_level` and `/log/level`)
+    logger::init();
 
     let peripherals = Peripherals::take();
     let system = peripherals.SYSTEM.split();
@@ -56,40 +70,99 @@ async fn main(spawner: Spawner) {
         log::error!("Failed to enable esp hal interrupt: {:?}", e);
     }
 
-    let clocks = ClockControl::max(system.clock_control).freeze();
+    // Leaked so the sensor bus can re-create its I2C instance at runtime.
+    let clocks = &*Box::leak(Box::new(ClockControl::max(system.clock_control).freeze()));
 
-    let timer_group0 = TimerGroup::new(peripherals.TIMG0, &clocks);
-    let timer_group1 = TimerGroup::new(peripherals.TIMG1, &clocks);
+    let timer_group0 = TimerGroup::new(peripherals.TIMG0, clocks);
+    let timer_group1 = TimerGroup::new(peripherals.TIMG1, clocks);
 
     log::info!("main init: Started");
     log_chip_info();
 
     // Init embassy
-    embassy::init(&clocks, timer_group0);
+    embassy::init(clocks, timer_group0);
+
+    // Init SD card config override (SCK: 25, MOSI: 26, MISO: 27, CS: 32)
+    #[cfg(feature = "sdcard")]
+    let cfg_override = {
+        let spi = Spi::new(peripherals.SPI2, 400_u32.kHz(), SpiMode::Mode0, clocks)
+            .with_sck(gpio.pins.gpio25)
+            .with_mosi(gpio.pins.gpio26)
+            .with_miso(gpio.pins.gpio27);
+        let cs = gpio.pins.gpio32.into_push_pull_output();
+
+        config::revive_from_sd(
+            ExclusiveDevice::new(spi, cs, Delay::new(clocks)),
+            Delay::new(clocks),
+        )
+    };
+    #[cfg(not(feature = "sdcard"))]
+    let cfg_override = None;
 
     // Init config
-    let cfg = Config::new().expect("failed to load config");
+    let cfg = Config::new(cfg_override).expect("failed to load config");
+    if cfg.first_boot() {
+        log::info!("First boot: no config persisted to flash, using defaults");
+    }
+
+    let device_name = cfg.load().device_name();
+    log::info!("Device name: {}", device_name);
+    logger::set_device_name(device_name);
+    logger::set_level(cfg.load().log_level);
+
+    // Init self-test (before anything else touches flash)
+    #[cfg(feature = "selftest")]
+    if let Err(e) = selftest::init(cfg.clone(), &spawner) {
+        log::error!("Failed to init self-test: {:?}", e);
+    }
+
+    // Init events
+    if let Err(e) = events::init(&spawner) {
+        log::error!("Failed to init events: {:?}", e);
+    }
+
+    // Init heartbeat
+    if let Err(e) = heartbeat::init(&spawner) {
+        log::error!("Failed to init heartbeat: {:?}", e);
+    }
 
     // Init chip control
     if let Err(e) = chip_control::init(cfg.clone(), &spawner) {
         log::error!("Failed to init chip control: {:?}", e);
     }
 
-    if cfg.load().display_enabled {
+    // Init config writer (debounced flash writes)
+    if let Err(e) = config::init(cfg.clone(), &spawner) {
+        log::error!("Failed to init config writer: {:?}", e);
+    }
+
+    // After a crash loop only the network (and API) is started, to fix whatever causes it.
+    let safe_mode = chip_control::is_safe_mode();
+
+    if cfg.load().display_enabled && !safe_mode {
         // Init display
         if let Err(e) = display::init(
             cfg.clone(),
             gpio.pins.gpio19,
             gpio.pins.gpio18,
             peripherals.I2C1,
-            &clocks,
+            clocks,
             &spawner,
         ) {
             log::error!("Failed to init display: {:?}", e);
         }
+
+        #[cfg(feature = "ldr")]
+        if let Err(e) = display::init_ldr(cfg.clone(), gpio.pins.gpio34, peripherals.ADC1, &spawner)
+        {
+            log::error!("Failed to init display LDR: {:?}", e);
+        }
     }
 
-    if cfg.load().network_enabled {
+    if cfg.load().network_enabled && cfg.load().wifi_ssid.is_empty() {
+        // Nothing to connect to (there is no access point fallback to commission from).
+        log::error!("No WIFI SSID configured (set SSID at build time), network disabled");
+    } else if cfg.load().network_enabled {
         // Init network
         if let Err(e) = network::init(
             cfg.clone(),
@@ -97,30 +170,80 @@ async fn main(spawner: Spawner) {
             peripherals.RNG,
             timer_group1,
             system.radio_clock_control,
-            &clocks,
+            clocks,
             &spawner,
         ) {
             log::error!("Failed to init network: {:?}", e);
         }
     }
 
-    if cfg.load().sensor_enabled {
-        // Init sensor
-        if let Err(e) = sensor::init(
+    if safe_mode {
+        log::info!("main init: Completed (safe mode)");
+        return;
+    }
+
+    #[cfg(feature = "scd40")]
+    let co2_enabled = cfg.load().co2_enabled;
+    #[cfg(not(feature = "scd40"))]
+    let co2_enabled = false;
+
+    if cfg.load().sensor_enabled || co2_enabled {
+        // Init sensor bus (shared by all sensors)
+        let sensor_bus = sensor::init_bus(
             cfg.clone(),
             gpio.pins.gpio14,
             gpio.pins.gpio15,
             peripherals.I2C0,
-            &clocks,
-            &spawner,
-        ) {
-            log::error!("Failed to init sensor: {:?}", e);
+            clocks,
+        );
+
+        if cfg.load().sensor_enabled {
+            // Init sensor
+            if let Err(e) = sensor::init(cfg.clone(), sensor_bus, clocks, &spawner) {
+                log::error!("Failed to init sensor: {:?}", e);
+            }
+        }
+
+        #[cfg(feature = "scd40")]
+        if co2_enabled {
+            // Init co2 sensor
+            if let Err(e) = co2::init(cfg.clone(), sensor_bus, &spawner) {
+                log::error!("Failed to init co2 sensor: {:?}", e);
+            }
         }
     }
 
     // Init mister
-    if let Err(e) = mister::init(cfg.clone(), gpio.pins.gpio17, gpio.pins.gpio22, &spawner) {
-        log::error!("Failed to init mister: {:?}", e);
+    let (mister_pwr_pin, status_led_pin) = board::mister_pins!(gpio.pins);
+    #[cfg(not(feature = "mister-soft-start"))]
+    let mister_output = MisterOutput::new(mister_pwr_pin);
+    #[cfg(feature = "mister-soft-start")]
+    let mister_output = MisterOutput::new(
+        mister_pwr_pin,
+        peripherals.LEDC,
+        clocks,
+        cfg.load().mister_soft_start_ms,
+    );
+
+    match mister_output {
+        Ok(mister_output) => {
+            if let Err(e) = mister::init(cfg.clone(), mister_output, status_led_pin, &spawner) {
+                log::error!("Failed to init mister: {:?}", e);
+            }
+        }
+        Err(e) => log::error!("Failed to init mister output: {:?}", e),
+    }
+
+    // Init heater
+    #[cfg(feature = "heater")]
+    if let Err(e) = heater::init(cfg.clone(), gpio.pins.gpio23, &spawner) {
+        log::error!("Failed to init heater: {:?}", e);
+    }
+
+    // Init alarm
+    #[cfg(feature = "buzzer")]
+    if let Err(e) = alarm::init(cfg.clone(), gpio.pins.gpio33, &spawner) {
+        log::error!("Failed to init alarm: {:?}", e);
     }
 
     // Init controls
@@ -128,9 +251,36 @@ async fn main(spawner: Spawner) {
         log::error!("Failed to init controls: {:?}", e);
     }
 
+    // Init mist trigger
+    #[cfg(feature = "mist-trigger")]
+    if let Err(e) = controls::init_mist_trigger(cfg.clone(), gpio.pins.gpio4, &spawner) {
+        log::error!("Failed to init mist trigger: {:?}", e);
+    }
+
+    // Init reset button
+    #[cfg(feature = "reset-button")]
+    if let Err(e) = controls::init_reset_button(cfg.clone(), gpio.pins.gpio13, &spawner) {
+        log::error!("Failed to init reset button: {:?}", e);
+    }
+
     log::info!("main init: Completed");
 }
 
+/// Called by `esp_backtrace` after a panic/exception has been reported.
+#[no_mangle]
+fn custom_halt() -> ! {
+    // Never leave the mister (or heater) running unattended.
+    mister::force_off();
+    #[cfg(feature = "heater")]
+    heater::force_off();
+    #[cfg(feature = "buzzer")]
+    alarm::force_off();
+
+    loop {
+        core::hint::spin_loop()
+    }
+}
+
 fn log_chip_info() {
     let mac_address = Efuse::read_base_mac_address();
     log::info!(
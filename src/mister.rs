@@ -1,56 +1,116 @@
 use alloc::format;
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
 use alloc::sync::Arc;
 use core::fmt::{Display, Formatter};
+use core::future::pending;
 use core::ops::DerefMut;
 
 use embassy_executor::Spawner;
-use embassy_futures::select::{select, Either};
+use embassy_futures::select::{select, select3, select4, Either, Either3, Either4};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::pubsub::{PubSubChannel, Publisher, Subscriber, WaitResult};
-use embassy_time::{Duration, Timer};
+use embassy_sync::signal::Signal;
+use embassy_time::{Duration, Instant, Timer};
 use embedded_hal::digital::{OutputPin, StatefulOutputPin};
 use embedded_storage::{ReadStorage, Storage};
 use esp_hal::gpio::{GpioPin, Output, PushPull, Unknown};
 use esp_storage::FlashStorage;
+use fungi_core::mister::{
+    auto_rh_decide, drive_output, status_steady, AutoRhDecision, AutoRhState, RhBand,
+};
+use fungi_core::schedule::{self, AutoScheduleAction, Program, StepTarget};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use spin::RwLock;
 
+pub(crate) use fungi_core::mister::Status;
+pub(crate) use fungi_core::schedule::{AutoScheduleMode, AutoScheduleState};
+
+use crate::board::{MISTER_POWER_GPIO_PIN, STATUS_LED_GPIO_PIN};
 use crate::config::{Config, ConfigInstance, MisterAutoSchedule};
+use crate::diag::DiagTask;
 use crate::error::{
     general_fault, map_embassy_pub_sub_err, map_embassy_spawn_err, map_infallible_err, Result,
 };
-use crate::sensor;
-use crate::sensor::{SensorMetrics, SensorSubscriber};
+use crate::events::EventKind;
+use crate::heartbeat::HeartbeatTask;
+use crate::lag::LagChannel;
+use crate::mister_output::MisterOutput;
+use crate::sensor::{SensorMetrics, SensorSubscriber, METRICS};
 use crate::utils::get_time_ms;
+use crate::{diag, events, heartbeat, lag, sensor, snapshot};
 
-const MISTER_POWER_GPIO_PIN: u8 = 17;
-const STATUS_LED_GPIO_PIN: u8 = 22;
 const MODE_FLASH_ADDR: u32 = 0x9000;
+// After the display mode byte (0x9001), u16.
+const AUTO_SCHEDULE_IDX_FLASH_ADDR: u32 = 0x9002;
+// ESP32 GPIO_OUT_W1TC_REG (write 1 to clear output bit).
+const GPIO_OUT_W1TC_REG: u32 = 0x3ff4_400c;
+// ESP32 GPIO_FUNC0_OUT_SEL_CFG_REG (one per pin), selecting what drives the pin's output.
+#[cfg(feature = "mister-soft-start")]
+const GPIO_FUNC0_OUT_SEL_CFG_REG: u32 = 0x3ff4_4530;
+// Output select for the plain GPIO output register (rather than a peripheral, i.e. LEDC).
+#[cfg(feature = "mister-soft-start")]
+const GPIO_OUT_SEL_SIMPLE: u32 = 0x100;
+// Faster than this and the blink is indistinguishable from solid.
+const STATUS_LED_FAULT_BLINK_MIN_MS: u32 = 50;
+// On (and off) time of each flash acknowledging a control (see `flash_status_led`).
+const STATUS_LED_FLASH_MS: u64 = 100;
 
 // Mode
-type ChangeModeSubscriber = Subscriber<'static, CriticalSectionRawMutex, ChangeMode, 1, 2, 2>;
+type ChangeModeSubscriber = Subscriber<'static, CriticalSectionRawMutex, ChangeMode, 1, 2, 3>;
 pub(crate) type ChangeModePublisher =
-    Publisher<'static, CriticalSectionRawMutex, ChangeMode, 1, 2, 2>;
-pub(crate) static CHANGE_MODE_CHANNEL: PubSubChannel<CriticalSectionRawMutex, ChangeMode, 1, 2, 2> =
+    Publisher<'static, CriticalSectionRawMutex, ChangeMode, 1, 2, 3>;
+pub(crate) static CHANGE_MODE_CHANNEL: PubSubChannel<CriticalSectionRawMutex, ChangeMode, 1, 2, 3> =
     PubSubChannel::new();
 
-type ModeChangedPublisher = Publisher<'static, CriticalSectionRawMutex, Mode, 1, 2, 1>;
-pub(crate) type ModeChangedSubscriber = Subscriber<'static, CriticalSectionRawMutex, Mode, 1, 2, 1>;
-pub(crate) static MODE_CHANGED_CHANNEL: PubSubChannel<CriticalSectionRawMutex, Mode, 1, 2, 1> =
+type ModeChangedPublisher = Publisher<'static, CriticalSectionRawMutex, Mode, 1, 4, 1>;
+pub(crate) type ModeChangedSubscriber = Subscriber<'static, CriticalSectionRawMutex, Mode, 1, 4, 1>;
+pub(crate) static MODE_CHANGED_CHANNEL: PubSubChannel<CriticalSectionRawMutex, Mode, 1, 4, 1> =
     PubSubChannel::new();
 
+// Schedule (operator control of the auto schedule position)
+type ScheduleControlSubscriber =
+    Subscriber<'static, CriticalSectionRawMutex, ScheduleControl, 1, 1, 1>;
+pub(crate) type ScheduleControlPublisher =
+    Publisher<'static, CriticalSectionRawMutex, ScheduleControl, 1, 1, 1>;
+pub(crate) static SCHEDULE_CONTROL_CHANNEL: PubSubChannel<
+    CriticalSectionRawMutex,
+    ScheduleControl,
+    1,
+    1,
+    1,
+> = PubSubChannel::new();
+
 pub(crate) static ACTIVE_MODE: RwLock<Option<Mode>> = RwLock::new(None);
+// Time the mister was paused (forced 'Off' while preserving the auto schedule position).
+static PAUSED_AT: RwLock<Option<u32>> = RwLock::new(None);
+// End of a manual run (forced 'On' for a while, then back to the mode).
+static MANUAL_RUN_UNTIL: RwLock<Option<Instant>> = RwLock::new(None);
+// The rh is below `mister_rh_hard_floor` (forcing 'On' in Auto).
+static RH_FLOOR_ACTIVE: RwLock<bool> = RwLock::new(false);
+// Operator rh target (i.e. from the API) used in Auto instead of the schedule step's, until it
+// expires.
+static SETPOINT_OVERRIDE: RwLock<Option<SetpointOverride>> = RwLock::new(None);
 
 // Status
 pub(crate) type StatusChangedPublisher =
-    Publisher<'static, CriticalSectionRawMutex, Status, 1, 2, 1>;
+    Publisher<'static, CriticalSectionRawMutex, Status, 1, 6, 1>;
 pub(crate) type StatusChangedSubscriber =
-    Subscriber<'static, CriticalSectionRawMutex, Status, 1, 2, 1>;
-pub(crate) static STATUS_CHANGED_CHANNEL: PubSubChannel<CriticalSectionRawMutex, Status, 1, 2, 1> =
+    Subscriber<'static, CriticalSectionRawMutex, Status, 1, 6, 1>;
+pub(crate) static STATUS_CHANGED_CHANNEL: PubSubChannel<CriticalSectionRawMutex, Status, 1, 6, 1> =
     PubSubChannel::new();
 pub(crate) static STATUS: RwLock<Option<Status>> = RwLock::new(Some(Status::Off));
+pub(crate) static FAULT_REASON: RwLock<Option<String>> = RwLock::new(None);
+// Time of the last status change (0 until the first, i.e. since boot).
+static STATUS_CHANGED_AT: RwLock<u32> = RwLock::new(0);
+// Time of the last mode change (0 until the first, i.e. since boot).
+static MODE_CHANGED_AT: RwLock<u32> = RwLock::new(0);
+
+// Number of flashes for the status LED to show (then return to the status).
+static STATUS_LED_FLASH: Signal<CriticalSectionRawMutex, u8> = Signal::new();
+
+// Burst (the pin cycles while the status remains 'On')
+static BURST: Lazy<RwLock<BurstState>> = Lazy::new(|| RwLock::new(BurstState::new()));
 
 // Auto
 pub(crate) type ActiveAutoScheduleState = Lazy<RwLock<AutoScheduleState>>;
@@ -58,10 +118,11 @@ pub(crate) static ACTIVE_AUTO_SCHEDULE: ActiveAutoScheduleState =
     Lazy::new(|| RwLock::new(AutoScheduleState::default()));
 
 static AUTO_SCHEDULE_PENDING_SLEEP_MS: u32 = 100;
+static AUTO_SCHEDULE_HOLDING_SLEEP_MS: u32 = 5000;
 
 pub(crate) fn init(
     cfg: Config,
-    mister_pwr_pin: GpioPin<Unknown, MISTER_POWER_GPIO_PIN>,
+    mister_pwr_pin: MisterOutput,
     status_led_pin: GpioPin<Unknown, STATUS_LED_GPIO_PIN>,
     spawner: &Spawner,
 ) -> Result<()> {
@@ -84,15 +145,22 @@ pub(crate) fn init(
         ))
         .map_err(map_embassy_spawn_err)?;
 
-    spawner
-        .spawn(mister_status_led_task(
-            cfg.clone(),
-            status_led_pin,
-            STATUS_CHANGED_CHANNEL
-                .subscriber()
-                .map_err(map_embassy_pub_sub_err)?,
-        ))
-        .map_err(map_embassy_spawn_err)?;
+    if cfg.load().status_led_enabled {
+        spawner
+            .spawn(mister_status_led_task(
+                cfg.clone(),
+                status_led_pin,
+                STATUS_CHANGED_CHANNEL
+                    .subscriber()
+                    .map_err(map_embassy_pub_sub_err)?,
+            ))
+            .map_err(map_embassy_spawn_err)?;
+    } else {
+        log::info!(
+            "Mister status LED disabled, leaving GPIO{} unconfigured",
+            STATUS_LED_GPIO_PIN
+        );
+    }
 
     spawner
         .spawn(mister_auto_schedule_task(
@@ -100,6 +168,9 @@ pub(crate) fn init(
             MODE_CHANGED_CHANNEL
                 .subscriber()
                 .map_err(map_embassy_pub_sub_err)?,
+            SCHEDULE_CONTROL_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
         ))
         .map_err(map_embassy_spawn_err)?;
 
@@ -109,20 +180,45 @@ pub(crate) fn init(
 #[embassy_executor::task]
 async fn mister_operation_task(
     cfg: Config,
-    mister_pwr_pin: GpioPin<Unknown, MISTER_POWER_GPIO_PIN>,
+    mut mister_pwr_pin: MisterOutput,
     mut mode_changed_pub: ModeChangedPublisher,
     mut change_mode_sub: ChangeModeSubscriber,
     mut status_changed_pub: StatusChangedPublisher,
     mut sensor_sub: SensorSubscriber,
 ) {
+    // The output was driven 'Off' when created (before anything else), let the power rail
+    // settle before the restored mode can turn it back on.
+    Timer::after(Duration::from_millis(
+        cfg.load().mister_startup_delay_ms as u64,
+    ))
+    .await;
+
     let mut storage = FlashStorage::new();
-    load_mode(&mut storage, &mut mode_changed_pub).await;
+    load_mode(cfg.load().as_ref(), &mut storage, &mut mode_changed_pub).await;
 
-    let mut mister_pwr_pin = mister_pwr_pin.into_push_pull_output();
+    if is_mode_auto() {
+        // Fault straight away if Auto can't function (i.e. no sensor).
+        if let Err(e) = change_status_from_mode(
+            cfg.load().as_ref(),
+            Mode::Auto,
+            &mut mister_pwr_pin,
+            &mut status_changed_pub,
+        )
+        .await
+        {
+            log::warn!("Failed to set mister status from restored mode: {:?}", e);
+        }
+    }
 
     let mut auto_state: Option<AutoRhState> = None;
+    // Consecutive sensor faults while in Auto (see `auto_fault_fallback_count`).
+    let mut auto_fault_count: u32 = 0;
+    // When metrics went missing in Auto (see `auto_metrics_fault_grace_ms`).
+    let mut metrics_missing_since: Option<u32> = None;
 
     loop {
+        heartbeat::beat(HeartbeatTask::Mister);
+
         if let Err(e) = mister_operation_task_poll(
             cfg.load(),
             &mut storage,
@@ -132,6 +228,8 @@ async fn mister_operation_task(
             &mut status_changed_pub,
             &mut sensor_sub,
             &mut auto_state,
+            &mut auto_fault_count,
+            &mut metrics_missing_since,
         )
         .await
         {
@@ -147,57 +245,139 @@ async fn mister_operation_task(
 async fn mister_operation_task_poll(
     cfg: Arc<ConfigInstance>,
     storage: &mut FlashStorage,
-    mister_pwr_pin: &mut GpioPin<Output<PushPull>, MISTER_POWER_GPIO_PIN>,
+    mister_pwr_pin: &mut MisterOutput,
     mode_changed_pub: &mut ModeChangedPublisher,
     change_mode_sub: &mut ChangeModeSubscriber,
     status_changed_pub: &mut StatusChangedPublisher,
     sensor_sub: &mut SensorSubscriber,
     auto_state: &mut Option<AutoRhState>,
+    auto_fault_count: &mut u32,
+    metrics_missing_since: &mut Option<u32>,
 ) -> Result<()> {
-    match select(change_mode_sub.next_message(), sensor_sub.next_message()).await {
-        Either::First(r) => match r {
+    let r = select(
+        select4(
+            change_mode_sub.next_message(),
+            sensor_sub.next_message(),
+            burst_phase_wait(cfg.as_ref()),
+            manual_run_wait(),
+        ),
+        soft_start_due(mister_pwr_pin.soft_start_at()),
+    )
+    .await;
+    let _handling = diag::Handling::start(DiagTask::Mister);
+
+    let r = match r {
+        Either::First(r) => r,
+        Either::Second(_) => return mister_pwr_pin.soft_start_step(),
+    };
+
+    match r {
+        Either4::First(r) => match r {
             WaitResult::Lagged(count) => {
                 log::warn!("mister mode subscriber lagged by {} messages", count);
+                lag::record(LagChannel::MisterChangeMode);
 
                 // Ignore
                 return Ok(());
             }
-            WaitResult::Message(change_mode) => match change_mode.mode {
-                Some(mode) => {
-                    store_mode(storage, mode, mode_changed_pub).await?;
-                    change_status_from_mode(mode, mister_pwr_pin, status_changed_pub).await?;
+            WaitResult::Message(change_mode) => {
+                if let Some(secs) = change_mode.run_secs {
+                    return start_manual_run(secs, mister_pwr_pin, status_changed_pub).await;
                 }
-                None => {
-                    let mode = toggle_mode(storage, mode_changed_pub).await?;
-                    change_status_from_mode(mode, mister_pwr_pin, status_changed_pub).await?;
+
+                // Pausing or changing mode ends a manual run.
+                let _ = MANUAL_RUN_UNTIL.write().take();
+
+                if let Some(paused) = change_mode.pause {
+                    return change_pause(cfg.as_ref(), paused, mister_pwr_pin, status_changed_pub)
+                        .await;
                 }
-            },
+
+                // An explicit mode change ends any pause or setpoint override (and resets the
+                // auto schedule).
+                let _ = PAUSED_AT.write().take();
+                let _ = SETPOINT_OVERRIDE.write().take();
+                *auto_fault_count = 0;
+                let _ = metrics_missing_since.take();
+
+                let was_auto = is_mode_auto();
+                match change_mode.mode {
+                    Some(mode) => {
+                        store_mode(cfg.as_ref(), storage, mode, mode_changed_pub).await?;
+                        change_status_from_mode(
+                            cfg.as_ref(),
+                            mode,
+                            mister_pwr_pin,
+                            status_changed_pub,
+                        )
+                        .await?;
+                    }
+                    None => {
+                        let mode = toggle_mode(cfg.as_ref(), storage, mode_changed_pub).await?;
+                        change_status_from_mode(
+                            cfg.as_ref(),
+                            mode,
+                            mister_pwr_pin,
+                            status_changed_pub,
+                        )
+                        .await?;
+                    }
+                }
+
+                if is_mode_auto() && !was_auto {
+                    auto_prime(cfg.as_ref(), mister_pwr_pin, status_changed_pub).await?;
+                }
+            }
         },
-        Either::Second(r) => {
-            if is_mode_auto() {
+        Either4::Second(r) => {
+            // An empty schedule already faulted on entering Auto.
+            if is_mode_auto()
+                && !is_paused()
+                && !is_manual_run()
+                && !cfg.mister_auto_schedule.is_empty()
+            {
                 match r {
                     WaitResult::Lagged(count) => {
                         log::warn!("sensor subscriber lagged by {} messages", count);
+                        lag::record(LagChannel::Sensor);
 
                         // Ignore
                         return Ok(());
                     }
                     WaitResult::Message(metrics) => {
+                        auto_schedule_clamp(cfg.as_ref());
+
                         match ACTIVE_AUTO_SCHEDULE.get_schedule(cfg.as_ref()) {
                             Some(sched) => {
-                                mister_auto_rh_poll(
+                                let faulted = mister_auto_rh_poll(
                                     cfg.clone(),
                                     auto_state,
-                                    sched.rh.clone(),
+                                    metrics_missing_since,
+                                    sched,
                                     metrics,
                                     mister_pwr_pin,
                                     status_changed_pub,
                                 )
                                 .await?;
+
+                                auto_fault_fallback_check(
+                                    cfg.as_ref(),
+                                    faulted,
+                                    auto_fault_count,
+                                    storage,
+                                    mister_pwr_pin,
+                                    mode_changed_pub,
+                                    status_changed_pub,
+                                )
+                                .await?;
                             }
                             None => {
-                                change_status(Status::Fault, mister_pwr_pin, status_changed_pub)
-                                    .await?;
+                                change_status_fault(
+                                    "no valid auto schedule".to_string(),
+                                    mister_pwr_pin,
+                                    status_changed_pub,
+                                )
+                                .await?;
 
                                 // Clear state.
                                 let _ = auto_state.take();
@@ -212,166 +392,321 @@ async fn mister_operation_task_poll(
                 }
             }
         }
+        Either4::Third(_) => burst_toggle(mister_pwr_pin)?,
+        Either4::Fourth(_) => {
+            let _ = MANUAL_RUN_UNTIL.write().take();
+            log::info!("Mister manual run finished");
+
+            // Back to what the mode (or a pause) calls for.
+            let mode = ACTIVE_MODE.read().clone();
+            match mode {
+                Some(_) if is_paused() => {
+                    change_status(Status::Off, mister_pwr_pin, status_changed_pub).await?
+                }
+                Some(mode) => {
+                    change_status_from_mode(cfg.as_ref(), mode, mister_pwr_pin, status_changed_pub)
+                        .await?
+                }
+                None => change_status(Status::Off, mister_pwr_pin, status_changed_pub).await?,
+            }
+        }
     }
 
     Ok(())
 }
 
-struct AutoRhState {
-    status: Status,
-    cycle_start_time: u32,
+/// Forces the mister 'On' for `secs` regardless of the mode (restarting any run in progress).
+async fn start_manual_run(
+    secs: u32,
+    mister_pwr_pin: &mut MisterOutput,
+    status_changed_pub: &mut StatusChangedPublisher,
+) -> Result<()> {
+    let _ = MANUAL_RUN_UNTIL
+        .write()
+        .insert(Instant::now() + Duration::from_secs(secs as u64));
+    log::info!("Mister manual run for {} seconds", secs);
+
+    change_status(Status::On, mister_pwr_pin, status_changed_pub).await
+}
+
+/// Primes on switching into Auto, a manual run of `mister_auto_prime_secs` (the auto loop takes
+/// over once it ends) unless the rh is already above the step's on threshold or isn't known.
+async fn auto_prime(
+    cfg: &ConfigInstance,
+    mister_pwr_pin: &mut MisterOutput,
+    status_changed_pub: &mut StatusChangedPublisher,
+) -> Result<()> {
+    // Disabled, or Auto faulted (i.e. no sensor).
+    if cfg.mister_auto_prime_secs == 0 || !matches!(STATUS.read().as_ref(), Some(&Status::Off)) {
+        return Ok(());
+    }
+
+    let rh = METRICS.read().as_ref().map(|m| m.rh);
+    let on_rh = ACTIVE_AUTO_SCHEDULE.get_schedule(cfg).map(|s| s.on_rh(cfg));
+    match (rh, on_rh) {
+        (Some(rh), Some(on_rh)) if rh < on_rh => {
+            log::info!("Priming Auto [rh: {}, on rh: {}]", rh, on_rh);
+
+            start_manual_run(
+                cfg.mister_auto_prime_secs,
+                mister_pwr_pin,
+                status_changed_pub,
+            )
+            .await
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Resolves when the manual run ends (never if there isn't one).
+async fn manual_run_wait() {
+    let until = *MANUAL_RUN_UNTIL.read();
+    match until {
+        Some(until) => Timer::at(until).await,
+        None => pending().await,
+    }
+}
+
+async fn soft_start_due(at: Option<Instant>) {
+    match at {
+        Some(at) => Timer::at(at).await,
+        None => pending().await,
+    }
+}
+
+struct BurstState {
+    off_phase: bool,
+    phase_start: Instant,
 }
 
-impl AutoRhState {
-    fn new(status: Status, cycle_start_time: u32) -> Self {
+impl BurstState {
+    fn new() -> Self {
         Self {
-            status,
-            cycle_start_time,
+            off_phase: false,
+            phase_start: Instant::now(),
         }
     }
 }
 
+/// Resolves when the current burst phase ends (never if not bursting).
+async fn burst_phase_wait(cfg: &ConfigInstance) {
+    if !cfg.mister_burst_enabled() || !matches!(STATUS.read().as_ref(), Some(&Status::On)) {
+        return pending().await;
+    }
+
+    let (off_phase, phase_start) = {
+        let burst = BURST.read();
+        (burst.off_phase, burst.phase_start)
+    };
+
+    let phase_ms = if off_phase {
+        cfg.mister_burst_off_ms
+    } else {
+        cfg.mister_burst_on_ms
+    };
+
+    Timer::at(phase_start + Duration::from_millis(phase_ms as u64)).await
+}
+
+fn burst_toggle(mister_pwr_pin: &mut MisterOutput) -> Result<()> {
+    let off_phase = {
+        let mut burst = BURST.write();
+        burst.off_phase = !burst.off_phase;
+        burst.phase_start = Instant::now();
+        burst.off_phase
+    };
+
+    if off_phase {
+        mister_pwr_pin.set_off()
+    } else {
+        mister_pwr_pin.set_on()
+    }
+}
+
+/// Returns true if the status was faulted (no metrics, past the grace period).
 async fn mister_auto_rh_poll(
     cfg: Arc<ConfigInstance>,
     state: &mut Option<AutoRhState>,
-    target_rh: f32,
+    metrics_missing_since: &mut Option<u32>,
+    sched: &MisterAutoSchedule,
     metrics: Option<SensorMetrics>,
-    mister_pwr_pin: &mut GpioPin<Output<PushPull>, MISTER_POWER_GPIO_PIN>,
+    mister_pwr_pin: &mut MisterOutput,
     status_changed_pub: &mut StatusChangedPublisher,
-) -> Result<()> {
-    match metrics {
-        Some(metrics) => {
-            let status = STATUS.read().clone();
-            let rh_on = cfg.mister_auto_on_rh(target_rh);
-            let rh_off = cfg.mister_auto_off_rh(target_rh);
-
-            // Verify state is accurate.
-            if let Some(cur) = state.as_ref() {
-                if let Some(status) = status.as_ref() {
-                    if !cur.status.eq(status) {
-                        // Clear state.
-                        let _ = state.take();
-                    }
-                }
-            }
-
-            // Determine new status
-            let new_status = if metrics.rh <= rh_on {
-                Status::On
-            } else if metrics.rh >= rh_off {
-                Status::Off
-            } else {
-                // If rh between on and off threshold preserve status (either 'rising' or 'falling').
-                status.clone().unwrap_or(Status::Off)
-            };
-
-            // Change status with guarding against flapping too fast
-            if let Some(status) = status.as_ref() {
-                if !new_status.eq(status) {
-                    match state.take() {
-                        Some(mut cur) => {
-                            // Check threshold and ignore event if required.
-                            if (get_time_ms() - cur.cycle_start_time)
-                                >= cfg.mister_auto_duration_min_ms
-                            {
-                                cur.cycle_start_time = get_time_ms();
-
-                                change_status(new_status, mister_pwr_pin, status_changed_pub)
-                                    .await?;
-                            }
+) -> Result<bool> {
+    let rh = metrics.map(|m| m.rh);
+
+    if metrics_fault_grace(
+        rh.is_some(),
+        metrics_missing_since,
+        get_time_ms(),
+        cfg.auto_metrics_fault_grace_ms,
+    ) {
+        log::warn!("No metrics returned by sensor, holding the mister status (grace period)");
+        return Ok(false);
+    }
 
-                            let _ = state.insert(cur);
+    let floor_active = match (rh, cfg.mister_rh_hard_floor) {
+        (Some(rh), Some(floor)) => rh < floor,
+        _ => false,
+    };
+    let was_floor_active = core::mem::replace(&mut *RH_FLOOR_ACTIVE.write(), floor_active);
+    if floor_active && !was_floor_active {
+        log::warn!(
+            "RH below the hard floor, forcing mister 'On' [rh: {:?}]",
+            rh
+        );
+    } else if !floor_active && was_floor_active {
+        log::info!("RH back above the hard floor [rh: {:?}]", rh);
+    }
 
-                            Ok(())
-                        }
-                        None => {
-                            let _ = state.insert(AutoRhState::new(new_status, get_time_ms()));
-                            change_status(new_status, mister_pwr_pin, status_changed_pub).await
-                        }
-                    }
-                } else {
-                    // This just verifies pin state.
-                    change_status(new_status, mister_pwr_pin, status_changed_pub).await
-                }
-            } else {
-                // Assume first init (shouldn't ever be None here though).
+    {
+        let mut setpoint = SETPOINT_OVERRIDE.write();
+        if setpoint.as_ref().is_some_and(|o| o.remaining_ms() == 0) {
+            let _ = setpoint.take();
+            log::info!("Setpoint override expired, back to the auto schedule");
+        }
+    }
 
-                // Clear state.
-                let _ = state.take();
+    let (on_rh, off_rh) = match setpoint_override() {
+        Some(o) => (o.on_rh(cfg.as_ref()), o.off_rh(cfg.as_ref())),
+        None => (sched.on_rh(cfg.as_ref()), sched.off_rh(cfg.as_ref())),
+    };
 
-                change_status(new_status, mister_pwr_pin, status_changed_pub).await
-            }
+    let decision = auto_rh_decide(
+        rh,
+        floor_active,
+        RhBand::new(on_rh, off_rh),
+        STATUS.read().clone(),
+        state,
+        get_time_ms(),
+        cfg.mister_auto_duration_min_ms,
+    );
+
+    match decision {
+        AutoRhDecision::Apply(new_status) => {
+            change_status(new_status, mister_pwr_pin, status_changed_pub).await?
         }
-        None => {
+        AutoRhDecision::Guarded => {}
+        AutoRhDecision::Fault => {
             log::warn!("No metrics returned by sensor, setting mister status to 'Fault'");
 
-            // Clear state.
-            let _ = state.take();
-
-            change_status(Status::Fault, mister_pwr_pin, status_changed_pub).await
+            change_status_fault(
+                "no metrics returned by sensor".to_string(),
+                mister_pwr_pin,
+                status_changed_pub,
+            )
+            .await?
         }
     }
-}
 
-#[derive(Clone, Copy, Serialize)]
-pub(crate) enum AutoScheduleMode {
-    Initial,
-    Pending,
-    Running,
+    Ok(decision == AutoRhDecision::Fault)
 }
 
-#[derive(Clone)]
-pub(crate) struct AutoScheduleState {
-    pub(crate) mode: AutoScheduleMode,
-    pub(crate) idx: usize,
-    // Time the schedule was activated (used to detect failed rises/falls).
-    pub(crate) start_time: u32,
-    // Time the humidity for the schedule was first met to begin countdown.
-    pub(crate) run_start_time: u32,
+/// True while missing metrics are within `grace_ms` of first going missing (the status is held
+/// rather than faulted), reset once metrics return.
+fn metrics_fault_grace(
+    has_metrics: bool,
+    missing_since: &mut Option<u32>,
+    now: u32,
+    grace_ms: u32,
+) -> bool {
+    if has_metrics {
+        let _ = missing_since.take();
+        return false;
+    }
+
+    let since = *missing_since.get_or_insert(now);
+
+    now.wrapping_sub(since) < grace_ms
 }
 
-impl AutoScheduleState {
-    fn new(mode: AutoScheduleMode, idx: usize, start_time: u32, run_start_time: u32) -> Self {
-        Self {
-            mode,
-            idx,
-            start_time,
-            run_start_time,
-        }
+/// Drops Auto to (persisted) 'Off' after `auto_fault_fallback_count` consecutive sensor faults.
+async fn auto_fault_fallback_check(
+    cfg: &ConfigInstance,
+    faulted: bool,
+    auto_fault_count: &mut u32,
+    storage: &mut FlashStorage,
+    mister_pwr_pin: &mut MisterOutput,
+    mode_changed_pub: &mut ModeChangedPublisher,
+    status_changed_pub: &mut StatusChangedPublisher,
+) -> Result<()> {
+    if !faulted {
+        *auto_fault_count = 0;
+        return Ok(());
     }
 
-    fn reset(&mut self) {
-        self.mode = AutoScheduleMode::Initial;
-        self.idx = 0;
-        self.start_time = 0;
-        self.run_start_time = 0;
+    *auto_fault_count = auto_fault_count.saturating_add(1);
+    if cfg.auto_fault_fallback_count == 0 || *auto_fault_count < cfg.auto_fault_fallback_count {
+        return Ok(());
     }
 
-    pub(crate) fn total_ms(&self) -> u32 {
-        get_time_ms() - self.start_time
+    log::error!(
+        "!!! Sensor faulted {} times in a row, falling back from mode 'Auto' to 'Off' \
+        (set the mode again to resume) !!!",
+        auto_fault_count
+    );
+
+    *auto_fault_count = 0;
+
+    store_mode(cfg, storage, Mode::Off, mode_changed_pub).await?;
+    change_status_from_mode(cfg, Mode::Off, mister_pwr_pin, status_changed_pub).await
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum ScheduleControl {
+    Goto(usize),
+    Next,
+}
+
+/// Firmware side of the (`fungi-core`) auto schedule state, reading the clock and config.
+pub(crate) trait AutoScheduleStateExt {
+    fn total_ms(&self) -> u32;
+
+    fn running_ms(&self) -> u32;
+
+    fn remaining_ms(&self, cfg: &ConfigInstance) -> Option<u32>;
+
+    fn program_elapsed_secs(&self, cfg: &ConfigInstance) -> u32;
+
+    fn get_auto_schedule<'a>(&self, cfg: &'a ConfigInstance) -> Option<&'a MisterAutoSchedule>;
+}
+
+impl AutoScheduleStateExt for AutoScheduleState {
+    fn total_ms(&self) -> u32 {
+        self.total_ms_at(get_time_ms())
     }
 
-    pub(crate) fn running_ms(&self) -> u32 {
-        get_time_ms() - self.run_start_time
+    fn running_ms(&self) -> u32 {
+        self.running_ms_at(get_time_ms())
     }
 
-    pub(crate) fn remaining_ms(&self, cfg: &ConfigInstance) -> Option<u32> {
+    fn remaining_ms(&self, cfg: &ConfigInstance) -> Option<u32> {
         match self.get_auto_schedule(cfg) {
-            Some(sched) => Some((sched.run_secs * 1000) - self.running_ms()),
+            // Condition driven, there is no countdown.
+            Some(sched) if sched.hold_until_target => None,
+            Some(sched) => Some((sched.run_secs * 1000).saturating_sub(self.running_ms())),
             None => None,
         }
     }
-    pub(crate) fn get_auto_schedule<'a>(
-        &self,
-        cfg: &'a ConfigInstance,
-    ) -> Option<&'a MisterAutoSchedule> {
-        cfg.mister_auto_schedule.get(self.idx)
+
+    /// Run time completed across the whole program (previous steps plus the current one).
+    fn program_elapsed_secs(&self, cfg: &ConfigInstance) -> u32 {
+        let previous_secs: u32 = cfg
+            .mister_auto_schedule
+            .iter()
+            .take(self.idx)
+            .map(|s| s.run_secs)
+            .sum();
+
+        match self.mode {
+            AutoScheduleMode::Running => previous_secs + (self.running_ms() / 1000),
+            AutoScheduleMode::Holding => cfg.mister_auto_schedule_total_secs(),
+            _ => previous_secs,
+        }
     }
-}
 
-impl Default for AutoScheduleState {
-    fn default() -> Self {
-        Self::new(AutoScheduleMode::Initial, 0, 0, 0)
+    fn get_auto_schedule<'a>(&self, cfg: &'a ConfigInstance) -> Option<&'a MisterAutoSchedule> {
+        cfg.mister_auto_schedule.get(self.idx)
     }
 }
 
@@ -421,6 +756,10 @@ impl AutoScheduleStateOperator for ActiveAutoScheduleState {
     fn update(&self, cb: impl FnOnce(&mut AutoScheduleState)) {
         let mut wr = self.write();
         cb(wr.deref_mut());
+
+        let state = wr.clone();
+        drop(wr);
+        snapshot::update(|s| s.auto_schedule = state);
     }
 
     fn get_schedule<'a>(&self, cfg: &'a ConfigInstance) -> Option<&'a MisterAutoSchedule> {
@@ -429,9 +768,25 @@ impl AutoScheduleStateOperator for ActiveAutoScheduleState {
 }
 
 #[embassy_executor::task]
-async fn mister_auto_schedule_task(cfg: Config, mut mode_changed_sub: ModeChangedSubscriber) {
+async fn mister_auto_schedule_task(
+    cfg: Config,
+    mut mode_changed_sub: ModeChangedSubscriber,
+    mut schedule_control_sub: ScheduleControlSubscriber,
+) {
+    let mut storage = FlashStorage::new();
+    // Resume a program interrupted by a reboot (only used for the first start).
+    let mut restore_idx = load_auto_schedule_idx(&mut storage);
+
     loop {
-        match mister_auto_schedule_task_poll(cfg.load(), &mut mode_changed_sub).await {
+        match mister_auto_schedule_task_poll(
+            cfg.load(),
+            &mut storage,
+            &mut restore_idx,
+            &mut mode_changed_sub,
+            &mut schedule_control_sub,
+        )
+        .await
+        {
             Ok(_) => {
                 // Yield.
                 Timer::after(Duration::from_millis(50)).await;
@@ -449,8 +804,23 @@ async fn mister_auto_schedule_task(cfg: Config, mut mode_changed_sub: ModeChange
 
 async fn mister_auto_schedule_task_poll(
     cfg: Arc<ConfigInstance>,
+    storage: &mut FlashStorage,
+    restore_idx: &mut Option<usize>,
     mode_changed_sub: &mut ModeChangedSubscriber,
+    schedule_control_sub: &mut ScheduleControlSubscriber,
 ) -> Result<()> {
+    if !cfg.sensor_enabled || cfg.mister_auto_schedule.is_empty() {
+        // Auto can't run without a sensor or schedule (the mister faults instead).
+        return Ok(());
+    }
+
+    auto_schedule_clamp(cfg.as_ref());
+
+    if is_paused() {
+        // Hold the schedule position (the timers are shifted on resume).
+        return Ok(());
+    }
+
     // Init
     if matches!(ACTIVE_AUTO_SCHEDULE.mode(), AutoScheduleMode::Initial) {
         if !is_mode_auto() {
@@ -458,7 +828,23 @@ async fn mister_auto_schedule_task_poll(
         }
 
         // Initialize.
-        mister_auto_schedule_start(cfg.as_ref(), 0).await?;
+        let idx = match restore_idx.take() {
+            Some(idx) if idx < cfg.mister_auto_schedule.len() => {
+                log::info!("Restored mister auto schedule '{}' from flash", idx);
+                idx
+            }
+            Some(idx) => {
+                // The schedule was edited (shortened) since.
+                log::warn!(
+                    "Restored mister auto schedule '{}' out of range, starting at '0'",
+                    idx
+                );
+                0
+            }
+            None => 0,
+        };
+
+        mister_auto_schedule_start(cfg.as_ref(), storage, idx).await?;
     } else if !is_mode_auto() {
         ACTIVE_AUTO_SCHEDULE.update(|s| s.reset());
         return Ok(());
@@ -469,9 +855,10 @@ async fn mister_auto_schedule_task_poll(
 
     let sleep_ms = match ACTIVE_AUTO_SCHEDULE.mode() {
         AutoScheduleMode::Pending => AUTO_SCHEDULE_PENDING_SLEEP_MS,
+        AutoScheduleMode::Running if sched.hold_until_target => AUTO_SCHEDULE_PENDING_SLEEP_MS,
         AutoScheduleMode::Running => {
             if ACTIVE_AUTO_SCHEDULE.run_start_time() > 0 {
-                (sched.run_secs * 1000) - ACTIVE_AUTO_SCHEDULE.running_ms()
+                (sched.run_secs * 1000).saturating_sub(ACTIVE_AUTO_SCHEDULE.running_ms())
             } else {
                 ACTIVE_AUTO_SCHEDULE.update(|s| s.reset());
 
@@ -480,25 +867,32 @@ async fn mister_auto_schedule_task_poll(
                 ));
             }
         }
-        _ => unreachable!(),
+        AutoScheduleMode::Holding => AUTO_SCHEDULE_HOLDING_SLEEP_MS,
+        AutoScheduleMode::Initial => {
+            return Err(general_fault(
+                "auto schedule still 'Initial' after start!".to_string(),
+            ));
+        }
     };
 
     if sleep_ms <= 0 {
-        return mister_auto_schedule_check(cfg.as_ref()).await;
+        return mister_auto_schedule_check(cfg.as_ref(), storage).await;
     }
 
-    match select(
+    match select3(
         mode_changed_sub.next_message(),
+        schedule_control_sub.next_message(),
         Timer::after(Duration::from_millis(sleep_ms as u64)),
     )
     .await
     {
-        Either::First(r) => match r {
+        Either3::First(r) => match r {
             WaitResult::Lagged(count) => {
                 log::warn!(
                     "mister mode changed subscriber lagged by {} messages",
                     count
                 );
+                lag::record(LagChannel::MisterModeChanged);
 
                 // Ignore
                 Ok(())
@@ -510,86 +904,202 @@ async fn mister_auto_schedule_task_poll(
                 Ok(())
             }
         },
-        Either::Second(_) => mister_auto_schedule_check(cfg.as_ref()).await,
+        Either3::Second(r) => match r {
+            WaitResult::Lagged(count) => {
+                log::warn!(
+                    "mister schedule control subscriber lagged by {} messages",
+                    count
+                );
+                lag::record(LagChannel::MisterScheduleControl);
+
+                // Ignore
+                Ok(())
+            }
+            WaitResult::Message(control) => {
+                mister_auto_schedule_control(cfg.as_ref(), storage, control)
+            }
+        },
+        Either3::Third(_) => mister_auto_schedule_check(cfg.as_ref(), storage).await,
     }
 }
 
-async fn mister_auto_schedule_start(cfg: &ConfigInstance, idx: usize) -> Result<()> {
-    ACTIVE_AUTO_SCHEDULE.update(|s| {
-        s.reset();
-        s.idx = idx;
-        s.mode = AutoScheduleMode::Pending;
-        s.start_time = get_time_ms();
-    });
+async fn mister_auto_schedule_start(
+    cfg: &ConfigInstance,
+    storage: &mut FlashStorage,
+    idx: usize,
+) -> Result<()> {
+    ACTIVE_AUTO_SCHEDULE.update(|s| *s = AutoScheduleState::pending(idx, get_time_ms()));
+
+    mister_auto_schedule_started(cfg, storage, idx)
+}
 
+fn mister_auto_schedule_started(
+    cfg: &ConfigInstance,
+    storage: &mut FlashStorage,
+    idx: usize,
+) -> Result<()> {
     let sched = get_auto_schedule_checked(cfg)?;
     log::info!("Started mister auto schedule '{}' [{:?}]", idx, sched);
 
+    if let Err(e) = store_auto_schedule_idx(storage, idx) {
+        log::warn!("Failed to persist mister auto schedule: {:?}", e);
+    }
+
+    events::record(
+        EventKind::ScheduleAdvanced,
+        Some(format!("idx: {}, rh: {}", idx, sched.rh)),
+    );
+
     Ok(())
 }
 
-async fn mister_auto_schedule_next(cfg: &ConfigInstance) -> Result<()> {
-    let cur_idx = ACTIVE_AUTO_SCHEDULE.idx();
-    if cfg.mister_auto_schedule.len() >= cur_idx + 2 {
-        mister_auto_schedule_start(cfg, cur_idx + 1).await
-    } else {
-        mister_auto_schedule_start(cfg, 0).await
+async fn mister_auto_schedule_check(
+    cfg: &ConfigInstance,
+    storage: &mut FlashStorage,
+) -> Result<()> {
+    if is_paused() {
+        return Ok(());
     }
-}
 
-async fn mister_auto_schedule_check(cfg: &ConfigInstance) -> Result<()> {
-    let sched = get_auto_schedule_checked(cfg)?;
+    let state = ACTIVE_AUTO_SCHEDULE.read().clone();
+    let rh = sensor::METRICS.read().as_ref().map(|m| m.rh);
 
-    match sensor::METRICS.read().clone() {
-        Some(metrics) => {
-            match ACTIVE_AUTO_SCHEDULE.mode() {
-                AutoScheduleMode::Pending => {
-                    let rh_on = cfg.mister_auto_on_rh(sched.rh);
-                    let rh_off = cfg.mister_auto_off_rh(sched.rh);
-
-                    let should_run = if metrics.rh >= rh_on && metrics.rh <= rh_off {
-                        log::info!("Mister auto schedule ('{}') now 'Running' [rh '{}' >= '{}' && <= '{}']",
-                            ACTIVE_AUTO_SCHEDULE.idx(), metrics.rh, rh_on, rh_off);
-
-                        true
-                    } else if let Some(max_wait_secs) = sched.max_wait_secs {
-                        if ACTIVE_AUTO_SCHEDULE.total_ms() >= max_wait_secs * 1000 {
-                            log::warn!("Mister auto schedule ('{}') now 'Running' [time-out waiting for rh '{}' >= '{}' && <= '{}']",
-                                ACTIVE_AUTO_SCHEDULE.idx(), metrics.rh, rh_on, rh_off);
-
-                            true
-                        } else {
-                            false
-                        }
-                    } else {
-                        false
-                    };
-
-                    if should_run {
-                        ACTIVE_AUTO_SCHEDULE.update(|s| {
-                            s.run_start_time = get_time_ms();
-                            s.mode = AutoScheduleMode::Running;
-                        });
-                    }
+    let (new_state, action) = match auto_schedule_advance(&state, rh, cfg, get_time_ms()) {
+        Ok(res) => res,
+        Err(e) => {
+            ACTIVE_AUTO_SCHEDULE.update(|s| s.reset());
 
-                    Ok(())
-                }
-                AutoScheduleMode::Running => {
-                    if ACTIVE_AUTO_SCHEDULE.running_ms() >= sched.run_secs * 1000 {
-                        mister_auto_schedule_next(cfg).await?;
-                    }
+            return Err(e);
+        }
+    };
 
-                    Ok(())
-                }
-                _ => unreachable!(),
+    mister_auto_schedule_apply(cfg, storage, &state, new_state, action)
+}
+
+/// Operator jump to a step (or the next one) from the API.
+fn mister_auto_schedule_control(
+    cfg: &ConfigInstance,
+    storage: &mut FlashStorage,
+    control: ScheduleControl,
+) -> Result<()> {
+    let state = ACTIVE_AUTO_SCHEDULE.read().clone();
+    let now = get_time_ms();
+
+    let (new_state, action) = match control {
+        ScheduleControl::Goto(idx) => {
+            if idx >= cfg.mister_auto_schedule.len() {
+                return Err(general_fault(format!(
+                    "no mister auto schedule found for idx: {}",
+                    idx
+                )));
             }
+
+            (
+                AutoScheduleState::pending(idx, now).carry(&state),
+                AutoScheduleAction::Started(idx),
+            )
         }
-        None => Err(general_fault(
+        ScheduleControl::Next => auto_schedule_next(&state, cfg, now),
+    };
+
+    log::info!("Mister auto schedule control: {:?}", control);
+
+    mister_auto_schedule_apply(cfg, storage, &state, new_state, action)
+}
+
+fn mister_auto_schedule_apply(
+    cfg: &ConfigInstance,
+    storage: &mut FlashStorage,
+    state: &AutoScheduleState,
+    new_state: AutoScheduleState,
+    action: AutoScheduleAction,
+) -> Result<()> {
+    if new_state.target_unreached && !state.target_unreached {
+        events::record(
+            EventKind::TargetUnreached,
+            Some(format!("idx: {}", state.idx)),
+        );
+    }
+
+    ACTIVE_AUTO_SCHEDULE.update(|s| *s = new_state);
+
+    match action {
+        AutoScheduleAction::None | AutoScheduleAction::Running => Ok(()),
+        AutoScheduleAction::Started(idx) => mister_auto_schedule_started(cfg, storage, idx),
+        AutoScheduleAction::Completed => {
+            log::info!(
+                "Mister auto schedule completed, holding on last schedule '{}'",
+                state.idx
+            );
+
+            Ok(())
+        }
+        AutoScheduleAction::Fault => Err(general_fault(
             "failed to check auto schedule - no sensor metrics".to_string(),
         )),
     }
 }
 
+/// Works out the next auto schedule state for the current `rh` at `now` (see
+/// `fungi_core::schedule::advance`), with the active step's targets taken from the config.
+fn auto_schedule_advance(
+    state: &AutoScheduleState,
+    rh: Option<f32>,
+    cfg: &ConfigInstance,
+    now: u32,
+) -> Result<(AutoScheduleState, AutoScheduleAction)> {
+    let sched = state.get_auto_schedule(cfg).ok_or_else(|| {
+        general_fault(format!(
+            "no mister auto schedule found for idx: {}",
+            state.idx
+        ))
+    })?;
+
+    let step = StepTarget {
+        rh_on: sched.on_rh(cfg),
+        rh_off: sched.off_rh(cfg),
+        run_secs: sched.run_secs,
+        max_wait_secs: sched.max_wait_secs,
+        hold_until_target: sched.hold_until_target,
+    };
+
+    let program = auto_schedule_program(cfg);
+
+    Ok(schedule::advance(state, &step, program, rh, now))
+}
+
+/// Moves on to the next step (wrapping when looping, otherwise holding on the last).
+fn auto_schedule_next(
+    state: &AutoScheduleState,
+    cfg: &ConfigInstance,
+    now: u32,
+) -> (AutoScheduleState, AutoScheduleAction) {
+    schedule::next(state, auto_schedule_program(cfg), now)
+}
+
+fn auto_schedule_program(cfg: &ConfigInstance) -> Program {
+    Program {
+        len: cfg.mister_auto_schedule.len(),
+        looping: cfg.mister_auto_schedule_loop,
+    }
+}
+
+/// Restarts the program when the active step no longer exists (i.e. the schedule was shortened
+/// by a config update while running), rather than faulting on the missing step.
+fn auto_schedule_clamp(cfg: &ConfigInstance) {
+    let idx = ACTIVE_AUTO_SCHEDULE.idx();
+    let mut clamped = false;
+    ACTIVE_AUTO_SCHEDULE.update(|s| clamped = s.clamp(cfg.mister_auto_schedule.len()));
+
+    if clamped {
+        log::warn!(
+            "Mister auto schedule '{}' no longer exists (schedule has {} entries), restarting",
+            idx,
+            cfg.mister_auto_schedule.len()
+        );
+    }
+}
+
 fn get_auto_schedule_checked(cfg: &ConfigInstance) -> Result<&MisterAutoSchedule> {
     match ACTIVE_AUTO_SCHEDULE.get_schedule(cfg) {
         Some(sched) => Ok(sched),
@@ -606,15 +1116,19 @@ fn get_auto_schedule_checked(cfg: &ConfigInstance) -> Result<&MisterAutoSchedule
 
 #[embassy_executor::task]
 async fn mister_status_led_task(
-    _cfg: Config,
+    cfg: Config,
     status_led_pin: GpioPin<Unknown, STATUS_LED_GPIO_PIN>,
     mut status_changed_sub: StatusChangedSubscriber,
 ) {
     let mut status_led_pin = status_led_pin.into_push_pull_output();
 
     loop {
-        if let Err(e) =
-            mister_status_led_task_poll(&mut status_led_pin, &mut status_changed_sub).await
+        if let Err(e) = mister_status_led_task_poll(
+            cfg.load().as_ref(),
+            &mut status_led_pin,
+            &mut status_changed_sub,
+        )
+        .await
         {
             log::warn!("mister status led task poll failed: {:?}", e);
 
@@ -626,18 +1140,21 @@ async fn mister_status_led_task(
 }
 
 async fn mister_status_led_task_poll(
+    cfg: &ConfigInstance,
     status_led_pin: &mut GpioPin<Output<PushPull>, STATUS_LED_GPIO_PIN>,
     status_changed_sub: &mut StatusChangedSubscriber,
 ) -> Result<()> {
-    match select(
+    match select3(
         status_changed_sub.next_message(),
-        Timer::after(Duration::from_millis(400)),
+        status_led_blink_wait(cfg),
+        STATUS_LED_FLASH.wait(),
     )
     .await
     {
-        Either::First(r) => match r {
+        Either3::First(r) => match r {
             WaitResult::Lagged(count) => {
                 log::warn!("status change subscriber lagged by {} messages", count);
+                lag::record(LagChannel::MisterStatusChanged);
 
                 // Ignore
                 return Ok(());
@@ -660,14 +1177,25 @@ async fn mister_status_led_task_poll(
                 }
             },
         },
-        Either::Second(_) => {
+        Either3::Second(_) => {
             // Blink (alternate)
-            if matches!(STATUS.read().as_ref(), Some(&Status::Fault)) {
-                if status_led_pin.is_set_low().map_err(map_infallible_err)? {
-                    status_led_pin.set_high().map_err(map_infallible_err)?;
-                } else {
-                    status_led_pin.set_low().map_err(map_infallible_err)?;
-                }
+            if status_led_pin.is_set_low().map_err(map_infallible_err)? {
+                status_led_pin.set_high().map_err(map_infallible_err)?;
+            } else {
+                status_led_pin.set_low().map_err(map_infallible_err)?;
+            }
+        }
+        Either3::Third(count) => {
+            for _ in 0..count {
+                status_led_pin.set_low().map_err(map_infallible_err)?;
+                Timer::after(Duration::from_millis(STATUS_LED_FLASH_MS)).await;
+                status_led_pin.set_high().map_err(map_infallible_err)?;
+                Timer::after(Duration::from_millis(STATUS_LED_FLASH_MS)).await;
+            }
+
+            // Back to the status (a fault blink carries on from here).
+            if matches!(STATUS.read().as_ref(), Some(&Status::Off) | None) {
+                status_led_pin.set_low().map_err(map_infallible_err)?;
             }
         }
     }
@@ -675,58 +1203,164 @@ async fn mister_status_led_task_poll(
     Ok(())
 }
 
+/// Resolves when the LED should next toggle (never while the status is solid).
+async fn status_led_blink_wait(cfg: &ConfigInstance) {
+    if !matches!(STATUS.read().as_ref(), Some(&Status::Fault)) {
+        return pending().await;
+    }
+
+    let blink_ms = cfg
+        .status_led_fault_blink_ms
+        .max(STATUS_LED_FAULT_BLINK_MIN_MS);
+
+    Timer::after(Duration::from_millis(blink_ms as u64)).await
+}
+
 async fn change_status_from_mode(
+    cfg: &ConfigInstance,
     mode: Mode,
-    mister_pwr_pin: &mut GpioPin<Output<PushPull>, MISTER_POWER_GPIO_PIN>,
+    mister_pwr_pin: &mut MisterOutput,
     status_changed_pub: &mut StatusChangedPublisher,
 ) -> Result<()> {
     match mode {
         Mode::On => change_status(Status::On, mister_pwr_pin, status_changed_pub).await?,
         Mode::Off => change_status(Status::Off, mister_pwr_pin, status_changed_pub).await?,
-        // Start 'Off' for Auto.
-        Mode::Auto => change_status(Status::Off, mister_pwr_pin, status_changed_pub).await?,
+        Mode::Auto => {
+            if !cfg.sensor_enabled {
+                log::error!("Mister mode 'Auto' requires a sensor but the sensor is disabled");
+
+                change_status_fault(
+                    "auto mode requires a sensor (sensor disabled)".to_string(),
+                    mister_pwr_pin,
+                    status_changed_pub,
+                )
+                .await?
+            } else if cfg.mister_auto_schedule.is_empty() {
+                // i.e. a legacy config blob (validation rejects an empty schedule now).
+                log::error!("Mister mode 'Auto' requires an auto schedule but it is empty");
+
+                change_status_fault(
+                    "auto mode requires an auto schedule (schedule empty)".to_string(),
+                    mister_pwr_pin,
+                    status_changed_pub,
+                )
+                .await?
+            } else {
+                // Start 'Off' for Auto.
+                change_status(Status::Off, mister_pwr_pin, status_changed_pub).await?
+            }
+        }
     }
 
     Ok(())
 }
 
-async fn change_status(
-    status: Status,
-    mister_pwr_pin: &mut GpioPin<Output<PushPull>, MISTER_POWER_GPIO_PIN>,
+async fn change_pause(
+    cfg: &ConfigInstance,
+    paused: bool,
+    mister_pwr_pin: &mut MisterOutput,
     status_changed_pub: &mut StatusChangedPublisher,
 ) -> Result<()> {
-    match status {
-        Status::Off => {
-            if mister_pwr_pin.is_set_high().map_err(map_infallible_err)? {
-                mister_pwr_pin.set_low().map_err(map_infallible_err)?;
-            }
+    if paused {
+        if is_paused() {
+            return Ok(());
         }
-        Status::On => {
-            if mister_pwr_pin.is_set_low().map_err(map_infallible_err)? {
-                mister_pwr_pin.set_high().map_err(map_infallible_err)?;
-            }
+
+        let _ = PAUSED_AT.write().insert(get_time_ms());
+        log::info!("Mister paused");
+
+        return change_status(Status::Off, mister_pwr_pin, status_changed_pub).await;
+    }
+
+    let paused_at = match PAUSED_AT.write().take() {
+        Some(paused_at) => paused_at,
+        None => return Ok(()),
+    };
+
+    // Shift the auto schedule timers so the paused time isn't counted.
+    let paused_ms = get_time_ms().wrapping_sub(paused_at);
+    ACTIVE_AUTO_SCHEDULE.update(|s| {
+        if s.start_time > 0 {
+            s.start_time = s.start_time.wrapping_add(paused_ms);
         }
-        Status::Fault => {
-            if mister_pwr_pin.is_set_high().map_err(map_infallible_err)? {
-                mister_pwr_pin.set_low().map_err(map_infallible_err)?;
-            }
+        if s.run_start_time > 0 {
+            s.run_start_time = s.run_start_time.wrapping_add(paused_ms);
         }
+    });
+
+    log::info!("Mister resumed after {}ms", paused_ms);
+
+    let mode = ACTIVE_MODE.read().clone();
+    match mode {
+        Some(mode) => change_status_from_mode(cfg, mode, mister_pwr_pin, status_changed_pub).await,
+        None => Ok(()),
     }
+}
+
+async fn change_status_fault(
+    reason: String,
+    mister_pwr_pin: &mut MisterOutput,
+    status_changed_pub: &mut StatusChangedPublisher,
+) -> Result<()> {
+    let _ = FAULT_REASON.write().insert(reason);
+
+    change_status(Status::Fault, mister_pwr_pin, status_changed_pub).await
+}
 
-    if match STATUS.read().as_ref() {
+async fn change_status(
+    status: Status,
+    mister_pwr_pin: &mut MisterOutput,
+    status_changed_pub: &mut StatusChangedPublisher,
+) -> Result<()> {
+    let changed = match STATUS.read().as_ref() {
         None => true,
         Some(v) => !v.eq(&status),
-    } {
+    };
+
+    // Steady state (i.e. every sensor sample in Auto), only the (cheap) pin check is kept.
+    let has_fault_reason = FAULT_REASON.read().is_some();
+    let burst_off_phase = BURST.read().off_phase;
+    if status_steady(
+        status,
+        changed,
+        has_fault_reason,
+        burst_off_phase,
+        mister_pwr_pin,
+    )? {
+        return Ok(());
+    }
+
+    if !matches!(status, Status::Fault) {
+        let _ = FAULT_REASON.write().take();
+    }
+
+    if changed && matches!(status, Status::On) {
+        // Always start a burst cycle with the 'on' phase.
+        *BURST.write() = BurstState::new();
+    }
+
+    drive_output(status, BURST.read().off_phase, mister_pwr_pin)?;
+
+    if changed {
         log::info!("Mister status changed to: {:?}", status);
 
         let _ = STATUS.write().insert(status);
+        *STATUS_CHANGED_AT.write() = get_time_ms();
         status_changed_pub.publish_immediate(status);
     }
 
+    // The fault reason can change without the status.
+    let fault_reason = FAULT_REASON.read().clone();
+    snapshot::update(|s| {
+        s.status = Some(status);
+        s.fault_reason = fault_reason;
+    });
+
     Ok(())
 }
 
 async fn toggle_mode(
+    cfg: &ConfigInstance,
     storage: &mut FlashStorage,
     mode_changed_pub: &mut ModeChangedPublisher,
 ) -> Result<Mode> {
@@ -742,12 +1376,23 @@ async fn toggle_mode(
         }
     };
 
-    store_mode(storage, next_mode, mode_changed_pub).await?;
+    store_mode(cfg, storage, next_mode, mode_changed_pub).await?;
 
     Ok(next_mode)
 }
 
-async fn load_mode(storage: &mut FlashStorage, mode_changed_pub: &mut ModeChangedPublisher) {
+async fn load_mode(
+    cfg: &ConfigInstance,
+    storage: &mut FlashStorage,
+    mode_changed_pub: &mut ModeChangedPublisher,
+) {
+    // Anything left in flash from when persistence was enabled is ignored.
+    if !cfg.persist_mode {
+        set_active_mode(cfg.default_mode);
+        mode_changed_pub.publish_immediate(cfg.default_mode);
+        return;
+    }
+
     let mut bytes = [0u8; 1];
     let mode = match storage.read(MODE_FLASH_ADDR, &mut bytes) {
         Ok(_) => {
@@ -757,45 +1402,189 @@ async fn load_mode(storage: &mut FlashStorage, mode_changed_pub: &mut ModeChange
                 log::info!("Restored previous mode '{}' from flash", mode);
                 mode
             } else {
-                Mode::Auto
+                cfg.default_mode
             }
         }
-        Err(_) => Mode::Auto,
+        Err(_) => cfg.default_mode,
     };
 
-    let _ = ACTIVE_MODE.write().insert(mode);
+    set_active_mode(mode);
     mode_changed_pub.publish_immediate(mode);
 }
 
 async fn store_mode(
+    cfg: &ConfigInstance,
     storage: &mut FlashStorage,
     mode: Mode,
     mode_changed_pub: &mut ModeChangedPublisher,
 ) -> Result<()> {
-    let mode_u8 = mode as u8;
+    if cfg.persist_mode {
+        let mode_u8 = mode as u8;
+        storage
+            .write(MODE_FLASH_ADDR, mode_u8.to_be_bytes().as_ref())
+            .map_err(|e| {
+                general_fault(format!(
+                    "Failed to persist active mode to flash storage: {:?}",
+                    e
+                ))
+            })?;
+
+        log::info!("Persisted mode '{}' to flash", mode);
+    }
+
+    set_active_mode(mode);
+    mode_changed_pub.publish_immediate(mode);
+
+    Ok(())
+}
+
+fn set_active_mode(mode: Mode) {
+    let previous = ACTIVE_MODE.write().replace(mode);
+    if previous != Some(mode) {
+        *MODE_CHANGED_AT.write() = get_time_ms();
+    }
+
+    snapshot::update(|s| s.mode = Some(mode));
+}
+
+fn load_auto_schedule_idx(storage: &mut FlashStorage) -> Option<usize> {
+    let mut bytes = [0u8; 2];
+    match storage.read(AUTO_SCHEDULE_IDX_FLASH_ADDR, &mut bytes) {
+        // Erased flash reads back as 0xff.
+        Ok(_) if bytes != [0xff; 2] => Some(u16::from_be_bytes(bytes) as usize),
+        _ => None,
+    }
+}
+
+fn store_auto_schedule_idx(storage: &mut FlashStorage, idx: usize) -> Result<()> {
+    // Skip the (sector erasing) write when unchanged, i.e. restarting at the same step.
+    if load_auto_schedule_idx(storage) == Some(idx) {
+        return Ok(());
+    }
+
     storage
-        .write(MODE_FLASH_ADDR, mode_u8.to_be_bytes().as_ref())
+        .write(
+            AUTO_SCHEDULE_IDX_FLASH_ADDR,
+            (idx as u16).to_be_bytes().as_ref(),
+        )
         .map_err(|e| {
             general_fault(format!(
-                "Failed to persist active mode to flash storage: {:?}",
+                "Failed to persist auto schedule idx to flash storage: {:?}",
                 e
             ))
-        })?;
-
-    log::info!("Persisted mode '{}' to flash", mode);
+        })
+}
 
-    let _ = ACTIVE_MODE.write().insert(mode);
-    mode_changed_pub.publish_immediate(mode);
+/// Last resort power-off of the mister (i.e. from the panic/exception halt path).
+///
+/// The pin is owned by `mister_operation_task`, so this bypasses the HAL and clears the output
+/// bit directly.
+pub(crate) fn force_off() {
+    // Take the pin back from the LEDC channel, otherwise the output bit has no effect.
+    #[cfg(feature = "mister-soft-start")]
+    unsafe {
+        core::ptr::write_volatile(
+            (GPIO_FUNC0_OUT_SEL_CFG_REG + 4 * MISTER_POWER_GPIO_PIN as u32) as *mut u32,
+            GPIO_OUT_SEL_SIMPLE,
+        );
+    }
 
-    Ok(())
+    unsafe {
+        core::ptr::write_volatile(GPIO_OUT_W1TC_REG as *mut u32, 1 << MISTER_POWER_GPIO_PIN);
+    }
 }
 
 pub(crate) fn is_mode_auto() -> bool {
     matches!(ACTIVE_MODE.read().as_ref(), Some(&Mode::Auto))
 }
 
+pub(crate) fn is_manual_run() -> bool {
+    MANUAL_RUN_UNTIL.read().is_some()
+}
+
+/// Targets `rh` in Auto (instead of the schedule step) for `ttl_secs`, replacing any override.
+pub(crate) fn set_setpoint_override(rh: f32, ttl_secs: u32) {
+    log::info!("Setpoint override: {}% for {} seconds", rh, ttl_secs);
+
+    let _ = SETPOINT_OVERRIDE.write().insert(SetpointOverride {
+        rh,
+        until: Instant::now() + Duration::from_secs(ttl_secs as u64),
+    });
+}
+
+/// Ends the override early, `false` if there wasn't one.
+pub(crate) fn clear_setpoint_override() -> bool {
+    let cleared = SETPOINT_OVERRIDE
+        .write()
+        .take()
+        .filter(|o| o.remaining_ms() > 0)
+        .is_some();
+    if cleared {
+        log::info!("Setpoint override cleared, back to the auto schedule");
+    }
+
+    cleared
+}
+
+/// The active override (expired ones are only cleared by the next auto poll).
+pub(crate) fn setpoint_override() -> Option<SetpointOverride> {
+    SETPOINT_OVERRIDE
+        .read()
+        .clone()
+        .filter(|o| o.remaining_ms() > 0)
+}
+
+pub(crate) fn is_paused() -> bool {
+    PAUSED_AT.read().is_some()
+}
+
+/// Flashes the status LED `count` times (i.e. to acknowledge a button), a no-op when the
+/// status LED is disabled.
+pub(crate) fn flash_status_led(count: u8) {
+    STATUS_LED_FLASH.signal(count);
+}
+
+/// How long the mister has been in its current status.
+pub(crate) fn status_elapsed_ms() -> u32 {
+    get_time_ms().wrapping_sub(*STATUS_CHANGED_AT.read())
+}
+
+/// How long the mister has been in its current mode.
+pub(crate) fn mode_elapsed_ms() -> u32 {
+    get_time_ms().wrapping_sub(*MODE_CHANGED_AT.read())
+}
+
+/// Whether the rh hard floor is currently forcing the mister 'On'.
+pub(crate) fn is_rh_floor_active() -> bool {
+    is_mode_auto() && !is_paused() && *RH_FLOOR_ACTIVE.read()
+}
+
 // Models
 
+#[derive(Clone, Debug)]
+pub(crate) struct SetpointOverride {
+    pub(crate) rh: f32,
+    until: Instant,
+}
+
+impl SetpointOverride {
+    pub(crate) fn remaining_ms(&self) -> u32 {
+        self.until
+            .checked_duration_since(Instant::now())
+            .map(|d| d.as_millis() as u32)
+            .unwrap_or(0)
+    }
+
+    /// Band around the override (with the global adjustments, as there's no step).
+    pub(crate) fn on_rh(&self, cfg: &ConfigInstance) -> f32 {
+        cfg.mister_auto_on_rh(self.rh)
+    }
+
+    pub(crate) fn off_rh(&self, cfg: &ConfigInstance) -> f32 {
+        cfg.mister_auto_off_rh(self.rh)
+    }
+}
+
 #[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub(crate) enum Mode {
     Auto = 1,
@@ -839,11 +1628,35 @@ impl From<u8> for Mode {
 #[derive(Copy, Clone)]
 pub(crate) struct ChangeMode {
     mode: Option<Mode>,
+    // Pause/resume without changing mode.
+    pause: Option<bool>,
+    // Manual run, 'On' for this many seconds then back to the mode.
+    run_secs: Option<u32>,
 }
 
 impl ChangeMode {
     pub(crate) fn new(mode: Option<Mode>) -> Self {
-        Self { mode }
+        Self {
+            mode,
+            pause: None,
+            run_secs: None,
+        }
+    }
+
+    pub(crate) fn pause(paused: bool) -> Self {
+        Self {
+            mode: None,
+            pause: Some(paused),
+            run_secs: None,
+        }
+    }
+
+    pub(crate) fn run(secs: u32) -> Self {
+        Self {
+            mode: None,
+            pause: None,
+            run_secs: Some(secs),
+        }
     }
 }
 
@@ -852,10 +1665,3 @@ impl Default for ChangeMode {
         Self::new(None)
     }
 }
-
-#[derive(Copy, Clone, PartialEq, Debug, Serialize)]
-pub(crate) enum Status {
-    Off,
-    On,
-    Fault,
-}
@@ -0,0 +1,236 @@
+//! The mister power output, a plain GPIO or (with `mister-soft-start`) an LEDC channel ramping
+//! the duty up when switching on.
+//!
+//! The soft start is for SSR driven setups, a mechanical relay can't be PWM'd (leave the feature
+//! off for those).
+
+#[cfg(feature = "mister-soft-start")]
+use alloc::boxed::Box;
+#[cfg(feature = "mister-soft-start")]
+use alloc::format;
+
+#[cfg(feature = "mister-soft-start")]
+use embassy_time::Duration;
+use embassy_time::Instant;
+#[cfg(not(feature = "mister-soft-start"))]
+use embedded_hal::digital::{OutputPin, StatefulOutputPin};
+#[cfg(feature = "mister-soft-start")]
+use esp_hal::clock::Clocks;
+use esp_hal::gpio::{GpioPin, Output, PushPull, Unknown};
+#[cfg(feature = "mister-soft-start")]
+use esp_hal::ledc::channel::{self, ChannelIFace};
+#[cfg(feature = "mister-soft-start")]
+use esp_hal::ledc::timer::{self, TimerIFace};
+#[cfg(feature = "mister-soft-start")]
+use esp_hal::ledc::{LSGlobalClkSource, LowSpeed, LEDC};
+#[cfg(feature = "mister-soft-start")]
+use esp_hal::peripherals::LEDC as LEDC_PERIPHERAL;
+#[cfg(feature = "mister-soft-start")]
+use fugit::RateExtU32;
+use fungi_core::mister::PowerOutput;
+
+use crate::board::MISTER_POWER_GPIO_PIN;
+#[cfg(feature = "mister-soft-start")]
+use crate::error::general_fault;
+#[cfg(not(feature = "mister-soft-start"))]
+use crate::error::map_infallible_err;
+use crate::error::{Error, Result};
+
+#[cfg(feature = "mister-soft-start")]
+const SOFT_START_PWM_KHZ: u32 = 1;
+// Duty steps over the ramp (each held for `soft_start_ms / SOFT_START_STEPS`).
+#[cfg(feature = "mister-soft-start")]
+const SOFT_START_STEPS: u8 = 20;
+
+type MisterPin = GpioPin<Output<PushPull>, MISTER_POWER_GPIO_PIN>;
+
+pub(crate) struct MisterOutput {
+    #[cfg(not(feature = "mister-soft-start"))]
+    pin: MisterPin,
+    #[cfg(feature = "mister-soft-start")]
+    channel: channel::Channel<'static, LowSpeed, MisterPin>,
+    #[cfg(feature = "mister-soft-start")]
+    soft_start_ms: u32,
+    #[cfg(feature = "mister-soft-start")]
+    on: bool,
+    #[cfg(feature = "mister-soft-start")]
+    ramp: Option<Ramp>,
+}
+
+/// Soft start progress, the duty step last set and when the next one is due.
+#[cfg(feature = "mister-soft-start")]
+struct Ramp {
+    step: u8,
+    next_at: Instant,
+}
+
+impl MisterOutput {
+    /// Drives the pin 'Off' straight away, its state after a reset isn't known.
+    #[cfg(not(feature = "mister-soft-start"))]
+    pub(crate) fn new(pin: GpioPin<Unknown, MISTER_POWER_GPIO_PIN>) -> Result<Self> {
+        let mut output = Self {
+            pin: pin.into_push_pull_output(),
+        };
+        output.set_off()?;
+
+        Ok(output)
+    }
+
+    /// Drives the pin 'Off' straight away, its state after a reset isn't known.
+    #[cfg(feature = "mister-soft-start")]
+    pub(crate) fn new(
+        pin: GpioPin<Unknown, MISTER_POWER_GPIO_PIN>,
+        ledc: LEDC_PERIPHERAL,
+        clocks: &'static Clocks<'static>,
+        soft_start_ms: u32,
+    ) -> Result<Self> {
+        let pin = pin.into_push_pull_output();
+
+        // The channel borrows the timer (and the timer the controller), both live forever.
+        let mut ledc = LEDC::new(ledc, clocks);
+        ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+        let ledc: &'static LEDC<'static> = Box::leak(Box::new(ledc));
+
+        let mut pwm_timer = ledc.get_timer::<LowSpeed>(timer::Number::Timer0);
+        pwm_timer
+            .configure(timer::config::Config {
+                duty: timer::config::Duty::Duty10Bit,
+                clock_source: timer::LSClockSource::APBClk,
+                frequency: SOFT_START_PWM_KHZ.kHz(),
+            })
+            .map_err(|e| general_fault(format!("failed to configure mister PWM timer: {:?}", e)))?;
+        let pwm_timer: &'static timer::Timer<'static, LowSpeed> = Box::leak(Box::new(pwm_timer));
+
+        let mut channel = ledc.get_channel(channel::Number::Channel0, pin);
+        channel
+            .configure(channel::config::Config {
+                timer: pwm_timer,
+                duty_pct: 0,
+                pin_config: channel::config::PinConfig::PushPull,
+            })
+            .map_err(|e| {
+                general_fault(format!("failed to configure mister PWM channel: {:?}", e))
+            })?;
+
+        Ok(Self {
+            channel,
+            soft_start_ms,
+            on: false,
+            ramp: None,
+        })
+    }
+
+    #[cfg(not(feature = "mister-soft-start"))]
+    pub(crate) fn is_on(&mut self) -> Result<bool> {
+        self.pin.is_set_high().map_err(map_infallible_err)
+    }
+
+    #[cfg(feature = "mister-soft-start")]
+    pub(crate) fn is_on(&mut self) -> Result<bool> {
+        Ok(self.on)
+    }
+
+    #[cfg(not(feature = "mister-soft-start"))]
+    pub(crate) fn set_on(&mut self) -> Result<()> {
+        self.pin.set_high().map_err(map_infallible_err)
+    }
+
+    /// Starts ramping the duty from 0 to 100% over `mister_soft_start_ms`.
+    ///
+    /// Doesn't wait for the ramp, the mister task steps it when `soft_start_at` is due (so it
+    /// keeps handling events and an 'Off' part way stops it).
+    #[cfg(feature = "mister-soft-start")]
+    pub(crate) fn set_on(&mut self) -> Result<()> {
+        // Counts as 'On' from the start of the ramp (so an 'Off' mid ramp isn't skipped).
+        self.on = true;
+
+        if self.soft_start_ms == 0 {
+            return self.set_duty(100);
+        }
+
+        let _ = self.ramp.insert(Ramp {
+            step: 0,
+            next_at: Instant::now(),
+        });
+
+        self.soft_start_step()
+    }
+
+    /// When the next soft start step is due (never without `mister-soft-start`).
+    #[cfg(not(feature = "mister-soft-start"))]
+    pub(crate) fn soft_start_at(&self) -> Option<Instant> {
+        None
+    }
+
+    /// When the next soft start step is due (none once the ramp finished or was stopped).
+    #[cfg(feature = "mister-soft-start")]
+    pub(crate) fn soft_start_at(&self) -> Option<Instant> {
+        self.ramp.as_ref().map(|ramp| ramp.next_at)
+    }
+
+    #[cfg(not(feature = "mister-soft-start"))]
+    pub(crate) fn soft_start_step(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Sets the next duty step of the ramp, the last one is full duty (and ends the ramp).
+    #[cfg(feature = "mister-soft-start")]
+    pub(crate) fn soft_start_step(&mut self) -> Result<()> {
+        let step_ms = (self.soft_start_ms / SOFT_START_STEPS as u32) as u64;
+
+        let duty_pct = match self.ramp.as_mut() {
+            Some(ramp) if ramp.step + 1 < SOFT_START_STEPS => {
+                ramp.step += 1;
+                ramp.next_at += Duration::from_millis(step_ms);
+
+                ramp.step * (100 / SOFT_START_STEPS)
+            }
+            Some(_) => {
+                let _ = self.ramp.take();
+
+                100
+            }
+            // Stopped (i.e. switched 'Off') since it was due.
+            None => return Ok(()),
+        };
+
+        self.set_duty(duty_pct)
+    }
+
+    #[cfg(not(feature = "mister-soft-start"))]
+    pub(crate) fn set_off(&mut self) -> Result<()> {
+        self.pin.set_low().map_err(map_infallible_err)
+    }
+
+    /// Also stops a soft start part way.
+    #[cfg(feature = "mister-soft-start")]
+    pub(crate) fn set_off(&mut self) -> Result<()> {
+        self.on = false;
+        let _ = self.ramp.take();
+
+        self.set_duty(0)
+    }
+
+    #[cfg(feature = "mister-soft-start")]
+    fn set_duty(&mut self, duty_pct: u8) -> Result<()> {
+        self.channel
+            .set_duty(duty_pct)
+            .map_err(|e| general_fault(format!("failed to set mister PWM duty: {:?}", e)))
+    }
+}
+
+impl PowerOutput for MisterOutput {
+    type Error = Error;
+
+    fn is_on(&mut self) -> Result<bool> {
+        MisterOutput::is_on(self)
+    }
+
+    fn set_on(&mut self) -> Result<()> {
+        MisterOutput::set_on(self)
+    }
+
+    fn set_off(&mut self) -> Result<()> {
+        MisterOutput::set_off(self)
+    }
+}
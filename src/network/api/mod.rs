@@ -6,11 +6,22 @@ use embassy_net::Stack;
 use embassy_time::{Duration, Timer};
 use esp_wifi::wifi::{WifiDevice, WifiStaDevice};
 use picoserve::{KeepAlive, ShutdownMethod, Timeouts};
+use spin::RwLock;
 
 use crate::chip_control::{ChipControlPublisher, CHIP_CONTROL_CHANNEL};
 use crate::config::Config;
-use crate::error::{map_embassy_pub_sub_err, map_embassy_spawn_err, Result};
-use crate::mister::{ChangeModePublisher, CHANGE_MODE_CHANNEL};
+use crate::display::{
+    ChangeModePublisher as DisplayChangeModePublisher, MessagePublisher as DisplayMessagePublisher,
+    CHANGE_MODE_CHANNEL as DISPLAY_CHANGE_MODE_CHANNEL, MESSAGE_CHANNEL,
+};
+use crate::error::{map_embassy_pub_sub_err, map_embassy_spawn_err, too_many_requests_err, Result};
+use crate::mister::{
+    ChangeModePublisher, ScheduleControlPublisher, CHANGE_MODE_CHANNEL, SCHEDULE_CONTROL_CHANNEL,
+};
+use crate::sensor::{
+    ResetPublisher as SensorResetPublisher, RESET_CHANNEL as SENSOR_RESET_CHANNEL,
+};
+use crate::utils::get_time_ms;
 
 mod routes;
 pub(crate) mod types;
@@ -18,12 +29,40 @@ pub(crate) mod utils;
 
 // Only works with 1 at the moment (probs how the stack is shared).
 pub(crate) const WEB_TASK_POOL_SIZE: usize = 1;
+pub(crate) const API_PORT: u16 = 80;
+// A kept-alive client blocks the (single) worker, so it's never held for long.
+const API_KEEP_ALIVE_SECS_MAX: u32 = 10;
+
+// Buffer sizes per worker, selected with an `*-api-buffers` feature. They're part of the
+// (statically allocated) web task rather than the heap, so cost rx + tx + http bytes of RAM per
+// worker whether used or not, the http buffer bounds the largest request (headers and body).
+#[cfg(all(feature = "small-api-buffers", feature = "large-api-buffers"))]
+compile_error!("only one of the `small-api-buffers` and `large-api-buffers` features can be set");
+#[cfg(feature = "small-api-buffers")]
+const API_TCP_BUFFER_LEN: usize = 512;
+#[cfg(feature = "small-api-buffers")]
+pub(crate) const API_HTTP_BUFFER_LEN: usize = 1024;
+#[cfg(not(any(feature = "small-api-buffers", feature = "large-api-buffers")))]
+const API_TCP_BUFFER_LEN: usize = 1024;
+#[cfg(not(any(feature = "small-api-buffers", feature = "large-api-buffers")))]
+pub(crate) const API_HTTP_BUFFER_LEN: usize = 2048;
+#[cfg(feature = "large-api-buffers")]
+const API_TCP_BUFFER_LEN: usize = 2048;
+#[cfg(feature = "large-api-buffers")]
+pub(crate) const API_HTTP_BUFFER_LEN: usize = 4096;
+
+// Shared by all mutating endpoints (there's only the one device to protect).
+static RATE_LIMIT: RwLock<RateLimitBucket> = RwLock::new(RateLimitBucket::new());
 
 #[derive(Clone)]
 struct ApiState {
     cfg: Config,
     change_mode_pub: Arc<ChangeModePublisher>,
     chip_control_pub: Arc<ChipControlPublisher>,
+    display_change_mode_pub: Arc<DisplayChangeModePublisher>,
+    display_message_pub: Arc<DisplayMessagePublisher>,
+    sensor_reset_pub: Arc<SensorResetPublisher>,
+    schedule_control_pub: Arc<ScheduleControlPublisher>,
 }
 
 impl ApiState {
@@ -31,13 +70,74 @@ impl ApiState {
         cfg: Config,
         change_mode_pub: Arc<ChangeModePublisher>,
         chip_control_pub: Arc<ChipControlPublisher>,
+        display_change_mode_pub: Arc<DisplayChangeModePublisher>,
+        display_message_pub: Arc<DisplayMessagePublisher>,
+        sensor_reset_pub: Arc<SensorResetPublisher>,
+        schedule_control_pub: Arc<ScheduleControlPublisher>,
     ) -> Self {
         Self {
             cfg,
             change_mode_pub,
             chip_control_pub,
+            display_change_mode_pub,
+            display_message_pub,
+            sensor_reset_pub,
+            schedule_control_pub,
         }
     }
+
+    /// Takes a token for a mutating request (reads are never limited).
+    pub(crate) fn check_rate_limit(&self) -> Result<()> {
+        let cfg = self.cfg.load();
+
+        RATE_LIMIT.write().take(
+            get_time_ms(),
+            cfg.api_rate_limit_burst,
+            cfg.api_rate_limit_refill_ms,
+        )
+    }
+}
+
+/// Token bucket, tracking the tokens spent so an empty one starts full.
+struct RateLimitBucket {
+    spent: u32,
+    refilled_at_ms: u32,
+}
+
+impl RateLimitBucket {
+    const fn new() -> Self {
+        Self {
+            spent: 0,
+            refilled_at_ms: 0,
+        }
+    }
+
+    fn take(&mut self, now_ms: u32, burst: u32, refill_ms: u32) -> Result<()> {
+        if burst == 0 || refill_ms == 0 {
+            return Ok(());
+        }
+
+        let elapsed_ms = now_ms.wrapping_sub(self.refilled_at_ms);
+        let refilled = elapsed_ms / refill_ms;
+        if refilled >= self.spent {
+            self.spent = 0;
+            self.refilled_at_ms = now_ms;
+        } else {
+            self.spent -= refilled;
+            self.refilled_at_ms = self.refilled_at_ms.wrapping_add(refilled * refill_ms);
+        }
+
+        if self.spent >= burst {
+            let since_refill_ms = now_ms.wrapping_sub(self.refilled_at_ms);
+            return Err(too_many_requests_err(
+                refill_ms.saturating_sub(since_refill_ms),
+            ));
+        }
+
+        self.spent += 1;
+
+        Ok(())
+    }
 }
 
 pub(crate) fn init(
@@ -45,13 +145,20 @@ pub(crate) fn init(
     stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>,
     spawner: &Spawner,
 ) -> Result<()> {
+    // With keep-alive the wait for the next request on a connection is the idle timeout.
+    let keep_alive_secs = cfg.load().api_keep_alive_secs.min(API_KEEP_ALIVE_SECS_MAX);
+    let (connection, start_read_request_secs) = match keep_alive_secs {
+        0 => (KeepAlive::Close, 5),
+        secs => (KeepAlive::KeepAlive, secs as u64),
+    };
+
     let pico_cfg = Box::leak(Box::new(picoserve::Config {
         timeouts: Timeouts {
-            start_read_request: Some(Duration::from_secs(5)),
+            start_read_request: Some(Duration::from_secs(start_read_request_secs)),
             read_request: Some(Duration::from_secs(1)),
             write: Some(Duration::from_secs(1)),
         },
-        connection: KeepAlive::Close,
+        connection,
         shutdown_method: ShutdownMethod::Shutdown,
     }));
 
@@ -67,7 +174,39 @@ pub(crate) fn init(
             .map_err(map_embassy_pub_sub_err)?,
     );
 
-    let api_state = ApiState::new(cfg.clone(), change_mode_pub, chip_control_pub);
+    let display_change_mode_pub = Arc::new(
+        DISPLAY_CHANGE_MODE_CHANNEL
+            .publisher()
+            .map_err(map_embassy_pub_sub_err)?,
+    );
+
+    let display_message_pub = Arc::new(
+        MESSAGE_CHANNEL
+            .publisher()
+            .map_err(map_embassy_pub_sub_err)?,
+    );
+
+    let sensor_reset_pub = Arc::new(
+        SENSOR_RESET_CHANNEL
+            .publisher()
+            .map_err(map_embassy_pub_sub_err)?,
+    );
+
+    let schedule_control_pub = Arc::new(
+        SCHEDULE_CONTROL_CHANNEL
+            .publisher()
+            .map_err(map_embassy_pub_sub_err)?,
+    );
+
+    let api_state = ApiState::new(
+        cfg.clone(),
+        change_mode_pub,
+        chip_control_pub,
+        display_change_mode_pub,
+        display_message_pub,
+        sensor_reset_pub,
+        schedule_control_pub,
+    );
 
     for id in 0..WEB_TASK_POOL_SIZE {
         spawner
@@ -87,10 +226,10 @@ pub async fn web_task(
 ) {
     let app = routes::init().expect("failed to init API routes");
 
-    let port = 80;
-    let mut tcp_rx_buffer = [0; 1024];
-    let mut tcp_tx_buffer = [0; 1024];
-    let mut http_buffer = [0; 2048];
+    let port = API_PORT;
+    let mut tcp_rx_buffer = [0; API_TCP_BUFFER_LEN];
+    let mut tcp_tx_buffer = [0; API_TCP_BUFFER_LEN];
+    let mut http_buffer = [0; API_HTTP_BUFFER_LEN];
 
     log::info!("API worker[{}]: Started (waiting for WIFI...)", id);
 
@@ -112,7 +251,7 @@ pub async fn web_task(
     .await
 }
 
-async fn wait_for_net(stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>) {
+pub(crate) async fn wait_for_net(stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>) {
     loop {
         if stack.is_link_up() {
             break;
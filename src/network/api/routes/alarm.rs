@@ -0,0 +1,53 @@
+use alloc::format;
+
+use picoserve::extract::{FromRequest, State};
+use picoserve::io::Read;
+use picoserve::request::{RequestBody, RequestParts};
+use picoserve::response::Json;
+use serde::Deserialize;
+
+use crate::alarm;
+use crate::error::{bad_request_err, Error, Result};
+use crate::network::api::types::OkResponse;
+use crate::network::api::utils::deser_from_request;
+use crate::network::api::ApiState;
+
+const ALARM_MUTE_MAX_SECS: u32 = 60 * 60 * 24;
+
+pub(crate) async fn handle_mute(
+    State(state): State<ApiState>,
+    req: AlarmMuteRequest,
+) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    if req.secs == 0 || req.secs > ALARM_MUTE_MAX_SECS {
+        return Err(bad_request_err(format!(
+            "secs must be between 1 and {} (got {})",
+            ALARM_MUTE_MAX_SECS, req.secs
+        )));
+    }
+
+    alarm::mute(req.secs);
+
+    Ok(Json(OkResponse::new(format!(
+        "alarm muted for {} seconds",
+        req.secs
+    ))))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct AlarmMuteRequest {
+    secs: u32,
+}
+
+impl<'r> FromRequest<'r, ApiState> for AlarmMuteRequest {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> Result<Self> {
+        deser_from_request(state, request_body).await
+    }
+}
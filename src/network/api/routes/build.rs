@@ -0,0 +1,65 @@
+use picoserve::extract::Query;
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::network::api::utils::{json_response, JsonFormat, JsonResponse};
+
+static BUILD_INFO: BuildInfo = BuildInfo {
+    version: env!("CARGO_PKG_VERSION"),
+    git_hash: option_env!("GIT_HASH"),
+    features: BuildFeatures {
+        hdc1080: cfg!(feature = "hdc1080"),
+        sht40: cfg!(feature = "sht40"),
+        scd40: cfg!(feature = "scd40"),
+        sdcard: cfg!(feature = "sdcard"),
+        large_heap: cfg!(feature = "large-heap"),
+        small_api_buffers: cfg!(feature = "small-api-buffers"),
+        large_api_buffers: cfg!(feature = "large-api-buffers"),
+        schedule_preset_oyster: cfg!(feature = "schedule-preset-oyster"),
+        schedule_preset_shiitake: cfg!(feature = "schedule-preset-shiitake"),
+        mock_sensor: cfg!(feature = "mock-sensor"),
+        heater: cfg!(feature = "heater"),
+        mist_trigger: cfg!(feature = "mist-trigger"),
+        reset_button: cfg!(feature = "reset-button"),
+        buzzer: cfg!(feature = "buzzer"),
+        ldr: cfg!(feature = "ldr"),
+        diag: cfg!(feature = "diag"),
+        selftest: cfg!(feature = "selftest"),
+        mister_soft_start: cfg!(feature = "mister-soft-start"),
+    },
+};
+
+pub(crate) async fn handle_get(Query(format): Query<JsonFormat>) -> Result<JsonResponse> {
+    json_response(&BUILD_INFO, &format)
+}
+
+#[derive(Serialize)]
+pub(crate) struct BuildInfo {
+    version: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_hash: Option<&'static str>,
+    features: BuildFeatures,
+}
+
+/// Cargo features the binary was compiled with.
+#[derive(Serialize)]
+pub(crate) struct BuildFeatures {
+    hdc1080: bool,
+    sht40: bool,
+    scd40: bool,
+    sdcard: bool,
+    large_heap: bool,
+    small_api_buffers: bool,
+    large_api_buffers: bool,
+    schedule_preset_oyster: bool,
+    schedule_preset_shiitake: bool,
+    mock_sensor: bool,
+    heater: bool,
+    mist_trigger: bool,
+    reset_button: bool,
+    buzzer: bool,
+    ldr: bool,
+    diag: bool,
+    selftest: bool,
+    mister_soft_start: bool,
+}
@@ -0,0 +1,75 @@
+use embedded_svc::io::asynch::Read;
+use picoserve::extract::{FromRequest, Query, State};
+use picoserve::request::{RequestBody, RequestParts};
+use picoserve::response::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigInstance;
+use crate::error::{Error, Result};
+use crate::network::api::utils::{deser_from_request, json_response, JsonFormat, JsonResponse};
+use crate::network::api::ApiState;
+
+pub(crate) async fn handle_get(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> Result<JsonResponse> {
+    json_response(
+        &CalibrationResponse::from(state.cfg.load().as_ref()),
+        &format,
+    )
+}
+
+/// Updates the RH adjustments (omitted ones are left as is), live without a reset.
+pub(crate) async fn handle_patch(
+    State(state): State<ApiState>,
+    req: CalibrationRequest,
+) -> Result<Json<CalibrationResponse>> {
+    state.check_rate_limit()?;
+
+    state.cfg.apply_calibration(
+        req.sensor_calibration_rh_adj,
+        req.mister_auto_on_rh_adj,
+        req.mister_auto_off_rh_adj,
+    )?;
+
+    Ok(Json(CalibrationResponse::from(state.cfg.load().as_ref())))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct CalibrationRequest {
+    #[serde(default)]
+    sensor_calibration_rh_adj: Option<f32>,
+    #[serde(default)]
+    mister_auto_on_rh_adj: Option<f32>,
+    #[serde(default)]
+    mister_auto_off_rh_adj: Option<f32>,
+}
+
+impl<'r> FromRequest<'r, ApiState> for CalibrationRequest {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> Result<Self> {
+        deser_from_request(state, request_body).await
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct CalibrationResponse {
+    sensor_calibration_rh_adj: Option<f32>,
+    mister_auto_on_rh_adj: Option<f32>,
+    mister_auto_off_rh_adj: Option<f32>,
+}
+
+impl From<&ConfigInstance> for CalibrationResponse {
+    fn from(cfg: &ConfigInstance) -> Self {
+        Self {
+            sensor_calibration_rh_adj: cfg.sensor_calibration_rh_adj,
+            mister_auto_on_rh_adj: cfg.mister_auto_on_rh_adj,
+            mister_auto_off_rh_adj: cfg.mister_auto_off_rh_adj,
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use alloc::format;
+use alloc::string::ToString;
 
 use picoserve::extract::State;
 use picoserve::response::Json;
@@ -10,6 +11,8 @@ use crate::network::api::ApiState;
 pub(crate) async fn handle_reset(
     State(state): State<ApiState>,
 ) -> crate::error::Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
     state
         .chip_control_pub
         .publish_immediate(ChipControlAction::Reset);
@@ -19,3 +22,29 @@ pub(crate) async fn handle_reset(
         state.cfg.load().reset_wait_secs
     ))))
 }
+
+pub(crate) async fn handle_cancel_reset(
+    State(state): State<ApiState>,
+) -> crate::error::Result<Json<OkResponse>> {
+    state
+        .chip_control_pub
+        .publish_immediate(ChipControlAction::CancelReset);
+
+    Ok(Json(OkResponse::new(
+        "pending reset (if any) will be cancelled".to_string(),
+    )))
+}
+
+pub(crate) async fn handle_restart_network(
+    State(state): State<ApiState>,
+) -> crate::error::Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    state
+        .chip_control_pub
+        .publish_immediate(ChipControlAction::RestartNetwork);
+
+    Ok(Json(OkResponse::new(
+        "network will restart (connection will drop briefly)".to_string(),
+    )))
+}
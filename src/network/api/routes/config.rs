@@ -1,24 +1,83 @@
 use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
 use embedded_svc::io::asynch::Read;
-use picoserve::extract::{FromRequest, State};
+use picoserve::extract::{FromRequest, Query, State};
 use picoserve::request::{RequestBody, RequestParts};
 use picoserve::response::Json;
+use serde::Serialize;
 
-use crate::config::MutableConfigInstance;
+use crate::config::{ConfigInstance, MutableConfigInstance};
 use crate::error::Error;
 use crate::network::api::types::OkResponse;
-use crate::network::api::utils::deser_from_request;
+use crate::network::api::utils::{
+    deser_from_request, json_response, read_request, BinaryResponse, JsonFormat, JsonResponse,
+};
 use crate::network::api::ApiState;
 
-pub(crate) async fn handle_get(State(state): State<ApiState>) -> Json<MutableConfigInstance> {
-    Json(MutableConfigInstance::from(state.cfg.load().as_ref()))
+pub(crate) async fn handle_get(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> crate::error::Result<JsonResponse> {
+    json_response(
+        &MutableConfigInstance::from(state.cfg.load().as_ref()),
+        &format,
+    )
+}
+
+/// Read-only view of the full effective config (secrets masked).
+pub(crate) async fn handle_get_effective(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> crate::error::Result<JsonResponse> {
+    json_response(state.cfg.load().as_ref(), &format)
+}
+
+/// The config as persisted to flash (CBOR), restored with `/config/import`.
+pub(crate) async fn handle_export(
+    State(state): State<ApiState>,
+) -> crate::error::Result<BinaryResponse> {
+    Ok(BinaryResponse(state.cfg.export()?))
+}
+
+pub(crate) async fn handle_import(
+    State(state): State<ApiState>,
+    req: ImportConfigRequest,
+) -> crate::error::Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    let reset = state.cfg.import(&req.0)?;
+
+    Ok(applied_response(&state, reset))
 }
 
 pub(crate) async fn handle_update(
     State(state): State<ApiState>,
     req: MutableConfigInstance,
 ) -> crate::error::Result<Json<OkResponse>> {
-    state.cfg.apply(req)?;
+    state.check_rate_limit()?;
+
+    let reset = state.cfg.apply(req)?;
+
+    Ok(applied_response(&state, reset))
+}
+
+/// Writes a debounced update to flash now (instead of waiting for the quiet period).
+pub(crate) async fn handle_commit(
+    State(state): State<ApiState>,
+) -> crate::error::Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    let reset = state.cfg.has_pending_reset();
+    if !state.cfg.commit()? {
+        return Ok(Json(OkResponse::new(
+            "no pending config to commit".to_string(),
+        )));
+    }
+    if !reset {
+        return Ok(Json(OkResponse::new("config persisted".to_string())));
+    }
 
     Ok(Json(OkResponse::new(format!(
         "device will reset in {} seconds",
@@ -26,9 +85,20 @@ pub(crate) async fn handle_update(
     ))))
 }
 
+pub(crate) async fn handle_validate(
+    State(state): State<ApiState>,
+    req: MutableConfigInstance,
+) -> crate::error::Result<Json<ValidateConfigResponse>> {
+    state.cfg.validate(&req)?;
+
+    Ok(Json(ValidateConfigResponse { valid: true }))
+}
+
 pub(crate) async fn handle_reset(
     State(state): State<ApiState>,
 ) -> crate::error::Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
     state.cfg.reset()?;
 
     Ok(Json(OkResponse::new(format!(
@@ -37,14 +107,53 @@ pub(crate) async fn handle_reset(
     ))))
 }
 
-impl<'r, State> FromRequest<'r, State> for MutableConfigInstance {
+/// Describes what follows an applied update (`reset` as returned by `Config::apply`).
+pub(crate) fn applied_response(state: &ApiState, reset: bool) -> Json<OkResponse> {
+    if state.cfg.has_pending_write() {
+        return Json(OkResponse::new(format!(
+            "config applied, will be persisted{} after {} ms without changes",
+            if reset { " (and the device reset)" } else { "" },
+            state.cfg.load().config_write_debounce_ms
+        )));
+    }
+    if !reset {
+        return Json(OkResponse::new("config applied".to_string()));
+    }
+
+    Json(OkResponse::new(format!(
+        "device will reset in {} seconds",
+        state.cfg.load().reset_wait_secs
+    )))
+}
+
+#[derive(Serialize)]
+pub(crate) struct ValidateConfigResponse {
+    valid: bool,
+}
+
+/// A `/config/export` download (raw CBOR bytes).
+pub(crate) struct ImportConfigRequest(Vec<u8>);
+
+impl<'r> FromRequest<'r, ApiState> for ImportConfigRequest {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> crate::error::Result<Self> {
+        Ok(Self(read_request(state, request_body).await?.to_vec()))
+    }
+}
+
+impl<'r> FromRequest<'r, ApiState> for MutableConfigInstance {
     type Rejection = Error;
 
     async fn from_request<R: Read>(
-        _state: &'r State,
+        state: &'r ApiState,
         _request_parts: RequestParts<'r>,
         request_body: RequestBody<'r, R>,
     ) -> crate::error::Result<Self> {
-        deser_from_request(request_body).await
+        deser_from_request(state, request_body).await
     }
 }
@@ -0,0 +1,63 @@
+use picoserve::extract::{Query, State};
+use serde::Serialize;
+
+use crate::config::{ConfigInstance, MisterAutoSchedule};
+use crate::error::Result;
+use crate::mister::{setpoint_override, AutoScheduleStateOperator, ACTIVE_AUTO_SCHEDULE};
+use crate::network::api::routes::status::StatusResponse;
+use crate::network::api::utils::{json_response, JsonFormat, JsonResponse};
+use crate::network::api::ApiState;
+
+/// Everything a front panel needs in one round-trip (`/status` plus the active step and its
+/// effective thresholds).
+pub(crate) async fn handle_get(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> Result<JsonResponse> {
+    let cfg = state.cfg.load();
+    let step = ACTIVE_AUTO_SCHEDULE.get_schedule(cfg.as_ref());
+
+    json_response(
+        &DashboardResponse {
+            status: StatusResponse::new(cfg.as_ref()),
+            schedule_step: step,
+            thresholds: DashboardThresholds::new(cfg.as_ref(), step),
+        },
+        &format,
+    )
+}
+
+#[derive(Serialize)]
+pub(crate) struct DashboardResponse<'a> {
+    #[serde(flatten)]
+    status: StatusResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schedule_step: Option<&'a MisterAutoSchedule>,
+    thresholds: DashboardThresholds,
+}
+
+#[derive(Serialize)]
+pub(crate) struct DashboardThresholds {
+    // Effective band of the active step (after adjustments), or of a setpoint override.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    on_rh: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    off_rh: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rh_hard_floor: Option<f32>,
+}
+
+impl DashboardThresholds {
+    pub(crate) fn new(cfg: &ConfigInstance, step: Option<&MisterAutoSchedule>) -> Self {
+        let (on_rh, off_rh) = match setpoint_override() {
+            Some(o) => (Some(o.on_rh(cfg)), Some(o.off_rh(cfg))),
+            None => (step.map(|s| s.on_rh(cfg)), step.map(|s| s.off_rh(cfg))),
+        };
+
+        Self {
+            on_rh,
+            off_rh,
+            rh_hard_floor: cfg.mister_rh_hard_floor,
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use alloc::string::{String, ToString};
+
+use picoserve::extract::{FromRequest, Query, State};
+use picoserve::io::Read;
+use picoserve::request::{RequestBody, RequestParts};
+use picoserve::response::Json;
+use serde::Deserialize;
+
+use crate::display::{ChangeMode, DisplayMessage, Mode as DisplayMode, RENDERED};
+use crate::error::{bad_request_err, Error, Result};
+use crate::network::api::types::OkResponse;
+use crate::network::api::utils::{deser_from_request, json_response, JsonFormat, JsonResponse};
+use crate::network::api::ApiState;
+
+pub(crate) async fn handle_message(
+    State(state): State<ApiState>,
+    req: DisplayMessageRequest,
+) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    // Text that doesn't fit is truncated by the display (secs of 0 clears it).
+    state
+        .display_message_pub
+        .publish_immediate(DisplayMessage::new(req.text, req.secs));
+
+    Ok(Json(OkResponse::default()))
+}
+
+/// Switches the panel to a screen (as the button's hold does, but to a specific one).
+pub(crate) async fn handle_mode(
+    State(state): State<ApiState>,
+    req: DisplayModeRequest,
+) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    if !state.cfg.load().display_enabled {
+        return Err(bad_request_err("display is disabled".to_string()));
+    }
+
+    state
+        .display_change_mode_pub
+        .publish_immediate(ChangeMode::new(Some(req.mode)));
+
+    Ok(Json(OkResponse::default()))
+}
+
+/// Exactly what the panel is showing (as of its last draw).
+pub(crate) async fn handle_get_state(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> Result<JsonResponse> {
+    if !state.cfg.load().display_enabled {
+        return Err(bad_request_err("display is disabled".to_string()));
+    }
+
+    json_response(&*RENDERED.read(), &format)
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DisplayMessageRequest {
+    text: String,
+    secs: u32,
+}
+
+impl<'r> FromRequest<'r, ApiState> for DisplayMessageRequest {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> Result<Self> {
+        deser_from_request(state, request_body).await
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct DisplayModeRequest {
+    // Unknown screens are rejected when deserializing.
+    mode: DisplayMode,
+}
+
+impl<'r> FromRequest<'r, ApiState> for DisplayModeRequest {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> Result<Self> {
+        deser_from_request(state, request_body).await
+    }
+}
@@ -0,0 +1,23 @@
+use alloc::vec::Vec;
+
+use picoserve::extract::Query;
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::events;
+use crate::events::Event;
+use crate::network::api::utils::{json_response, JsonFormat, JsonResponse};
+
+pub(crate) async fn handle_get_history(Query(format): Query<JsonFormat>) -> Result<JsonResponse> {
+    json_response(
+        &EventHistoryResponse {
+            events: events::history(),
+        },
+        &format,
+    )
+}
+
+#[derive(Serialize)]
+pub(crate) struct EventHistoryResponse {
+    events: Vec<Event>,
+}
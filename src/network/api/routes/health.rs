@@ -0,0 +1,60 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use picoserve::extract::{Query, State};
+use serde::Serialize;
+
+use crate::chip_control::ResetRecord;
+use crate::config::ConfigFlashUsage;
+#[cfg(feature = "diag")]
+use crate::diag;
+#[cfg(feature = "diag")]
+use crate::diag::LoopTimes;
+use crate::error::Result;
+use crate::heap::HeapStats;
+use crate::heartbeat::HeartbeatCounts;
+use crate::lag::LagCounts;
+use crate::network::api::utils::{json_response, JsonFormat, JsonResponse};
+use crate::network::api::ApiState;
+use crate::network::wifi;
+use crate::network::wifi::NetworkAddresses;
+use crate::{chip_control, heap, heartbeat, lag};
+
+pub(crate) async fn handle_get(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> Result<JsonResponse> {
+    json_response(
+        &HealthResponse {
+            device_name: state.cfg.load().device_name(),
+            config_flash: state.cfg.flash_usage()?,
+            heartbeats: heartbeat::counts(),
+            pubsub_lag: lag::counts(),
+            heap: heap::stats(),
+            network: wifi::addresses(),
+            reset_history: chip_control::reset_history(),
+            safe_mode: chip_control::is_safe_mode(),
+            #[cfg(feature = "diag")]
+            loop_times: diag::loop_times(),
+        },
+        &format,
+    )
+}
+
+#[derive(Serialize)]
+pub(crate) struct HealthResponse {
+    device_name: String,
+    config_flash: ConfigFlashUsage,
+    heartbeats: HeartbeatCounts,
+    // Subscriber lag events per channel (messages dropped before being handled).
+    pubsub_lag: LagCounts,
+    heap: HeapStats,
+    network: NetworkAddresses,
+    // Most recent first (including this boot).
+    reset_history: Vec<ResetRecord>,
+    // Booted into safe mode after a crash loop (the mister, sensor and controls aren't running).
+    safe_mode: bool,
+    // How long each task took to handle its events (excluding the waits for them).
+    #[cfg(feature = "diag")]
+    loop_times: LoopTimes,
+}
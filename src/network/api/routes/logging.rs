@@ -0,0 +1,56 @@
+use embedded_svc::io::asynch::Read;
+use picoserve::extract::{FromRequest, Query, State};
+use picoserve::request::{RequestBody, RequestParts};
+use picoserve::response::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::logger;
+use crate::logger::LogLevel;
+use crate::network::api::utils::{deser_from_request, json_response, JsonFormat, JsonResponse};
+use crate::network::api::ApiState;
+
+pub(crate) async fn handle_get_level(Query(format): Query<JsonFormat>) -> Result<JsonResponse> {
+    json_response(
+        &LogLevelResponse {
+            level: logger::level(),
+        },
+        &format,
+    )
+}
+
+/// Changes the log level straight away (persisted, so it survives a reset).
+pub(crate) async fn handle_set_level(
+    State(state): State<ApiState>,
+    req: LogLevelRequest,
+) -> Result<Json<LogLevelResponse>> {
+    state.check_rate_limit()?;
+
+    state.cfg.apply_log_level(req.level)?;
+
+    Ok(Json(LogLevelResponse {
+        level: logger::level(),
+    }))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct LogLevelRequest {
+    level: LogLevel,
+}
+
+impl<'r> FromRequest<'r, ApiState> for LogLevelRequest {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> Result<Self> {
+        deser_from_request(state, request_body).await
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct LogLevelResponse {
+    level: LogLevel,
+}
@@ -0,0 +1,135 @@
+use alloc::format;
+use alloc::string::ToString;
+
+use picoserve::extract::{FromRequest, Query, State};
+use picoserve::io::Read;
+use picoserve::request::{RequestBody, RequestParts};
+use picoserve::response::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{bad_request_err, Error, Result};
+use crate::mister::{
+    clear_setpoint_override, is_manual_run, is_mode_auto, is_paused, is_rh_floor_active,
+    set_setpoint_override, setpoint_override, status_elapsed_ms, AutoScheduleStateOperator,
+    Mode as MisterMode, Status as MisterStatus, ACTIVE_AUTO_SCHEDULE, ACTIVE_MODE, STATUS,
+};
+use crate::network::api::routes::dashboard::DashboardThresholds;
+use crate::network::api::types::OkResponse;
+use crate::network::api::utils::{deser_from_request, json_response, JsonFormat, JsonResponse};
+use crate::network::api::ApiState;
+
+const SETPOINT_TTL_MAX_SECS: u32 = 60 * 60 * 24;
+
+/// Just the mister (a small payload for polling the relay), `/status` has everything else.
+pub(crate) async fn handle_get(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> Result<JsonResponse> {
+    let cfg = state.cfg.load();
+    let step = ACTIVE_AUTO_SCHEDULE.get_schedule(cfg.as_ref());
+
+    json_response(
+        &MisterResponse {
+            mode: ACTIVE_MODE.read().clone(),
+            status: STATUS.read().clone(),
+            status_elapsed_ms: status_elapsed_ms(),
+            paused: is_paused(),
+            manual_run: is_manual_run(),
+            rh_floor_active: is_rh_floor_active(),
+            setpoint_override: setpoint_override().map(|o| SetpointOverrideState {
+                rh: o.rh,
+                remaining_ms: o.remaining_ms(),
+            }),
+            thresholds: DashboardThresholds::new(cfg.as_ref(), step),
+        },
+        &format,
+    )
+}
+
+/// Targets an rh in Auto (instead of the schedule step) for a while, the schedule keeps running
+/// underneath and takes back over once it expires.
+pub(crate) async fn handle_set_setpoint(
+    State(state): State<ApiState>,
+    req: SetpointRequest,
+) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    if !(0.0..=100.0).contains(&req.rh) {
+        return Err(bad_request_err(format!(
+            "rh must be between 0 and 100 (got {})",
+            req.rh
+        )));
+    }
+    if req.ttl_secs == 0 || req.ttl_secs > SETPOINT_TTL_MAX_SECS {
+        return Err(bad_request_err(format!(
+            "ttl_secs must be between 1 and {} (got {})",
+            SETPOINT_TTL_MAX_SECS, req.ttl_secs
+        )));
+    }
+    if !is_mode_auto() {
+        return Err(bad_request_err("mister mode is not auto".to_string()));
+    }
+
+    set_setpoint_override(req.rh, req.ttl_secs);
+
+    Ok(Json(OkResponse::new(format!(
+        "mister will target {}% for {} seconds",
+        req.rh, req.ttl_secs
+    ))))
+}
+
+pub(crate) async fn handle_clear_setpoint(
+    State(state): State<ApiState>,
+) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    if !clear_setpoint_override() {
+        return Err(bad_request_err("no setpoint override to clear".to_string()));
+    }
+
+    Ok(Json(OkResponse::new(
+        "setpoint override cleared".to_string(),
+    )))
+}
+
+#[derive(Serialize)]
+pub(crate) struct MisterResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mode: Option<MisterMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<MisterStatus>,
+    // Time in the current status.
+    status_elapsed_ms: u32,
+    paused: bool,
+    manual_run: bool,
+    // The rh hard floor is forcing the mister 'On'.
+    rh_floor_active: bool,
+    // Targeted in Auto instead of the schedule step (until it expires).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    setpoint_override: Option<SetpointOverrideState>,
+    thresholds: DashboardThresholds,
+}
+
+#[derive(Serialize)]
+pub(crate) struct SetpointOverrideState {
+    rh: f32,
+    remaining_ms: u32,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SetpointRequest {
+    rh: f32,
+    ttl_secs: u32,
+}
+
+impl<'r> FromRequest<'r, ApiState> for SetpointRequest {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> Result<Self> {
+        deser_from_request(state, request_body).await
+    }
+}
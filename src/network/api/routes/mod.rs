@@ -1,22 +1,126 @@
-use picoserve::routing::{get, post, PathRouter};
-use picoserve::Router;
+use alloc::string::ToString;
 
-use crate::error::Result;
+use picoserve::io::Read;
+use picoserve::request::{Path, Request};
+use picoserve::response::{IntoResponse, Json, ResponseWriter, StatusCode};
+use picoserve::routing::{
+    get, parse_path_segment, post, put, NoPathParameters, PathRouter, PathRouterService,
+};
+use picoserve::{ResponseSent, Router};
+
+use crate::error::{ApiError, ErrorCode, Result};
 use crate::network::api::ApiState;
 
+#[cfg(feature = "buzzer")]
+pub(crate) mod alarm;
+pub(crate) mod build;
+pub(crate) mod calibration;
 pub(crate) mod chip_control;
 pub(crate) mod config;
+pub(crate) mod dashboard;
+pub(crate) mod display;
+pub(crate) mod events;
+pub(crate) mod health;
+pub(crate) mod logging;
+pub(crate) mod mister;
 pub(crate) mod mode;
+pub(crate) mod ping;
+pub(crate) mod schedule;
+#[cfg(feature = "selftest")]
+pub(crate) mod selftest;
+pub(crate) mod sensor;
 pub(crate) mod status;
 
 pub(crate) fn init() -> Result<Router<impl PathRouter<ApiState> + Sized, ApiState>> {
-    Ok(Router::new()
+    let router = Router::from_service(NotFound)
         .route("/", get(status::handle_get))
         .route("/reset", post(chip_control::handle_reset))
+        .route("/reset/cancel", post(chip_control::handle_cancel_reset))
+        .route(
+            "/restart-network",
+            post(chip_control::handle_restart_network),
+        )
         .route("/status", get(status::handle_get))
+        .route("/status/next", get(status::handle_get_next))
+        .route("/health", get(health::handle_get))
+        .route("/dashboard", get(dashboard::handle_get))
+        .route("/ping", get(ping::handle_get))
+        .route("/build", get(build::handle_get))
+        .route(
+            "/log/level",
+            get(logging::handle_get_level).post(logging::handle_set_level),
+        )
         .route("/mode", get(mode::handle_get))
         .route("/mode/change", post(mode::handle_change))
+        .route("/mode/cycle", post(mode::handle_cycle))
+        .route("/mode/pause", post(mode::handle_pause))
+        .route("/mode/resume", post(mode::handle_resume))
+        .route("/mister", get(mister::handle_get))
+        .route("/mister/run", post(mode::handle_run))
+        .route(
+            "/mister/setpoint",
+            post(mister::handle_set_setpoint).delete(mister::handle_clear_setpoint),
+        )
+        .route("/display/message", post(display::handle_message))
+        .route("/display/mode", post(display::handle_mode))
+        .route("/display/state", get(display::handle_get_state))
+        .route("/sensor/drivers", get(sensor::handle_get_drivers))
+        .route("/sensor/reset", post(sensor::handle_reset))
+        .route("/sensor/calibrate", post(sensor::handle_calibrate))
+        .route(
+            "/calibration",
+            get(calibration::handle_get).patch(calibration::handle_patch),
+        )
         .route("/config", get(config::handle_get))
+        .route("/config/effective", get(config::handle_get_effective))
+        .route("/config/export", get(config::handle_export))
+        .route("/config/import", post(config::handle_import))
         .route("/config/update", post(config::handle_update))
-        .route("/config/reset", post(config::handle_reset)))
+        .route("/config/validate", post(config::handle_validate))
+        .route("/config/commit", post(config::handle_commit))
+        .route("/config/reset", post(config::handle_reset))
+        .route("/schedule", get(schedule::handle_get))
+        .route("/schedule/summary", get(schedule::handle_get_summary))
+        .route("/schedule/goto", post(schedule::handle_goto))
+        .route("/schedule/next", post(schedule::handle_next))
+        .route("/schedule/preset", post(schedule::handle_preset))
+        .route(
+            ("/schedule", parse_path_segment::<usize>()),
+            put(schedule::handle_update_step),
+        )
+        .route("/events/history", get(events::handle_get_history));
+
+    #[cfg(feature = "selftest")]
+    let router = router.route("/selftest", get(selftest::handle_get));
+
+    #[cfg(feature = "buzzer")]
+    let router = router.route("/alarm/mute", post(alarm::handle_mute));
+
+    Ok(router)
+}
+
+/// Fallback for unknown routes (so clients always receive a JSON body).
+struct NotFound;
+
+impl PathRouterService<ApiState> for NotFound {
+    async fn call_request_handler_service<R: Read, W: ResponseWriter<Error = R::Error>>(
+        &self,
+        _state: &ApiState,
+        _current_path_parameters: NoPathParameters,
+        _path: Path<'_>,
+        request: Request<'_, R>,
+        response_writer: W,
+    ) -> core::result::Result<ResponseSent, W::Error> {
+        let connection = request.body_connection.finalize().await?;
+
+        Json(ApiError::new(
+            StatusCode::NOT_FOUND.as_u16(),
+            ErrorCode::NotFound,
+            "route not found".to_string(),
+        ))
+        .into_response()
+        .with_status_code(StatusCode::NOT_FOUND)
+        .write_to(connection, response_writer)
+        .await
+    }
 }
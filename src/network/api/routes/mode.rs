@@ -1,25 +1,31 @@
-use picoserve::extract::{FromRequest, State};
+use alloc::format;
+
+use embassy_time::{Duration, Timer};
+use picoserve::extract::{FromRequest, Query, State};
 use picoserve::io::Read;
 use picoserve::request::{RequestBody, RequestParts};
 use picoserve::response::Json;
 use serde::{Deserialize, Serialize};
 
-use crate::error::{Error, Result};
-use crate::mister::{ChangeMode, Mode as MisterMode, ACTIVE_MODE};
+use crate::error::{bad_request_err, Error, Result};
+use crate::mister::{is_paused, ChangeMode, Mode as MisterMode, ACTIVE_MODE};
 use crate::network::api::types::OkResponse;
-use crate::network::api::utils::deser_from_request;
+use crate::network::api::utils::{deser_from_request, json_response, JsonFormat, JsonResponse};
 use crate::network::api::ApiState;
 
-pub(crate) async fn handle_get() -> Json<GetModeResponse> {
-    Json(GetModeResponse {
-        mode: ACTIVE_MODE.read().clone(),
-    })
+const MODE_SETTLE_MS: u64 = 100;
+const MANUAL_RUN_MAX_SECS: u32 = 3600;
+
+pub(crate) async fn handle_get(Query(format): Query<JsonFormat>) -> Result<JsonResponse> {
+    json_response(&GetModeResponse::new(), &format)
 }
 
 pub(crate) async fn handle_change(
     State(state): State<ApiState>,
     req: ChangeModeRequest,
 ) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
     state
         .change_mode_pub
         .publish_immediate(ChangeMode::new(Some(req.mode)));
@@ -27,9 +33,78 @@ pub(crate) async fn handle_change(
     Ok(Json(OkResponse::default()))
 }
 
+pub(crate) async fn handle_cycle(State(state): State<ApiState>) -> Result<Json<GetModeResponse>> {
+    state.check_rate_limit()?;
+
+    // Same as a button press (Auto -> Off -> On -> Auto).
+    state
+        .change_mode_pub
+        .publish_immediate(ChangeMode::default());
+
+    // Give the mister operation task a moment to apply the new mode.
+    Timer::after(Duration::from_millis(MODE_SETTLE_MS)).await;
+
+    Ok(Json(GetModeResponse::new()))
+}
+
+pub(crate) async fn handle_pause(State(state): State<ApiState>) -> Result<Json<GetModeResponse>> {
+    change_pause(&state, true).await
+}
+
+pub(crate) async fn handle_resume(State(state): State<ApiState>) -> Result<Json<GetModeResponse>> {
+    change_pause(&state, false).await
+}
+
+/// Manual run, the mister is 'On' for `secs` (whatever the mode) then goes back to the mode.
+pub(crate) async fn handle_run(
+    State(state): State<ApiState>,
+    req: ManualRunRequest,
+) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    if req.secs == 0 || req.secs > MANUAL_RUN_MAX_SECS {
+        return Err(bad_request_err(format!(
+            "secs must be between 1 and {} (got {})",
+            MANUAL_RUN_MAX_SECS, req.secs
+        )));
+    }
+
+    state
+        .change_mode_pub
+        .publish_immediate(ChangeMode::run(req.secs));
+
+    Ok(Json(OkResponse::new(format!(
+        "mister will run for {} seconds",
+        req.secs
+    ))))
+}
+
+async fn change_pause(state: &ApiState, paused: bool) -> Result<Json<GetModeResponse>> {
+    state.check_rate_limit()?;
+
+    state
+        .change_mode_pub
+        .publish_immediate(ChangeMode::pause(paused));
+
+    // Give the mister operation task a moment to apply it.
+    Timer::after(Duration::from_millis(MODE_SETTLE_MS)).await;
+
+    Ok(Json(GetModeResponse::new()))
+}
+
 #[derive(Serialize)]
 pub(crate) struct GetModeResponse {
     mode: Option<MisterMode>,
+    paused: bool,
+}
+
+impl GetModeResponse {
+    fn new() -> Self {
+        Self {
+            mode: ACTIVE_MODE.read().clone(),
+            paused: is_paused(),
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -37,14 +112,31 @@ pub(crate) struct ChangeModeRequest {
     mode: MisterMode,
 }
 
-impl<'r, State> FromRequest<'r, State> for ChangeModeRequest {
+impl<'r> FromRequest<'r, ApiState> for ChangeModeRequest {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> Result<Self> {
+        deser_from_request(state, request_body).await
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ManualRunRequest {
+    secs: u32,
+}
+
+impl<'r> FromRequest<'r, ApiState> for ManualRunRequest {
     type Rejection = Error;
 
     async fn from_request<R: Read>(
-        _state: &'r State,
+        state: &'r ApiState,
         _request_parts: RequestParts<'r>,
         request_body: RequestBody<'r, R>,
     ) -> Result<Self> {
-        deser_from_request(request_body).await
+        deser_from_request(state, request_body).await
     }
 }
@@ -0,0 +1,12 @@
+use picoserve::response::Json;
+use serde::Serialize;
+
+/// Cheap liveness check (no flash reads or lock contention, unlike `/status`).
+pub(crate) async fn handle_get() -> Json<PingResponse> {
+    Json(PingResponse { ok: true })
+}
+
+#[derive(Serialize)]
+pub(crate) struct PingResponse {
+    ok: bool,
+}
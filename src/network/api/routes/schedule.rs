@@ -0,0 +1,214 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use embedded_svc::io::asynch::Read;
+use picoserve::extract::{FromRequest, Query, State};
+use picoserve::request::{RequestBody, RequestParts};
+use picoserve::response::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{MisterAutoSchedule, SchedulePreset};
+use crate::error::{bad_request_err, Error, Result};
+use crate::mister::{is_mode_auto, ScheduleControl};
+use crate::network::api::routes::config::applied_response;
+use crate::network::api::types::OkResponse;
+use crate::network::api::utils::{deser_from_request, json_response, JsonFormat, JsonResponse};
+use crate::network::api::ApiState;
+
+pub(crate) async fn handle_get(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> Result<JsonResponse> {
+    let cfg = state.cfg.load();
+
+    json_response(
+        &ScheduleResponse {
+            steps: cfg
+                .mister_auto_schedule
+                .iter()
+                .enumerate()
+                .map(|(idx, step)| ScheduleStep { idx, step })
+                .collect(),
+            looping: cfg.mister_auto_schedule_loop,
+        },
+        &format,
+    )
+}
+
+/// The whole program with the effective thresholds (after adjustments) and durations.
+pub(crate) async fn handle_get_summary(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> Result<JsonResponse> {
+    let cfg = state.cfg.load();
+
+    json_response(
+        &ScheduleSummaryResponse {
+            steps: cfg
+                .mister_auto_schedule
+                .iter()
+                .enumerate()
+                .map(|(idx, step)| ScheduleSummaryStep {
+                    idx,
+                    name: step.name.clone(),
+                    rh: step.rh,
+                    on_rh: step.on_rh(cfg.as_ref()),
+                    off_rh: step.off_rh(cfg.as_ref()),
+                    run_secs: step.run_secs,
+                    max_wait_secs: step.max_wait_secs,
+                    hold_until_target: step.hold_until_target,
+                })
+                .collect(),
+            looping: cfg.mister_auto_schedule_loop,
+            total_run_secs: cfg.mister_auto_schedule_total_secs(),
+            max_wait_secs: cfg
+                .mister_auto_schedule
+                .iter()
+                .map(|s| s.max_wait_secs)
+                .sum(),
+        },
+        &format,
+    )
+}
+
+pub(crate) async fn handle_update_step(
+    idx: usize,
+    State(state): State<ApiState>,
+    req: MisterAutoSchedule,
+) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    let reset = state.cfg.apply_schedule_step(idx, req)?;
+
+    Ok(applied_response(&state, reset))
+}
+
+/// Replaces the whole program with a built-in one.
+pub(crate) async fn handle_preset(
+    State(state): State<ApiState>,
+    req: SchedulePresetRequest,
+) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    let reset = state.cfg.apply_schedule_preset(req.preset)?;
+
+    Ok(applied_response(&state, reset))
+}
+
+pub(crate) async fn handle_goto(
+    State(state): State<ApiState>,
+    req: ScheduleGotoRequest,
+) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    let len = state.cfg.load().mister_auto_schedule.len();
+    if req.idx >= len {
+        return Err(bad_request_err(format!(
+            "mister auto schedule index out of range: {} (len: {})",
+            req.idx, len
+        )));
+    }
+
+    publish_control(&state, ScheduleControl::Goto(req.idx))
+}
+
+pub(crate) async fn handle_next(State(state): State<ApiState>) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    publish_control(&state, ScheduleControl::Next)
+}
+
+fn publish_control(state: &ApiState, control: ScheduleControl) -> Result<Json<OkResponse>> {
+    if !is_mode_auto() {
+        return Err(bad_request_err("mister mode is not auto".to_string()));
+    }
+
+    state.schedule_control_pub.publish_immediate(control);
+
+    Ok(Json(OkResponse::default()))
+}
+
+#[derive(Serialize)]
+pub(crate) struct ScheduleResponse<'a> {
+    steps: Vec<ScheduleStep<'a>>,
+    looping: bool,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ScheduleStep<'a> {
+    idx: usize,
+    #[serde(flatten)]
+    step: &'a MisterAutoSchedule,
+}
+
+impl<'r> FromRequest<'r, ApiState> for MisterAutoSchedule {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> Result<Self> {
+        deser_from_request(state, request_body).await
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct ScheduleSummaryResponse {
+    steps: Vec<ScheduleSummaryStep>,
+    looping: bool,
+    // Run time of the whole program (excluding time spent waiting for the rh band).
+    total_run_secs: u32,
+    // Longest possible wait on top of that (`None` if any step can wait forever).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_wait_secs: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct ScheduleSummaryStep {
+    idx: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    rh: f32,
+    on_rh: f32,
+    off_rh: f32,
+    run_secs: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_wait_secs: Option<u32>,
+    hold_until_target: bool,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ScheduleGotoRequest {
+    idx: usize,
+}
+
+impl<'r> FromRequest<'r, ApiState> for ScheduleGotoRequest {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> Result<Self> {
+        deser_from_request(state, request_body).await
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SchedulePresetRequest {
+    preset: SchedulePreset,
+}
+
+impl<'r> FromRequest<'r, ApiState> for SchedulePresetRequest {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> Result<Self> {
+        deser_from_request(state, request_body).await
+    }
+}
@@ -0,0 +1,26 @@
+use alloc::vec::Vec;
+
+use picoserve::extract::Query;
+use serde::Serialize;
+
+use crate::error::Result;
+use crate::network::api::utils::{json_response, JsonFormat, JsonResponse};
+use crate::selftest;
+use crate::selftest::SelfTestResult;
+
+/// The boot self-test results so far (`complete` once every check has one).
+pub(crate) async fn handle_get(Query(format): Query<JsonFormat>) -> Result<JsonResponse> {
+    json_response(
+        &SelfTestResponse {
+            complete: selftest::is_complete(),
+            results: selftest::results(),
+        },
+        &format,
+    )
+}
+
+#[derive(Serialize)]
+pub(crate) struct SelfTestResponse {
+    complete: bool,
+    results: Vec<SelfTestResult>,
+}
@@ -0,0 +1,106 @@
+use alloc::format;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use embedded_svc::io::asynch::Read;
+use picoserve::extract::{FromRequest, Query, State};
+use picoserve::request::{RequestBody, RequestParts};
+use picoserve::response::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::config::SensorDriver;
+use crate::error::{bad_request_err, Error, Result};
+use crate::network::api::types::OkResponse;
+use crate::network::api::utils::{deser_from_request, json_response, JsonFormat, JsonResponse};
+use crate::network::api::ApiState;
+use crate::sensor;
+
+pub(crate) async fn handle_reset(State(state): State<ApiState>) -> Result<Json<OkResponse>> {
+    state.check_rate_limit()?;
+
+    if !state.cfg.load().sensor_enabled {
+        return Err(bad_request_err("sensor is disabled".to_string()));
+    }
+
+    state.sensor_reset_pub.publish_immediate(());
+
+    Ok(Json(OkResponse::new(
+        "sensor will be reset and re-created".to_string(),
+    )))
+}
+
+/// The sensor drivers this firmware was built with (others fail config validation).
+pub(crate) async fn handle_get_drivers(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> Result<JsonResponse> {
+    json_response(
+        &SensorDriversResponse {
+            drivers: SensorDriver::available(),
+            current: state.cfg.load().sensor_driver.clone(),
+        },
+        &format,
+    )
+}
+
+/// Sets `sensor_calibration_rh_adj` so the current (raw) reading matches a reference hygrometer.
+pub(crate) async fn handle_calibrate(
+    State(state): State<ApiState>,
+    req: SensorCalibrateRequest,
+) -> Result<Json<SensorCalibrateResponse>> {
+    state.check_rate_limit()?;
+
+    if !state.cfg.load().sensor_enabled {
+        return Err(bad_request_err("sensor is disabled".to_string()));
+    }
+
+    if !(0.0..=100.0).contains(&req.reference_rh) {
+        return Err(bad_request_err(format!(
+            "reference_rh out of range: {}",
+            req.reference_rh
+        )));
+    }
+
+    let raw_rh = sensor::raw_rh()
+        .ok_or_else(|| bad_request_err("no sensor reading to calibrate against".to_string()))?;
+    let rh_adj = req.reference_rh - raw_rh;
+
+    // Read every loop, so live without a reset.
+    state.cfg.apply_sensor_calibration_rh_adj(rh_adj)?;
+
+    Ok(Json(SensorCalibrateResponse {
+        raw_rh,
+        reference_rh: req.reference_rh,
+        rh_adj,
+    }))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct SensorCalibrateRequest {
+    reference_rh: f32,
+}
+
+impl<'r> FromRequest<'r, ApiState> for SensorCalibrateRequest {
+    type Rejection = Error;
+
+    async fn from_request<R: Read>(
+        state: &'r ApiState,
+        _request_parts: RequestParts<'r>,
+        request_body: RequestBody<'r, R>,
+    ) -> Result<Self> {
+        deser_from_request(state, request_body).await
+    }
+}
+
+#[derive(Serialize)]
+pub(crate) struct SensorCalibrateResponse {
+    raw_rh: f32,
+    reference_rh: f32,
+    rh_adj: f32,
+}
+
+#[derive(Serialize)]
+pub(crate) struct SensorDriversResponse {
+    drivers: Vec<SensorDriver>,
+    current: SensorDriver,
+}
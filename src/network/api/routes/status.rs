@@ -1,39 +1,131 @@
-use core::ops::Deref;
+use alloc::string::String;
 
-use picoserve::extract::State;
-use picoserve::response::{IntoResponse, Json};
+use embassy_futures::select::select3;
+use embassy_time::{Duration, Timer};
+use picoserve::extract::{Query, State};
 use serde::Serialize;
 
-use crate::config::ConfigInstance;
+#[cfg(feature = "buzzer")]
+use crate::alarm::is_alarm_on;
+#[cfg(feature = "scd40")]
+use crate::co2::CO2_METRICS;
+use crate::config::{ConfigInstance, TemperatureUnit};
+use crate::error::{map_embassy_pub_sub_err, Result};
+#[cfg(feature = "heater")]
+use crate::heater::is_heater_on;
 use crate::mister::{
-    AutoScheduleMode, AutoScheduleState, Mode as MisterMode, Status as MisterStatus,
-    ACTIVE_AUTO_SCHEDULE, ACTIVE_MODE, STATUS,
+    is_paused, is_rh_floor_active, mode_elapsed_ms, status_elapsed_ms, AutoScheduleMode,
+    AutoScheduleState, AutoScheduleStateExt, Mode as MisterMode, Status as MisterStatus,
+    STATUS_CHANGED_CHANNEL,
 };
+use crate::network::api::utils::{json_response, JsonFormat, JsonResponse};
 use crate::network::api::ApiState;
-use crate::sensor::{SensorMetrics, METRICS};
+use crate::sensor::{
+    metrics_age_ms, SensorDriverState, SensorMetrics, CHANNEL as SENSOR_CHANNEL, DRIVER_STATE,
+};
+use crate::snapshot;
+
+// The single API worker (`WEB_TASK_POOL_SIZE`) serves nothing else while a long-poll is held.
+const STATUS_NEXT_MAX_HOLD_SECS: u64 = 30;
 
-pub(crate) async fn handle_get(State(state): State<ApiState>) -> impl IntoResponse {
-    Json(StatusResponse {
-        mode: ACTIVE_MODE.read().clone(),
-        status: STATUS.read().clone(),
-        active_auto_schedule: ActiveAutoSchedule::from(
-            ACTIVE_AUTO_SCHEDULE.read().deref(),
-            state.cfg.load().as_ref(),
-        ),
-        metrics: METRICS.read().clone(),
-    })
+pub(crate) async fn handle_get(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> Result<JsonResponse> {
+    json_response(&StatusResponse::new(state.cfg.load().as_ref()), &format)
+}
+
+/// Long-poll, waits for the next sensor reading or status change (or the max hold) and then
+/// responds like `/status`. Only one can be held at a time.
+pub(crate) async fn handle_get_next(
+    State(state): State<ApiState>,
+    Query(format): Query<JsonFormat>,
+) -> Result<JsonResponse> {
+    let mut sensor_sub = SENSOR_CHANNEL
+        .subscriber()
+        .map_err(map_embassy_pub_sub_err)?;
+    let mut status_changed_sub = STATUS_CHANGED_CHANNEL
+        .subscriber()
+        .map_err(map_embassy_pub_sub_err)?;
+
+    // Any outcome (including lagged) responds with the latest state.
+    let _ = select3(
+        Timer::after(Duration::from_secs(STATUS_NEXT_MAX_HOLD_SECS)),
+        sensor_sub.next_message(),
+        status_changed_sub.next_message(),
+    )
+    .await;
+
+    json_response(&StatusResponse::new(state.cfg.load().as_ref()), &format)
 }
 
 #[derive(Serialize)]
 pub(crate) struct StatusResponse {
+    device_name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     mode: Option<MisterMode>,
+    // Time in the current mode and status.
+    mode_since_ms: u32,
+    status_since_ms: u32,
+    paused: bool,
+    // A schedule step ended without reaching its rh band (e.g. an empty reservoir).
+    target_unreached: bool,
+    // The rh hard floor is forcing the mister 'On'.
+    rh_floor_active: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     status: Option<MisterStatus>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    fault_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     active_auto_schedule: Option<ActiveAutoSchedule>,
     #[serde(skip_serializing_if = "Option::is_none")]
     metrics: Option<SensorMetrics>,
+    // Unit of `metrics.temp`.
+    temp_unit: TemperatureUnit,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics_age_ms: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sensor_driver: Option<SensorDriverState>,
+    #[cfg(feature = "scd40")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    co2_ppm: Option<u16>,
+    #[cfg(feature = "heater")]
+    heater_on: bool,
+    #[cfg(feature = "buzzer")]
+    alarm_on: bool,
+}
+
+impl StatusResponse {
+    pub(crate) fn new(cfg: &ConfigInstance) -> Self {
+        // Taken once, so the control state is consistent (and no control lock is held).
+        let state = snapshot::load();
+
+        Self {
+            device_name: cfg.device_name(),
+            mode: state.mode,
+            mode_since_ms: mode_elapsed_ms(),
+            status_since_ms: status_elapsed_ms(),
+            paused: is_paused(),
+            target_unreached: state.auto_schedule.target_unreached,
+            rh_floor_active: is_rh_floor_active(),
+            status: state.status,
+            fault_reason: state.fault_reason.clone(),
+            active_auto_schedule: ActiveAutoSchedule::from(&state.auto_schedule, cfg),
+            metrics: state.metrics.clone().map(|mut m| {
+                m.temp = cfg.temp_unit.convert_celsius(m.temp);
+                m
+            }),
+            temp_unit: cfg.temp_unit,
+            metrics_age_ms: metrics_age_ms(),
+            sensor_driver: DRIVER_STATE.read().clone(),
+            #[cfg(feature = "scd40")]
+            co2_ppm: CO2_METRICS.read().as_ref().map(|m| m.co2_ppm),
+            #[cfg(feature = "heater")]
+            heater_on: is_heater_on(),
+            #[cfg(feature = "buzzer")]
+            alarm_on: is_alarm_on(),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -47,6 +139,9 @@ pub(crate) struct ActiveAutoSchedule {
     remaining_ms: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     total_ms: Option<u32>,
+    program_total_secs: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    program_elapsed_secs: Option<u32>,
 }
 
 impl ActiveAutoSchedule {
@@ -58,6 +153,8 @@ impl ActiveAutoSchedule {
                 rh: None,
                 remaining_ms: None,
                 total_ms: None,
+                program_total_secs: cfg.mister_auto_schedule_total_secs(),
+                program_elapsed_secs: None,
             }),
             AutoScheduleMode::Pending => {
                 let sched = state.get_auto_schedule(cfg)?;
@@ -68,6 +165,21 @@ impl ActiveAutoSchedule {
                     rh: Some(sched.rh),
                     remaining_ms: None,
                     total_ms: Some(state.total_ms()),
+                    program_total_secs: cfg.mister_auto_schedule_total_secs(),
+                    program_elapsed_secs: Some(state.program_elapsed_secs(cfg)),
+                })
+            }
+            AutoScheduleMode::Holding => {
+                let sched = state.get_auto_schedule(cfg)?;
+
+                Some(Self {
+                    mode: state.mode.clone(),
+                    idx: Some(state.idx),
+                    rh: Some(sched.rh),
+                    remaining_ms: None,
+                    total_ms: Some(state.total_ms()),
+                    program_total_secs: cfg.mister_auto_schedule_total_secs(),
+                    program_elapsed_secs: Some(state.program_elapsed_secs(cfg)),
                 })
             }
             AutoScheduleMode::Running => {
@@ -77,8 +189,10 @@ impl ActiveAutoSchedule {
                     mode: state.mode.clone(),
                     idx: Some(state.idx),
                     rh: Some(sched.rh),
-                    remaining_ms: Some(state.remaining_ms(cfg)?),
+                    remaining_ms: state.remaining_ms(cfg),
                     total_ms: Some(state.total_ms()),
+                    program_total_secs: cfg.mister_auto_schedule_total_secs(),
+                    program_elapsed_secs: Some(state.program_elapsed_secs(cfg)),
                 })
             }
         }
@@ -1,20 +1,139 @@
-use crate::error::{general_fault, Result};
+use crate::error::{bad_request_err, general_fault, request_too_large_err, Result};
 use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
 use embedded_svc::io::asynch::Read;
+use picoserve::io::Write;
 use picoserve::request::RequestBody;
-use serde::de;
+use picoserve::response::{Connection, Content, IntoResponse, Response, ResponseWriter};
+use picoserve::ResponseSent;
+use serde::{de, Deserialize, Serialize};
+
+use crate::network::api::ApiState;
 
 pub(crate) async fn deser_from_request<'r, T, R: Read>(
+    state: &ApiState,
     request_body: RequestBody<'r, R>,
 ) -> Result<T>
 where
     T: de::Deserialize<'r>,
 {
-    serde_json::from_slice(
-        request_body
-            .read_all()
+    serde_json::from_slice(read_request(state, request_body).await?)
+        .map_err(|e| bad_request_err(format!("failed to parse JSON from request: {:?}", e)))
+}
+
+/// The raw request body (limited to `api_max_body_bytes`).
+pub(crate) async fn read_request<'r, R: Read>(
+    state: &ApiState,
+    request_body: RequestBody<'r, R>,
+) -> Result<&'r [u8]> {
+    // Reject before buffering anything (based on the Content-Length header).
+    let max_bytes = state.cfg.load().api_max_body_bytes;
+    if request_body.content_length() > max_bytes {
+        return Err(request_too_large_err(
+            request_body.content_length(),
+            max_bytes,
+        ));
+    }
+
+    request_body
+        .read_all()
+        .await
+        .map(|bytes| &*bytes)
+        .map_err(|e| general_fault(format!("failed to read data from request: {:?}", e)))
+}
+
+/// Serializes a response, pretty printed when requested with `?pretty=1` (compact by default
+/// to save bytes).
+pub(crate) fn json_response<T: Serialize>(value: &T, format: &JsonFormat) -> Result<JsonResponse> {
+    let body = if format.is_pretty() {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    }
+    .map_err(|e| general_fault(format!("failed to serialize JSON response: {:?}", e)))?;
+
+    Ok(JsonResponse(body))
+}
+
+/// Query params controlling JSON output (i.e. `?pretty=1`).
+#[derive(Deserialize, Default)]
+pub(crate) struct JsonFormat {
+    #[serde(default)]
+    pretty: Option<u8>,
+}
+
+impl JsonFormat {
+    fn is_pretty(&self) -> bool {
+        matches!(self.pretty, Some(v) if v > 0)
+    }
+}
+
+pub(crate) struct JsonResponse(String);
+
+impl IntoResponse for JsonResponse {
+    async fn write_to<R: Read, W: ResponseWriter<Error = R::Error>>(
+        self,
+        connection: Connection<'_, R>,
+        response_writer: W,
+    ) -> core::result::Result<ResponseSent, W::Error> {
+        response_writer
+            .write_response(connection, Response::ok(JsonContent(self.0)))
             .await
-            .map_err(|e| general_fault(format!("failed to read data from request: {:?}", e)))?,
-    )
-    .map_err(|e| general_fault(format!("failed to parse JSON from request: {:?}", e)))
+    }
+}
+
+/// Raw bytes (i.e. a CBOR download).
+pub(crate) struct BinaryResponse(pub(crate) Vec<u8>);
+
+impl IntoResponse for BinaryResponse {
+    async fn write_to<R: Read, W: ResponseWriter<Error = R::Error>>(
+        self,
+        connection: Connection<'_, R>,
+        response_writer: W,
+    ) -> core::result::Result<ResponseSent, W::Error> {
+        response_writer
+            .write_response(connection, Response::ok(BinaryContent(self.0)))
+            .await
+    }
+}
+
+struct BinaryContent(Vec<u8>);
+
+impl Content for BinaryContent {
+    fn content_type(&self) -> &'static str {
+        "application/octet-stream"
+    }
+
+    fn content_length(&self) -> usize {
+        self.0.len()
+    }
+
+    async fn write_content<R: Read, W: Write>(
+        self,
+        _connection: Connection<'_, R>,
+        mut writer: W,
+    ) -> core::result::Result<(), W::Error> {
+        writer.write_all(&self.0).await
+    }
+}
+
+struct JsonContent(String);
+
+impl Content for JsonContent {
+    fn content_type(&self) -> &'static str {
+        "application/json"
+    }
+
+    fn content_length(&self) -> usize {
+        self.0.len()
+    }
+
+    async fn write_content<R: Read, W: Write>(
+        self,
+        _connection: Connection<'_, R>,
+        mut writer: W,
+    ) -> core::result::Result<(), W::Error> {
+        writer.write_all(self.0.as_bytes()).await
+    }
 }
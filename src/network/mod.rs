@@ -1,9 +1,17 @@
 pub(crate) mod api;
+pub(crate) mod telemetry;
+pub(crate) mod webhook;
 pub(crate) mod wifi;
 
 use alloc::boxed::Box;
+use alloc::format;
+
 use embassy_executor::Spawner;
-use embassy_net::{Config as NetConfig, Stack, StackResources};
+use embassy_net::tcp::TcpSocket;
+use embassy_net::{
+    Config as NetConfig, ConfigV6, Ipv6Address, Ipv6Cidr, Stack, StackResources, StaticConfigV6,
+};
+use embassy_net_driver::{Driver, HardwareAddress};
 use esp_hal::clock::Clocks;
 use esp_hal::peripherals::{RNG, TIMG1, WIFI};
 use esp_hal::system::RadioClockControl;
@@ -12,11 +20,16 @@ use esp_hal::Rng;
 use esp_wifi::wifi::{WifiDevice, WifiStaDevice};
 use esp_wifi::{initialize, EspWifiInitFor};
 
+use crate::chip_control::CHIP_CONTROL_CHANNEL;
 use crate::config::Config;
-use crate::error::{map_embassy_spawn_err, map_wifi_err, map_wifi_init_err, Result};
+use crate::error::{
+    general_fault, map_embassy_pub_sub_err, map_embassy_spawn_err, map_wifi_err, map_wifi_init_err,
+    Result,
+};
 use crate::network::api::WEB_TASK_POOL_SIZE;
 
-pub(crate) const STACK_POOL_SIZE: usize = WEB_TASK_POOL_SIZE + 3;
+// Extra sockets for the webhook and the telemetry server.
+pub(crate) const STACK_POOL_SIZE: usize = WEB_TASK_POOL_SIZE + 5;
 
 pub(crate) fn init(
     cfg: Config,
@@ -39,7 +52,11 @@ pub(crate) fn init(
     let (wifi_interface, controller) =
         esp_wifi::wifi::new_with_mode(&init, wifi, WifiStaDevice).map_err(map_wifi_err)?;
 
-    let config = NetConfig::dhcpv4(Default::default());
+    let mut config = NetConfig::dhcpv4(Default::default());
+    // No SLAAC/DHCPv6 in the stack, so only the link-local address is configured.
+    if let HardwareAddress::Ethernet(mac) = wifi_interface.hardware_address() {
+        config.ipv6 = ConfigV6::Static(link_local_v6(mac));
+    }
     let stack_resources = Box::leak(Box::new(StackResources::<STACK_POOL_SIZE>::new()));
     let seed = 1234; // very random, very secure seed
 
@@ -51,9 +68,20 @@ pub(crate) fn init(
         .map_err(map_embassy_spawn_err)?;
 
     spawner
-        .spawn(wifi::connection(cfg.clone(), stack, controller))
+        .spawn(wifi::connection(
+            cfg.clone(),
+            stack,
+            controller,
+            CHIP_CONTROL_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+        ))
         .map_err(map_embassy_spawn_err)?;
 
+    webhook::init(cfg.clone(), stack, spawner)?;
+
+    telemetry::init(cfg.clone(), stack, spawner)?;
+
     api::init(cfg, stack, spawner)?;
 
     Ok(())
@@ -65,3 +93,50 @@ pub async fn net_stack(stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>
 
     stack.run().await
 }
+
+// Utils
+
+/// Writes all of `buf` (`what` names it in errors), a write of 0 bytes means the peer closed.
+pub(crate) async fn write_all(
+    socket: &mut TcpSocket<'_>,
+    mut buf: &[u8],
+    what: &str,
+) -> Result<()> {
+    while !buf.is_empty() {
+        let n = socket
+            .write(buf)
+            .await
+            .map_err(|e| general_fault(format!("failed to write {}: {:?}", what, e)))?;
+
+        if n == 0 {
+            return Err(general_fault(format!(
+                "failed to write {}: connection closed",
+                what
+            )));
+        }
+
+        buf = &buf[n..];
+    }
+
+    Ok(())
+}
+
+/// Link-local (fe80::/64) address from the MAC (modified EUI-64).
+fn link_local_v6(mac: [u8; 6]) -> StaticConfigV6 {
+    let address = Ipv6Address::new(
+        0xfe80,
+        0,
+        0,
+        0,
+        u16::from_be_bytes([mac[0] ^ 0x02, mac[1]]),
+        u16::from_be_bytes([mac[2], 0xff]),
+        u16::from_be_bytes([0xfe, mac[3]]),
+        u16::from_be_bytes([mac[4], mac[5]]),
+    );
+
+    StaticConfigV6 {
+        address: Ipv6Cidr::new(address, 64),
+        gateway: None,
+        dns_servers: Default::default(),
+    }
+}
@@ -0,0 +1,132 @@
+use alloc::format;
+
+use embassy_executor::Spawner;
+use embassy_net::tcp::TcpSocket;
+use embassy_net::Stack;
+use embassy_sync::pubsub::WaitResult;
+use embassy_time::{Duration, Timer};
+use esp_wifi::wifi::{WifiDevice, WifiStaDevice};
+
+use crate::config::Config;
+use crate::error::{general_fault, map_embassy_pub_sub_err, map_embassy_spawn_err, Result};
+use crate::lag;
+use crate::lag::LagChannel;
+use crate::network::api::wait_for_net;
+use crate::network::write_all;
+use crate::sensor;
+use crate::sensor::SensorSubscriber;
+
+pub(crate) fn init(
+    cfg: Config,
+    stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>,
+    spawner: &Spawner,
+) -> Result<()> {
+    let cfg = cfg.load();
+
+    // Unset (or 0) disables the telemetry server.
+    let port = match cfg.telemetry_port {
+        Some(port) if port > 0 => port,
+        _ => return Ok(()),
+    };
+
+    if !cfg.sensor_enabled {
+        log::info!("Sensor disabled, not starting the telemetry server");
+        return Ok(());
+    }
+
+    spawner
+        .spawn(telemetry_task(
+            port,
+            stack,
+            sensor::CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+        ))
+        .map_err(map_embassy_spawn_err)
+}
+
+#[embassy_executor::task]
+async fn telemetry_task(
+    port: u16,
+    stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>,
+    mut sensor_sub: SensorSubscriber,
+) {
+    let mut rx_buffer = [0; 64];
+    let mut tx_buffer = [0; 512];
+
+    log::info!("Started: Telemetry task (waiting for WIFI...)");
+
+    wait_for_net(stack).await;
+
+    log::info!("Telemetry: Listening on port {}", port);
+
+    loop {
+        if let Err(e) =
+            telemetry_task_poll(port, stack, &mut rx_buffer, &mut tx_buffer, &mut sensor_sub).await
+        {
+            log::warn!("telemetry task poll failed: {:?}", e);
+
+            // Some sleep to avoid thrashing.
+            Timer::after(Duration::from_millis(500)).await;
+        }
+    }
+}
+
+/// Serves a single client (until it goes away), then returns to accept the next.
+async fn telemetry_task_poll(
+    port: u16,
+    stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>,
+    rx_buffer: &mut [u8],
+    tx_buffer: &mut [u8],
+    sensor_sub: &mut SensorSubscriber,
+) -> Result<()> {
+    let mut socket = TcpSocket::new(stack, rx_buffer, tx_buffer);
+
+    socket
+        .accept(port)
+        .await
+        .map_err(|e| general_fault(format!("failed to accept telemetry client: {:?}", e)))?;
+
+    log::info!("Telemetry client connected: {:?}", socket.remote_endpoint());
+
+    let res = telemetry_stream(&mut socket, sensor_sub).await;
+
+    socket.abort();
+    let _ = socket.flush().await;
+
+    log::info!("Telemetry client disconnected");
+
+    res
+}
+
+async fn telemetry_stream(
+    socket: &mut TcpSocket<'_>,
+    sensor_sub: &mut SensorSubscriber,
+) -> Result<()> {
+    // Don't replay a reading from before the client connected.
+    while sensor_sub.try_next_message().is_some() {}
+
+    loop {
+        let metrics = match sensor_sub.next_message().await {
+            WaitResult::Lagged(count) => {
+                log::warn!("telemetry sensor subscriber lagged by {} messages", count);
+                lag::record(LagChannel::Sensor);
+
+                // Ignore
+                continue;
+            }
+            WaitResult::Message(metrics) => metrics,
+        };
+
+        // Faults (no reading) aren't streamed.
+        let Some(metrics) = metrics else {
+            continue;
+        };
+
+        let mut line = serde_json::to_vec(&metrics)
+            .map_err(|e| general_fault(format!("failed to serialize telemetry: {:?}", e)))?;
+        line.push(b'\n');
+
+        write_all(socket, &line, "telemetry").await?;
+    }
+}
@@ -0,0 +1,287 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::str::FromStr;
+
+use embassy_executor::Spawner;
+use embassy_futures::select::{select, Either};
+use embassy_net::tcp::TcpSocket;
+use embassy_net::{IpAddress, IpEndpoint, Ipv4Address, Stack};
+use embassy_sync::pubsub::WaitResult;
+use embassy_time::{with_timeout, Duration, Timer};
+use esp_wifi::wifi::{WifiDevice, WifiStaDevice};
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::error::{
+    general_fault, invalid_config_err, map_embassy_pub_sub_err, map_embassy_spawn_err, Result,
+};
+use crate::lag;
+use crate::lag::LagChannel;
+use crate::mister;
+use crate::mister::{Mode, ModeChangedSubscriber, Status, StatusChangedSubscriber};
+use crate::network::write_all;
+use crate::utils::get_time_ms;
+
+const WEBHOOK_DEFAULT_PORT: u16 = 80;
+static WEBHOOK_TIMEOUT_SECS: u64 = 5;
+static WEBHOOK_RETRY_DELAY_MS: u64 = 1000;
+
+pub(crate) fn init(
+    cfg: Config,
+    stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>,
+    spawner: &Spawner,
+) -> Result<()> {
+    spawner
+        .spawn(webhook_task(
+            cfg,
+            stack,
+            mister::MODE_CHANGED_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+            mister::STATUS_CHANGED_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
+        ))
+        .map_err(map_embassy_spawn_err)
+}
+
+#[embassy_executor::task]
+async fn webhook_task(
+    cfg: Config,
+    stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>,
+    mut mode_changed_sub: ModeChangedSubscriber,
+    mut status_changed_sub: StatusChangedSubscriber,
+) {
+    log::info!("Started: Webhook task");
+
+    loop {
+        if let Err(e) =
+            webhook_task_poll(&cfg, stack, &mut mode_changed_sub, &mut status_changed_sub).await
+        {
+            log::warn!("webhook task poll failed: {:?}", e);
+
+            // Some sleep to avoid thrashing.
+            Timer::after(Duration::from_millis(500)).await;
+        }
+    }
+}
+
+async fn webhook_task_poll(
+    cfg: &Config,
+    stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>,
+    mode_changed_sub: &mut ModeChangedSubscriber,
+    status_changed_sub: &mut StatusChangedSubscriber,
+) -> Result<()> {
+    let payload = match select(
+        mode_changed_sub.next_message(),
+        status_changed_sub.next_message(),
+    )
+    .await
+    {
+        Either::First(r) => match r {
+            WaitResult::Lagged(count) => {
+                log::warn!("webhook mode subscriber lagged by {} messages", count);
+                lag::record(LagChannel::MisterModeChanged);
+                return Ok(());
+            }
+            WaitResult::Message(mode) => WebhookPayload::new(
+                WebhookEvent::ModeChanged,
+                Some(mode),
+                *mister::STATUS.read(),
+            ),
+        },
+        Either::Second(r) => match r {
+            WaitResult::Lagged(count) => {
+                log::warn!("webhook status subscriber lagged by {} messages", count);
+                lag::record(LagChannel::MisterStatusChanged);
+                return Ok(());
+            }
+            WaitResult::Message(status) => WebhookPayload::new(
+                WebhookEvent::StatusChanged,
+                *mister::ACTIVE_MODE.read(),
+                Some(status),
+            ),
+        },
+    };
+
+    // An empty url disables the webhook (so it can be cleared via the API).
+    let url = match cfg.load().webhook_url.as_deref() {
+        Some(url) if !url.is_empty() => WebhookUrl::parse(url)?,
+        _ => return Ok(()),
+    };
+
+    if stack.config_v4().is_none() {
+        log::warn!("Skipping webhook {:?}, network is not up", payload.event);
+        return Ok(());
+    }
+
+    let body = serde_json::to_vec(&payload)
+        .map_err(|e| general_fault(format!("failed to serialize webhook payload: {:?}", e)))?;
+
+    if let Err(e) = webhook_post(stack, &url, &body).await {
+        log::warn!("Webhook POST failed, retrying once: {:?}", e);
+        Timer::after(Duration::from_millis(WEBHOOK_RETRY_DELAY_MS)).await;
+
+        webhook_post(stack, &url, &body).await?;
+    }
+
+    log::debug!("Sent webhook: {:?}", payload.event);
+
+    Ok(())
+}
+
+async fn webhook_post(
+    stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>,
+    url: &WebhookUrl,
+    body: &[u8],
+) -> Result<()> {
+    let mut rx_buffer = [0; 256];
+    let mut tx_buffer = [0; 512];
+    let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+    socket.set_timeout(Some(Duration::from_secs(WEBHOOK_TIMEOUT_SECS)));
+
+    let res = match with_timeout(
+        Duration::from_secs(WEBHOOK_TIMEOUT_SECS),
+        webhook_exchange(&mut socket, url, body),
+    )
+    .await
+    {
+        Ok(res) => res,
+        Err(_) => Err(general_fault("webhook request timed out".to_string())),
+    };
+
+    socket.abort();
+
+    res
+}
+
+async fn webhook_exchange(socket: &mut TcpSocket<'_>, url: &WebhookUrl, body: &[u8]) -> Result<()> {
+    socket
+        .connect(url.endpoint)
+        .await
+        .map_err(|e| general_fault(format!("failed to connect to webhook: {:?}", e)))?;
+
+    let head = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+        Content-Length: {}\r\nConnection: close\r\n\r\n",
+        url.path,
+        url.host,
+        body.len()
+    );
+    write_all(socket, head.as_bytes(), "webhook request").await?;
+    write_all(socket, body, "webhook request").await?;
+    socket
+        .flush()
+        .await
+        .map_err(|e| general_fault(format!("failed to flush webhook request: {:?}", e)))?;
+
+    // Only the status line matters (i.e. 'HTTP/1.1 200').
+    let mut buf = [0u8; 12];
+    let mut len = 0;
+    while len < buf.len() {
+        match socket.read(&mut buf[len..]).await {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) => {
+                return Err(general_fault(format!(
+                    "failed to read webhook response: {:?}",
+                    e
+                )))
+            }
+        }
+    }
+
+    match parse_status_code(&buf[..len]) {
+        Some(code) if (200..300).contains(&code) => Ok(()),
+        Some(code) => Err(general_fault(format!(
+            "webhook responded with status: {}",
+            code
+        ))),
+        None => Err(general_fault("webhook response was not HTTP".to_string())),
+    }
+}
+
+// Models
+
+#[derive(Copy, Clone, Debug, Serialize)]
+enum WebhookEvent {
+    ModeChanged,
+    StatusChanged,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct WebhookPayload {
+    event: WebhookEvent,
+    mode: Option<Mode>,
+    status: Option<Status>,
+    timestamp_ms: u32,
+}
+
+impl WebhookPayload {
+    fn new(event: WebhookEvent, mode: Option<Mode>, status: Option<Status>) -> Self {
+        Self {
+            event,
+            mode,
+            status,
+            timestamp_ms: get_time_ms(),
+        }
+    }
+}
+
+pub(crate) struct WebhookUrl {
+    endpoint: IpEndpoint,
+    host: String,
+    path: String,
+}
+
+impl WebhookUrl {
+    /// Parses `http://<ipv4>[:port][/path]` (there is no DNS or TLS in the network stack).
+    pub(crate) fn parse(url: &str) -> Result<Self> {
+        let rest = url.strip_prefix("http://").ok_or_else(|| {
+            invalid_config_err(format!(
+                "webhook url must start with 'http://' (got '{}')",
+                url
+            ))
+        })?;
+
+        let (authority, path) = match rest.find('/') {
+            Some(idx) => rest.split_at(idx),
+            None => (rest, "/"),
+        };
+
+        let (host, port) = match authority.split_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse::<u16>().map_err(|_| {
+                    invalid_config_err(format!("webhook url has an invalid port: '{}'", port))
+                })?,
+            ),
+            None => (authority, WEBHOOK_DEFAULT_PORT),
+        };
+
+        let addr = Ipv4Address::from_str(host).map_err(|_| {
+            invalid_config_err(format!(
+                "webhook url host must be an IPv4 address (got '{}')",
+                host
+            ))
+        })?;
+
+        Ok(Self {
+            endpoint: IpEndpoint::new(IpAddress::Ipv4(addr), port),
+            host: authority.to_string(),
+            path: path.to_string(),
+        })
+    }
+}
+
+// Utils
+
+fn parse_status_code(head: &[u8]) -> Option<u16> {
+    core::str::from_utf8(head)
+        .ok()?
+        .strip_prefix("HTTP/1.")?
+        .split(' ')
+        .nth(1)?
+        .parse()
+        .ok()
+}
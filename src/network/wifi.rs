@@ -1,31 +1,68 @@
-use crate::config::Config;
+use crate::config::{Config, ConfigInstance};
 use alloc::format;
-use alloc::string::ToString;
+use alloc::string::{String, ToString};
+use embassy_futures::select::{select3, Either3};
 use embassy_net::Stack;
+use embassy_sync::pubsub::WaitResult;
 use embassy_time::{Duration, Timer};
 use esp_wifi::wifi::{
-    ClientConfiguration, Configuration, WifiController, WifiDevice, WifiEvent, WifiStaDevice,
-    WifiState,
+    AuthMethod, ClientConfiguration, Configuration, WifiController, WifiDevice, WifiEvent,
+    WifiStaDevice, WifiState,
 };
-use smoltcp::wire::Ipv4Address;
+use serde::Serialize;
+use smoltcp::wire::{Ipv4Address, Ipv6Address};
 use spin::RwLock;
 
+use crate::chip_control::{ChipControlAction, ChipControlSubscriber};
 use crate::error::{general_fault, Result};
+use crate::heartbeat;
+use crate::heartbeat::HeartbeatTask;
+use crate::lag;
+use crate::lag::LagChannel;
+
+static CONNECTED_CHECK_INTERVAL_SECS: u64 = 30;
+static RECONNECT_BASE_MS: u32 = 5000;
 
 pub(crate) static IP_ADDRESS: RwLock<Option<Ipv4Address>> = RwLock::new(None);
+pub(crate) static IPV6_ADDRESS: RwLock<Option<Ipv6Address>> = RwLock::new(None);
+
+// WPA2-Enterprise API from the wpa_supplicant blob (esp-wifi has no safe wrapper).
+extern "C" {
+    fn esp_wifi_sta_wpa2_ent_set_identity(identity: *const u8, len: i32) -> i32;
+    fn esp_wifi_sta_wpa2_ent_set_username(username: *const u8, len: i32) -> i32;
+    fn esp_wifi_sta_wpa2_ent_set_password(password: *const u8, len: i32) -> i32;
+    fn esp_wifi_sta_wpa2_ent_enable() -> i32;
+}
 
 #[embassy_executor::task]
 pub async fn connection(
     cfg: Config,
     stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>,
     mut controller: WifiController<'static>,
+    mut chip_control_sub: ChipControlSubscriber,
 ) {
     log::info!("Started: WIFI connection task");
 
+    let mut attempts: u32 = 0;
     loop {
-        if let Err(e) = connection_poll(cfg.clone(), stack, &mut controller).await {
-            log::error!("Failed to poll WIFI connection status: {:?}", e);
-            Timer::after(Duration::from_millis(10000)).await
+        heartbeat::beat(HeartbeatTask::Network);
+
+        match connection_poll(cfg.clone(), stack, &mut controller, &mut chip_control_sub).await {
+            Ok(_) => {
+                attempts = 0;
+            }
+            Err(e) => {
+                let delay_ms = reconnect_delay_ms(attempts, cfg.load().wifi_reconnect_max_ms);
+                attempts = attempts.saturating_add(1);
+
+                log::error!(
+                    "Failed to poll WIFI connection status [attempt {}, retry in {} ms]: {:?}",
+                    attempts,
+                    delay_ms,
+                    e
+                );
+                Timer::after(Duration::from_millis(delay_ms as u64)).await
+            }
         }
     }
 }
@@ -34,38 +71,92 @@ async fn connection_poll(
     cfg: Config,
     stack: &'static Stack<WifiDevice<'static, WifiStaDevice>>,
     controller: &mut WifiController<'static>,
+    chip_control_sub: &mut ChipControlSubscriber,
 ) -> Result<()> {
     let cfg = cfg.load();
 
     match esp_wifi::wifi::get_wifi_state() {
         WifiState::StaConnected => {
-            // wait until we're no longer connected
-            controller.wait_for_event(WifiEvent::StaDisconnected).await;
-            Timer::after(Duration::from_millis(5000)).await
+            // wait until we're no longer connected (or asked to restart), waking
+            // periodically so the heartbeat keeps advancing.
+            match select3(
+                controller.wait_for_event(WifiEvent::StaDisconnected),
+                chip_control_sub.next_message(),
+                Timer::after(Duration::from_secs(CONNECTED_CHECK_INTERVAL_SECS)),
+            )
+            .await
+            {
+                Either3::First(_) => {
+                    Timer::after(Duration::from_millis(5000)).await;
+                }
+                Either3::Second(WaitResult::Message(ChipControlAction::RestartNetwork)) => {
+                    log::warn!("Restarting WIFI connection");
+
+                    controller
+                        .disconnect()
+                        .await
+                        .map_err(|e| general_fault(format!("failed to disconnect: {:?}", e)))?;
+                }
+                Either3::Second(WaitResult::Lagged(count)) => {
+                    log::warn!("wifi chip control subscriber lagged by {} messages", count);
+                    lag::record(LagChannel::ChipControl);
+
+                    return Ok(());
+                }
+                Either3::Second(WaitResult::Message(_)) => {
+                    // Not for us.
+                    return Ok(());
+                }
+                Either3::Third(_) => {
+                    // Still connected.
+                    return Ok(());
+                }
+            }
+
+            let _ = IP_ADDRESS.write().take();
+            let _ = IPV6_ADDRESS.write().take();
         }
         _ => {}
     }
 
-    let client_config = Configuration::Client(ClientConfiguration {
-        ssid: cfg
-            .wifi_ssid
-            .as_str()
-            .try_into()
-            .map_err(|e| general_fault(format!("failed to cast SSID: {:?}", e)))?,
-        password: cfg
-            .wifi_password
-            .as_str()
-            .try_into()
-            .map_err(|e| general_fault(format!("failed to cast PASSWORD: {:?}", e)))?,
-        ..Default::default()
-    });
+    let client_config = if cfg.wifi_eap_enabled() {
+        Configuration::Client(ClientConfiguration {
+            ssid: cfg
+                .wifi_ssid
+                .as_str()
+                .try_into()
+                .map_err(|e| general_fault(format!("failed to cast SSID: {:?}", e)))?,
+            auth_method: AuthMethod::WPA2Enterprise,
+            ..Default::default()
+        })
+    } else {
+        Configuration::Client(ClientConfiguration {
+            ssid: cfg
+                .wifi_ssid
+                .as_str()
+                .try_into()
+                .map_err(|e| general_fault(format!("failed to cast SSID: {:?}", e)))?,
+            password: cfg
+                .wifi_password
+                .as_str()
+                .try_into()
+                .map_err(|e| general_fault(format!("failed to cast PASSWORD: {:?}", e)))?,
+            ..Default::default()
+        })
+    };
 
     controller
         .set_configuration(&client_config)
         .map_err(|e| general_fault(format!("failed to set configuration: {:?}", e)))?;
+
+    if cfg.wifi_eap_enabled() {
+        configure_enterprise(&cfg)?;
+    }
+
     log::info!(
-        "WIFI device configured [SSID: {}, HW: {}]",
+        "WIFI device configured [SSID: {}, EAP: {}, HW: {}]",
         cfg.wifi_ssid.as_str(),
+        cfg.wifi_eap_enabled(),
         stack.hardware_address()
     );
 
@@ -104,5 +195,76 @@ async fn connection_poll(
         let _ = IP_ADDRESS.write().insert(ip_addr);
     }
 
+    if let Some(v6) = stack.config_v6() {
+        let ipv6_addr = v6.address.address();
+        log::info!("IPv6 address: {:?}", ipv6_addr.to_string());
+
+        let _ = IPV6_ADDRESS.write().insert(ipv6_addr);
+    }
+
+    Ok(())
+}
+
+pub(crate) fn addresses() -> NetworkAddresses {
+    NetworkAddresses {
+        ipv4: IP_ADDRESS.read().as_ref().map(|ip| ip.to_string()),
+        ipv6: IPV6_ADDRESS.read().as_ref().map(|ip| ip.to_string()),
+    }
+}
+
+// Models
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct NetworkAddresses {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv4: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ipv6: Option<String>,
+}
+
+// Utils
+
+/// Doubles the base delay for each consecutive failure (capped at `max_ms`).
+fn reconnect_delay_ms(attempts: u32, max_ms: u32) -> u32 {
+    RECONNECT_BASE_MS
+        .saturating_mul(2_u32.saturating_pow(attempts))
+        .min(max_ms.max(RECONNECT_BASE_MS))
+}
+
+/// Sets the WPA2-Enterprise credentials (must follow `set_configuration`).
+fn configure_enterprise(cfg: &ConfigInstance) -> Result<()> {
+    let (Some(username), Some(password)) = (&cfg.wifi_eap_username, &cfg.wifi_eap_password) else {
+        return Err(general_fault(
+            "missing WPA2-Enterprise username or password".to_string(),
+        ));
+    };
+    let identity = cfg.wifi_eap_identity.as_ref().unwrap_or(username);
+
+    // The supplicant copies the credentials, so they only need to outlive each call.
+    unsafe {
+        check_enterprise_res(
+            "identity",
+            esp_wifi_sta_wpa2_ent_set_identity(identity.as_ptr(), identity.len() as i32),
+        )?;
+        check_enterprise_res(
+            "username",
+            esp_wifi_sta_wpa2_ent_set_username(username.as_ptr(), username.len() as i32),
+        )?;
+        check_enterprise_res(
+            "password",
+            esp_wifi_sta_wpa2_ent_set_password(password.as_ptr(), password.len() as i32),
+        )?;
+        check_enterprise_res("enable", esp_wifi_sta_wpa2_ent_enable())
+    }
+}
+
+fn check_enterprise_res(step: &str, res: i32) -> Result<()> {
+    if res != 0 {
+        return Err(general_fault(format!(
+            "failed to configure WPA2-Enterprise ({}): {}",
+            step, res
+        )));
+    }
+
     Ok(())
 }
@@ -0,0 +1,205 @@
+//! One-shot boot self-test for bring-up of assembled units (only with the `selftest` feature).
+//!
+//! Flash is checked directly at boot, the rest pass once the normal init brings them up (a
+//! sensor reading, the display flushed, WIFI associated) within `SELFTEST_TIMEOUT_SECS`.
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Instant, Timer};
+use embedded_storage::{ReadStorage, Storage};
+use esp_storage::FlashStorage;
+use serde::Serialize;
+use spin::RwLock;
+
+use crate::chip_control;
+use crate::config::Config;
+use crate::error::{general_fault, map_embassy_spawn_err, Result};
+use crate::network::wifi::IP_ADDRESS;
+use crate::sensor::METRICS;
+
+// In the boot sector (after the reset log and unstable boot counter), restored after the check.
+const SELFTEST_FLASH_ADDR: u32 = chip_control::BOOT_FLASH_SECTOR_ADDR + 0x100;
+const SELFTEST_FLASH_PATTERN: [u8; 4] = [0xa5, 0x5a, 0x0f, 0xf0];
+const SELFTEST_TIMEOUT_SECS: u64 = 60;
+const SELFTEST_POLL_MS: u64 = 250;
+
+static RESULTS: RwLock<Vec<SelfTestResult>> = RwLock::new(Vec::new());
+
+/// Runs the flash check, then waits (in the background) on the other subsystems.
+pub(crate) fn init(cfg: Config, spawner: &Spawner) -> Result<()> {
+    log::info!("Self-test: started");
+
+    record(
+        SelfTestCheck::Flash,
+        flash_round_trip(&mut FlashStorage::new()),
+    );
+
+    let cfg = cfg.load();
+    for (check, enabled) in [
+        (SelfTestCheck::Sensor, cfg.sensor_enabled),
+        (SelfTestCheck::Display, cfg.display_enabled),
+        (SelfTestCheck::Wifi, cfg.network_enabled),
+    ] {
+        if !enabled {
+            set(check, SelfTestOutcome::Skipped, None);
+        }
+    }
+
+    spawner
+        .spawn(selftest_task())
+        .map_err(map_embassy_spawn_err)
+}
+
+/// Records a check's result (replacing an earlier one), as reported by its init path.
+pub(crate) fn record(check: SelfTestCheck, res: Result<()>) {
+    match res {
+        Ok(_) => {
+            log::info!("Self-test: {:?} PASS", check);
+            set(check, SelfTestOutcome::Pass, None);
+        }
+        Err(e) => {
+            log::warn!("Self-test: {:?} FAIL ({:?})", check, e);
+            set(check, SelfTestOutcome::Fail, Some(format!("{:?}", e)));
+        }
+    }
+}
+
+pub(crate) fn results() -> Vec<SelfTestResult> {
+    RESULTS.read().clone()
+}
+
+/// All checks have a result (or timed out).
+pub(crate) fn is_complete() -> bool {
+    let results = RESULTS.read();
+
+    SelfTestCheck::ALL
+        .iter()
+        .all(|check| results.iter().any(|r| r.check == *check))
+}
+
+#[embassy_executor::task]
+async fn selftest_task() {
+    let deadline = Instant::now() + Duration::from_secs(SELFTEST_TIMEOUT_SECS);
+
+    while !is_complete() && Instant::now() < deadline {
+        if !has_result(SelfTestCheck::Sensor) && METRICS.read().is_some() {
+            record(SelfTestCheck::Sensor, Ok(()));
+        }
+        if !has_result(SelfTestCheck::Wifi) && IP_ADDRESS.read().is_some() {
+            record(SelfTestCheck::Wifi, Ok(()));
+        }
+
+        Timer::after(Duration::from_millis(SELFTEST_POLL_MS)).await;
+    }
+
+    for check in SelfTestCheck::ALL {
+        if !has_result(check) {
+            record(
+                check,
+                Err(general_fault(format!(
+                    "timed out after {} seconds",
+                    SELFTEST_TIMEOUT_SECS
+                ))),
+            );
+        }
+    }
+
+    let failed = RESULTS
+        .read()
+        .iter()
+        .filter(|r| r.outcome == SelfTestOutcome::Fail)
+        .count();
+    if failed == 0 {
+        log::info!("Self-test: completed, all checks passed");
+    } else {
+        log::warn!("Self-test: completed, {} check(s) failed", failed);
+    }
+}
+
+/// Writes a pattern to a scratch address and reads it back (then restores what was there).
+fn flash_round_trip<S>(storage: &mut S) -> Result<()>
+where
+    S: Storage,
+    S::Error: Debug,
+{
+    let mut original = [0u8; SELFTEST_FLASH_PATTERN.len()];
+    storage
+        .read(SELFTEST_FLASH_ADDR, &mut original)
+        .map_err(|e| general_fault(format!("failed to read flash: {:?}", e)))?;
+
+    storage
+        .write(SELFTEST_FLASH_ADDR, &SELFTEST_FLASH_PATTERN)
+        .map_err(|e| general_fault(format!("failed to write flash: {:?}", e)))?;
+
+    let mut read_back = [0u8; SELFTEST_FLASH_PATTERN.len()];
+    let res = storage
+        .read(SELFTEST_FLASH_ADDR, &mut read_back)
+        .map_err(|e| general_fault(format!("failed to read back flash: {:?}", e)));
+
+    storage
+        .write(SELFTEST_FLASH_ADDR, &original)
+        .map_err(|e| general_fault(format!("failed to restore flash: {:?}", e)))?;
+    res?;
+
+    if read_back != SELFTEST_FLASH_PATTERN {
+        return Err(general_fault(format!(
+            "flash read back mismatch: {:02x?}",
+            read_back
+        )));
+    }
+
+    Ok(())
+}
+
+fn set(check: SelfTestCheck, outcome: SelfTestOutcome, detail: Option<String>) {
+    let mut results = RESULTS.write();
+    results.retain(|r| r.check != check);
+    results.push(SelfTestResult {
+        check,
+        outcome,
+        detail,
+    });
+}
+
+fn has_result(check: SelfTestCheck) -> bool {
+    RESULTS.read().iter().any(|r| r.check == check)
+}
+
+// Models
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize)]
+pub(crate) enum SelfTestCheck {
+    Flash,
+    Sensor,
+    Display,
+    Wifi,
+}
+
+impl SelfTestCheck {
+    const ALL: [SelfTestCheck; 4] = [
+        SelfTestCheck::Flash,
+        SelfTestCheck::Sensor,
+        SelfTestCheck::Display,
+        SelfTestCheck::Wifi,
+    ];
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize)]
+pub(crate) enum SelfTestOutcome {
+    Pass,
+    Fail,
+    // The subsystem is disabled in the config.
+    Skipped,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct SelfTestResult {
+    check: SelfTestCheck,
+    outcome: SelfTestOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+}
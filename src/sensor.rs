@@ -1,61 +1,118 @@
+use alloc::boxed::Box;
 use alloc::format;
+use alloc::string::String;
 use core::cell::RefCell;
 
+#[cfg(feature = "mock-sensor")]
+use core::f32::consts::PI;
 use embassy_executor::Spawner;
+use embassy_futures::select::{select, Either};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::pubsub::{PubSubChannel, Publisher, Subscriber};
+#[cfg(feature = "mock-sensor")]
+use embassy_time::Instant;
 use embassy_time::{Duration, Timer};
+#[cfg(feature = "hdc1080")]
+use embedded_hal::i2c::I2c;
 use embedded_hal_bus::i2c::RefCellDevice;
 #[cfg(feature = "hdc1080")]
 use embedded_hdc1080_rs::Hdc1080;
 use esp_hal::clock::Clocks;
-use esp_hal::gpio::{InputPin, OutputPin};
+use esp_hal::gpio::{GpioPin, Unknown};
 use esp_hal::i2c::{Instance, I2C};
 use esp_hal::peripheral::Peripheral;
 use esp_hal::peripherals::I2C0;
 use esp_hal::Delay;
 use fugit::RateExtU32;
+use num_traits::float::Float;
 #[cfg(feature = "sht40")]
 use sensor_temp_humidity_sht40::{I2CAddr, Precision, SHT40Driver, TempUnit};
 use serde::Serialize;
 use spin::RwLock;
 
+#[cfg(feature = "sht40")]
+use crate::config::SensorPrecision;
 use crate::config::{Config, ConfigInstance, SensorDriver};
+use crate::diag;
+use crate::diag::DiagTask;
 use crate::error::{
     general_fault, map_embassy_pub_sub_err, map_embassy_spawn_err, sensor_fault, Result,
 };
+use crate::heartbeat;
+use crate::heartbeat::HeartbeatTask;
+use crate::snapshot;
+use crate::utils::{get_time_ms, random_u32};
+
+const SENSOR_SDA_GPIO_PIN: u8 = 14;
+const SENSOR_SCL_GPIO_PIN: u8 = 15;
+
+const HDC1080_I2C_ADDR: u8 = 0x40;
+const SHT40_I2C_ADDR: u8 = 0x44;
+#[cfg(feature = "hdc1080")]
+const HDC1080_REG_CONFIG: u8 = 0x02;
+#[cfg(feature = "hdc1080")]
+const HDC1080_CONFIG_HEAT: u16 = 1 << 13;
 
 static MAX_RH: f32 = 100_f32;
+// Changes at or above these (since backoff began) snap the poll interval back to the base.
+static BACKOFF_RH_DELTA: f32 = 0.5;
+static BACKOFF_TEMP_DELTA: f32 = 0.3;
 static MAX_ATTEMPTS: u8 = 10;
+// Error backoff jitter, up to 1/N of the delay.
+const ERR_BACKOFF_JITTER_DIV: u32 = 10;
 
 pub(crate) static METRICS: RwLock<Option<SensorMetrics>> = RwLock::new(None);
+// Uncalibrated RH of the last successful read.
+static RAW_RH: RwLock<Option<f32>> = RwLock::new(None);
+// Time of the last successful read.
+static METRICS_READ_AT: RwLock<Option<u32>> = RwLock::new(None);
+// Driver the emitter last constructed (or attempted to).
+pub(crate) static DRIVER_STATE: RwLock<Option<SensorDriverState>> = RwLock::new(None);
 
 pub type SensorSubscriber =
-    Subscriber<'static, CriticalSectionRawMutex, Option<SensorMetrics>, 1, 2, 1>;
+    Subscriber<'static, CriticalSectionRawMutex, Option<SensorMetrics>, 1, 6, 1>;
+
+pub(crate) static CHANNEL: PubSubChannel<CriticalSectionRawMutex, Option<SensorMetrics>, 1, 6, 1> =
+    PubSubChannel::new();
 
-pub(crate) static CHANNEL: PubSubChannel<CriticalSectionRawMutex, Option<SensorMetrics>, 1, 2, 1> =
+// Out of band reset requests (i.e. from the API).
+type ResetSubscriber = Subscriber<'static, CriticalSectionRawMutex, (), 1, 1, 1>;
+pub(crate) type ResetPublisher = Publisher<'static, CriticalSectionRawMutex, (), 1, 1, 1>;
+pub(crate) static RESET_CHANNEL: PubSubChannel<CriticalSectionRawMutex, (), 1, 1, 1> =
     PubSubChannel::new();
 
-pub(crate) fn init<SDA, SDA_, SCL, SCL_>(
+pub(crate) fn init_bus(
     cfg: Config,
-    sda: SDA,
-    scl: SCL,
+    sda: GpioPin<Unknown, SENSOR_SDA_GPIO_PIN>,
+    scl: GpioPin<Unknown, SENSOR_SCL_GPIO_PIN>,
     i2c0: I2C0,
+    clocks: &'static Clocks<'static>,
+) -> &'static SensorBus {
+    let khz = cfg.load().sensor_i2c_khz;
+    log::info!("Initializing sensor I2C bus at {} kHz", khz);
+
+    Box::leak(Box::new(SensorBus::new(
+        SensorBusPeripherals { i2c0, sda, scl },
+        khz,
+        clocks,
+    )))
+}
+
+pub(crate) fn init(
+    cfg: Config,
+    bus: &'static SensorBus,
     clocks: &Clocks,
     spawner: &Spawner,
-) -> Result<()>
-where
-    SDA: Peripheral<P = SDA_> + 'static,
-    SDA_: InputPin + OutputPin,
-    SCL: Peripheral<P = SCL_> + 'static,
-    SCL_: InputPin + OutputPin,
-{
+) -> Result<()> {
     spawner
         .spawn(emitter(
             cfg,
-            I2C::new(i2c0, sda, scl, 1.kHz(), &clocks),
+            bus,
             Delay::new(clocks),
             CHANNEL.publisher().map_err(map_embassy_pub_sub_err)?,
+            RESET_CHANNEL
+                .subscriber()
+                .map_err(map_embassy_pub_sub_err)?,
         ))
         .map_err(map_embassy_spawn_err)?;
 
@@ -65,55 +122,177 @@ where
 #[embassy_executor::task]
 async fn emitter(
     cfg: Config,
-    i2c: I2C<'static, I2C0>,
+    bus: &'static SensorBus,
     delay: Delay,
-    publisher: Publisher<'static, CriticalSectionRawMutex, Option<SensorMetrics>, 1, 2, 1>,
+    publisher: Publisher<'static, CriticalSectionRawMutex, Option<SensorMetrics>, 1, 6, 1>,
+    mut reset_sub: ResetSubscriber,
 ) {
-    let i2c_rc = RefCell::new(i2c);
+    let mut err_backoff = ErrBackoff::new();
 
     loop {
-        let i2c = RefCellDevice::new(&i2c_rc);
-
-        match Device::new(cfg.load().as_ref(), i2c, delay) {
-            Ok(mut dev) => loop {
-                match emitter_poll(&cfg, &mut dev, &publisher).await {
-                    Ok(reload) => {
-                        if reload {
-                            log::warn!("Reloading sensor device");
-                            break;
+        let driver = cfg.load().sensor_driver.clone();
+
+        match Device::new(cfg.load().as_ref(), &bus.i2c, delay) {
+            Ok(mut dev) => {
+                let _ = DRIVER_STATE
+                    .write()
+                    .insert(SensorDriverState::new(driver, None));
+
+                emitter_warmup(&cfg, &mut dev).await;
+
+                let mut heater_at_ms = get_time_ms();
+                let mut backoff = PollBackoff::new(cfg.load().as_ref());
+
+                loop {
+                    heartbeat::beat(HeartbeatTask::Sensor);
+
+                    emitter_heater_cycle(&cfg, &mut dev, &mut heater_at_ms).await;
+
+                    match emitter_poll(
+                        &cfg,
+                        &mut dev,
+                        &publisher,
+                        &mut reset_sub,
+                        &mut backoff,
+                        &mut err_backoff,
+                    )
+                    .await
+                    {
+                        Ok(reload) => {
+                            if reload {
+                                log::warn!("Reloading sensor device");
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            log::warn!("Sensor emitter poll failed: {:?}", e);
                         }
-                    }
-                    Err(e) => {
-                        log::warn!("Sensor emitter poll failed: {:?}", e);
                     }
                 }
-            },
+            }
             Err(e) => {
                 log::warn!("Failed to create sensor device: {:?}", e);
+                let _ = DRIVER_STATE
+                    .write()
+                    .insert(SensorDriverState::new(driver, Some(format!("{}", e))));
                 publisher.publish_immediate(None);
 
-                Timer::after(Duration::from_millis(cfg.load().sensor_delay_err_ms as u64)).await;
+                let delay_ms = err_backoff.next_delay_ms(cfg.load().as_ref());
+                Timer::after(Duration::from_millis(delay_ms as u64)).await;
             }
         }
+
+        // Repeated failures, the bus itself may be stuck (e.g. sensor re-plugged).
+        bus.reinit();
+    }
+}
+
+/// Time since the last successful read (`None` if there hasn't been one).
+pub(crate) fn metrics_age_ms() -> Option<u32> {
+    METRICS_READ_AT
+        .read()
+        .map(|read_at| get_time_ms().wrapping_sub(read_at))
+}
+
+/// RH of the last successful read before `sensor_calibration_rh_adj` was applied.
+pub(crate) fn raw_rh() -> Option<f32> {
+    *RAW_RH.read()
+}
+
+/// Discards the first (possibly inaccurate) reads after the device is created.
+async fn emitter_warmup<'d>(cfg: &Config, dev: &mut Device<'d, I2C0>) {
+    let cfg = cfg.load();
+
+    for read in 1..(cfg.sensor_warmup_reads + 1) {
+        match dev.read() {
+            Ok((temp, rh)) => log::debug!(
+                "Discarded sensor warm-up read (temp: {}, rh: {}) [{} of {}]",
+                temp,
+                rh,
+                read,
+                cfg.sensor_warmup_reads
+            ),
+            Err(e) => log::warn!(
+                "Failed sensor warm-up read: {:?} [{} of {}]",
+                e,
+                read,
+                cfg.sensor_warmup_reads
+            ),
+        }
+
+        Timer::after(Duration::from_millis(cfg.sensor_delay_ms as u64)).await;
     }
 }
 
+/// Periodically runs a measurement with the heater enabled (to de-fog the sensor).
+///
+/// The heated sample is discarded (and never published) as its RH would be skewed low.
+async fn emitter_heater_cycle<'d>(
+    cfg: &Config,
+    dev: &mut Device<'d, I2C0>,
+    heater_at_ms: &mut u32,
+) {
+    let cfg = cfg.load();
+
+    if cfg.sensor_heater_interval_secs == 0
+        || get_time_ms().wrapping_sub(*heater_at_ms)
+            < cfg.sensor_heater_interval_secs.saturating_mul(1000)
+    {
+        return;
+    }
+    *heater_at_ms = get_time_ms();
+
+    match dev.heated_read() {
+        Ok(Some((temp, rh))) => {
+            log::info!(
+                "Ran sensor heater cycle (discarded temp: {}, rh: {})",
+                temp,
+                rh
+            )
+        }
+        Ok(None) => {
+            log::warn!(
+                "Sensor heater not supported by driver: {:?}",
+                cfg.sensor_driver
+            )
+        }
+        Err(e) => log::warn!("Failed to run sensor heater cycle: {:?}", e),
+    }
+
+    // Give the sensor a moment to settle before the next (published) read.
+    Timer::after(Duration::from_millis(cfg.sensor_delay_ms as u64)).await;
+}
+
 async fn emitter_poll<'d>(
     cfg: &Config,
     dev: &mut Device<'d, I2C0>,
-    publisher: &Publisher<'static, CriticalSectionRawMutex, Option<SensorMetrics>, 1, 2, 1>,
+    publisher: &Publisher<'static, CriticalSectionRawMutex, Option<SensorMetrics>, 1, 6, 1>,
+    reset_sub: &mut ResetSubscriber,
+    backoff: &mut PollBackoff,
+    err_backoff: &mut ErrBackoff,
 ) -> Result<bool> {
     let cfg = cfg.load();
+    // The read (with its retries) up to publishing, not the wait for the next one.
+    let handling = diag::Handling::start(DiagTask::Sensor);
 
     let mut msg: Option<SensorMetrics> = None;
+    let mut raw_rh: Option<f32> = None;
     for attempt in 1..(MAX_ATTEMPTS + 1) {
-        match dev.read() {
+        match timed_read(cfg.as_ref(), dev) {
             Ok((temp, mut rh)) => {
-                if temp > 0_f32 && rh > 0_f32 {
+                // Sub-zero temperatures are valid, only the configured range filters them.
+                let temp_in_range = (cfg.sensor_temp_min..=cfg.sensor_temp_max).contains(&temp);
+                if temp_in_range && rh > 0_f32 {
+                    let _ = raw_rh.insert(rh);
+                    let mut quality = match attempt {
+                        1 => SensorQuality::Good,
+                        _ => SensorQuality::Retried,
+                    };
                     if let Some(adj) = cfg.sensor_calibration_rh_adj {
                         rh += adj;
                         if rh > MAX_RH {
                             rh = MAX_RH;
+                            quality = SensorQuality::Clamped;
                         }
 
                         log::debug!("Sensor - Temp: {}, RH: {}% (+{})", temp, rh, adj);
@@ -121,7 +300,7 @@ async fn emitter_poll<'d>(
                         log::debug!("Sensor - Temp: {}, RH: {}%", temp, rh);
                     }
 
-                    let _ = msg.insert(SensorMetrics { temp, rh });
+                    let _ = msg.insert(SensorMetrics { temp, rh, quality });
                     break;
                 } else {
                     log::error!(
@@ -164,45 +343,279 @@ async fn emitter_poll<'d>(
             *wr = msg.clone();
         }
     }
+    snapshot::update(|s| s.metrics = msg.clone());
+    *RAW_RH.write() = raw_rh;
+
+    if !failed {
+        let _ = METRICS_READ_AT.write().insert(get_time_ms());
+    }
+
+    let delay_ms = match msg.as_ref() {
+        Some(metrics) => {
+            err_backoff.reset();
+            backoff.next_delay_ms(cfg.as_ref(), metrics)
+        }
+        None => err_backoff.next_delay_ms(cfg.as_ref()),
+    };
 
     publisher.publish_immediate(msg);
+    drop(handling);
+
+    match select(
+        Timer::after(Duration::from_millis(delay_ms as u64)),
+        reset_sub.next_message(),
+    )
+    .await
+    {
+        Either::First(_) => {}
+        Either::Second(_) => {
+            // Lagged is still a reset request.
+            log::warn!("Sensor reset requested");
 
-    if failed {
-        Timer::after(Duration::from_millis(cfg.sensor_delay_err_ms as u64)).await;
+            if let Err(e) = dev.reset() {
+                log::error!("Failed to send reset command to sensor: {:?}", e);
+            }
+
+            // Re-create device.
+            return Ok(true);
+        }
+    }
 
+    if failed {
         // Re-create device.
         return Ok(true);
     }
 
-    Timer::after(Duration::from_millis(cfg.sensor_delay_ms as u64)).await;
-
     Ok(false)
 }
 
+/// Reads from the device, failing if the read overran `sensor_read_timeout_ms`.
+///
+/// The drivers are blocking so a hung transaction can't be abandoned part way (a select against a
+/// timer would never be polled), the late reading is discarded instead so it goes through the
+/// usual retry/reset path.
+fn timed_read<'d>(cfg: &ConfigInstance, dev: &mut Device<'d, I2C0>) -> Result<(f32, f32)> {
+    let started_at = get_time_ms();
+    let res = dev.read()?;
+
+    let elapsed_ms = get_time_ms().wrapping_sub(started_at);
+    if elapsed_ms > cfg.sensor_read_timeout_ms {
+        return Err(sensor_fault(format!(
+            "sensor read timed out (took {} ms, limit {} ms)",
+            elapsed_ms, cfg.sensor_read_timeout_ms
+        )));
+    }
+
+    Ok(res)
+}
+
+/// Adaptive poll interval, backs off toward `sensor_delay_max_ms` while readings are stable.
+struct PollBackoff {
+    delay_ms: u32,
+    // Reading at the time the backoff began (compared against to detect drift).
+    reference: Option<SensorMetrics>,
+}
+
+impl PollBackoff {
+    fn new(cfg: &ConfigInstance) -> Self {
+        Self {
+            delay_ms: cfg.sensor_delay_ms,
+            reference: None,
+        }
+    }
+
+    fn next_delay_ms(&mut self, cfg: &ConfigInstance, metrics: &SensorMetrics) -> u32 {
+        let stable = match self.reference.as_ref() {
+            Some(reference) => {
+                (metrics.rh - reference.rh).abs() < BACKOFF_RH_DELTA
+                    && (metrics.temp - reference.temp).abs() < BACKOFF_TEMP_DELTA
+            }
+            None => false,
+        };
+
+        if stable {
+            self.delay_ms = (self.delay_ms * 2)
+                .min(cfg.sensor_delay_max_ms)
+                .max(cfg.sensor_delay_ms);
+        } else {
+            if self.delay_ms != cfg.sensor_delay_ms {
+                log::debug!("Sensor readings changed, resetting poll backoff");
+            }
+
+            self.delay_ms = cfg.sensor_delay_ms;
+            let _ = self.reference.insert(metrics.clone());
+        }
+
+        self.delay_ms
+    }
+}
+
+/// Delay after a failed read (or device creation) before the device is recreated, doubling from
+/// `sensor_delay_err_ms` to `sensor_delay_err_max_ms` while failures continue, with jitter so
+/// devices don't fall into step. Reset by a successful read.
+struct ErrBackoff {
+    failures: u32,
+}
+
+impl ErrBackoff {
+    fn new() -> Self {
+        Self { failures: 0 }
+    }
+
+    fn reset(&mut self) {
+        if self.failures > 0 {
+            log::debug!("Sensor read succeeded, resetting error backoff");
+        }
+
+        self.failures = 0;
+    }
+
+    fn next_delay_ms(&mut self, cfg: &ConfigInstance) -> u32 {
+        let delay_ms = cfg
+            .sensor_delay_err_ms
+            .saturating_mul(1 << self.failures.min(16))
+            .min(cfg.sensor_delay_err_max_ms)
+            .max(cfg.sensor_delay_err_ms);
+        self.failures = self.failures.saturating_add(1);
+
+        delay_ms + random_u32() % (delay_ms / ERR_BACKOFF_JITTER_DIV + 1)
+    }
+}
+
+/// I2C bus shared by all sensor devices (via `RefCellDevice`).
+pub(crate) struct SensorBus {
+    i2c: RefCell<I2C<'static, I2C0>>,
+    peripherals: RefCell<SensorBusPeripherals>,
+    khz: u32,
+    clocks: &'static Clocks<'static>,
+}
+
+impl SensorBus {
+    fn new(
+        mut peripherals: SensorBusPeripherals,
+        khz: u32,
+        clocks: &'static Clocks<'static>,
+    ) -> Self {
+        Self {
+            i2c: RefCell::new(peripherals.create_i2c(khz, clocks)),
+            peripherals: RefCell::new(peripherals),
+            khz,
+            clocks,
+        }
+    }
+
+    #[cfg(feature = "scd40")]
+    pub(crate) fn device(&self) -> RefCellDevice<'_, I2C<'static, I2C0>> {
+        RefCellDevice::new(&self.i2c)
+    }
+
+    /// Re-creates the I2C instance (and reconfigures the peripheral) to clear a stuck bus.
+    pub(crate) fn reinit(&self) {
+        let i2c = self
+            .peripherals
+            .borrow_mut()
+            .create_i2c(self.khz, self.clocks);
+
+        // Devices only borrow the bus for the duration of a (blocking) transaction.
+        let _ = self.i2c.replace(i2c);
+
+        log::warn!("Re-initialized sensor I2C bus");
+    }
+}
+
+struct SensorBusPeripherals {
+    i2c0: I2C0,
+    sda: GpioPin<Unknown, SENSOR_SDA_GPIO_PIN>,
+    scl: GpioPin<Unknown, SENSOR_SCL_GPIO_PIN>,
+}
+
+impl SensorBusPeripherals {
+    fn create_i2c(&mut self, khz: u32, clocks: &Clocks) -> I2C<'static, I2C0> {
+        // Safety: the peripheral and pins are owned by the bus and only ever driven by the single
+        // `I2C` instance it holds (re-init replaces the previous instance).
+        unsafe {
+            I2C::new(
+                self.i2c0.clone_unchecked(),
+                self.sda.clone_unchecked(),
+                self.scl.clone_unchecked(),
+                khz.kHz(),
+                clocks,
+            )
+        }
+    }
+}
+
 #[derive(Clone, Debug, Serialize)]
 pub(crate) struct SensorMetrics {
     pub(crate) temp: f32,
     pub(crate) rh: f32,
+    pub(crate) quality: SensorQuality,
+}
+
+/// How a reading was obtained.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub(crate) enum SensorQuality {
+    // Read on the first attempt.
+    Good,
+    // Read after one or more failed attempts (possibly after a sensor reset).
+    Retried,
+    // The calibrated rh exceeded `MAX_RH` and was clamped (regardless of the attempt).
+    Clamped,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub(crate) struct SensorDriverState {
+    driver: SensorDriver,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    i2c_addr: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fault: Option<String>,
+}
+
+impl SensorDriverState {
+    fn new(driver: SensorDriver, fault: Option<String>) -> Self {
+        let i2c_addr = match driver {
+            SensorDriver::HDC1080 => Some(HDC1080_I2C_ADDR),
+            SensorDriver::SHT40 => Some(SHT40_I2C_ADDR),
+            #[cfg(feature = "mock-sensor")]
+            SensorDriver::Mock => None,
+        };
+
+        Self {
+            driver,
+            i2c_addr,
+            fault,
+        }
+    }
 }
 
 enum Device<'d, T> {
     #[cfg(feature = "hdc1080")]
-    HDC1080(Hdc1080<RefCellDevice<'d, I2C<'d, T>>, Delay>),
+    // The second device is used for raw register access (heater).
+    HDC1080(
+        Hdc1080<RefCellDevice<'d, I2C<'d, T>>, Delay>,
+        RefCellDevice<'d, I2C<'d, T>>,
+    ),
     #[cfg(feature = "sht40")]
-    SHT40(SHT40Driver<RefCellDevice<'d, I2C<'d, T>>, Delay>),
+    SHT40(
+        SHT40Driver<RefCellDevice<'d, I2C<'d, T>>, Delay>,
+        SensorPrecision,
+    ),
+    #[cfg(feature = "mock-sensor")]
+    Mock(MockSensor),
 }
 
 impl<'d, T> Device<'d, T>
 where
     T: Instance,
 {
-    fn new(cfg: &ConfigInstance, i2c: RefCellDevice<'d, I2C<'d, T>>, delay: Delay) -> Result<Self> {
+    fn new(cfg: &ConfigInstance, i2c: &'d RefCell<I2C<'d, T>>, delay: Delay) -> Result<Self> {
         log::info!("Creating sensor device driver for: {:?}", cfg.sensor_driver);
 
         match cfg.sensor_driver {
             #[cfg(feature = "hdc1080")]
             SensorDriver::HDC1080 => {
-                let mut dev = Hdc1080::new(i2c, delay).map_err(|e| {
+                let mut dev = Hdc1080::new(RefCellDevice::new(i2c), delay).map_err(|e| {
                     general_fault(format!("failed to create hdc1080 sensor device: {:?}", e))
                 })?;
 
@@ -210,13 +623,20 @@ where
                     general_fault(format!("failed to init hdc1080 sensor device: {:?}", e))
                 })?;
 
-                Ok(Device::HDC1080(dev))
+                Ok(Device::HDC1080(dev, RefCellDevice::new(i2c)))
             }
             #[cfg(feature = "sht40")]
-            SensorDriver::SHT40 => Ok(Device::SHT40(SHT40Driver::new(
-                i2c,
-                I2CAddr::SHT4x_A,
-                delay,
+            SensorDriver::SHT40 => Ok(Device::SHT40(
+                SHT40Driver::new(RefCellDevice::new(i2c), I2CAddr::SHT4x_A, delay),
+                cfg.sensor_precision,
+            )),
+            #[cfg(feature = "mock-sensor")]
+            SensorDriver::Mock => Ok(Device::Mock(MockSensor::new(cfg))),
+            // Config validation rejects these, but a config persisted by another build may not.
+            #[allow(unreachable_patterns)]
+            _ => Err(general_fault(format!(
+                "sensor driver {:?} is not supported by this firmware",
+                cfg.sensor_driver
             ))),
         }
     }
@@ -224,16 +644,16 @@ where
     fn read(&mut self) -> Result<(f32, f32)> {
         match self {
             #[cfg(feature = "hdc1080")]
-            Device::HDC1080(dev) => dev.read().map_err(|e| {
+            Device::HDC1080(dev, _) => dev.read().map_err(|e| {
                 general_fault(format!(
                     "failed to read from hdc1080 sensor device: {:?}",
                     e
                 ))
             }),
             #[cfg(feature = "sht40")]
-            Device::SHT40(dev) => {
+            Device::SHT40(dev, precision) => {
                 let measurement = dev
-                    .get_temp_and_rh(Precision::High, TempUnit::MilliDegreesCelsius)
+                    .get_temp_and_rh(sht40_precision(*precision), TempUnit::MilliDegreesCelsius)
                     .map_err(|e| {
                         sensor_fault(format!("Failed to take measurement from sensor: {:?}", e))
                     })?;
@@ -243,20 +663,112 @@ where
                     measurement.rel_hum_pcm as f32 / 1000_f32,
                 ));
             }
+            #[cfg(feature = "mock-sensor")]
+            Device::Mock(dev) => Ok(dev.read()),
+        }
+    }
+
+    /// Takes a measurement with the heater enabled (`None` if the driver has no heater support).
+    fn heated_read(&mut self) -> Result<Option<(f32, f32)>> {
+        match self {
+            #[cfg(feature = "hdc1080")]
+            Device::HDC1080(dev, raw) => {
+                hdc1080_set_heater(raw, true)?;
+                let res = dev.read().map_err(|e| {
+                    general_fault(format!(
+                        "failed to read from hdc1080 sensor device (heated): {:?}",
+                        e
+                    ))
+                });
+
+                // Always turn the heater back off (even if the read failed).
+                hdc1080_set_heater(raw, false)?;
+
+                res.map(Some)
+            }
+            #[cfg(feature = "sht40")]
+            Device::SHT40(..) => Ok(None),
+            #[cfg(feature = "mock-sensor")]
+            Device::Mock(_) => Ok(None),
         }
     }
 
-    #[allow(dead_code)]
     fn reset(&mut self) -> Result<()> {
         match self {
             #[cfg(feature = "hdc1080")]
-            Device::HDC1080(dev) => dev.reset().map_err(|e| {
+            Device::HDC1080(dev, _) => dev.reset().map_err(|e| {
                 general_fault(format!("failed to reset hdc1080 sensor device: {:?}", e))
             }),
             #[cfg(feature = "sht40")]
-            Device::SHT40(dev) => dev.soft_reset_device().map_err(|e| {
+            Device::SHT40(dev, _) => dev.soft_reset_device().map_err(|e| {
                 general_fault(format!("failed to reset sht40 sensor device: {:?}", e))
             }),
+            #[cfg(feature = "mock-sensor")]
+            Device::Mock(_) => Ok(()),
         }
     }
 }
+
+/// Simulated sensor, readings drift along a sine wave (rh and temp out of phase).
+#[cfg(feature = "mock-sensor")]
+struct MockSensor {
+    rh_mid: f32,
+    rh_amplitude: f32,
+    temp_mid: f32,
+    temp_amplitude: f32,
+    period_secs: f32,
+}
+
+#[cfg(feature = "mock-sensor")]
+impl MockSensor {
+    fn new(cfg: &ConfigInstance) -> Self {
+        Self {
+            rh_mid: cfg.sensor_mock_rh_mid,
+            rh_amplitude: cfg.sensor_mock_rh_amplitude,
+            temp_mid: cfg.sensor_mock_temp_mid,
+            temp_amplitude: cfg.sensor_mock_temp_amplitude,
+            period_secs: cfg.sensor_mock_period_secs.max(1) as f32,
+        }
+    }
+
+    /// Based on the uptime so the readings are the same for each boot.
+    fn read(&self) -> (f32, f32) {
+        let secs = Instant::now().as_millis() as f32 / 1000_f32;
+        let phase = 2_f32 * PI * (secs % self.period_secs) / self.period_secs;
+
+        (
+            self.temp_mid + self.temp_amplitude * phase.cos(),
+            (self.rh_mid + self.rh_amplitude * phase.sin()).clamp(0_f32, MAX_RH),
+        )
+    }
+}
+
+// Utils
+
+#[cfg(feature = "sht40")]
+fn sht40_precision(precision: SensorPrecision) -> Precision {
+    match precision {
+        SensorPrecision::Low => Precision::Low,
+        SensorPrecision::Medium => Precision::Medium,
+        SensorPrecision::High => Precision::High,
+    }
+}
+
+#[cfg(feature = "hdc1080")]
+fn hdc1080_set_heater<I: I2c>(i2c: &mut I, on: bool) -> Result<()> {
+    // Read-modify-write so the driver's mode/resolution settings are kept.
+    let mut buf = [0u8; 2];
+    i2c.write_read(HDC1080_I2C_ADDR, &[HDC1080_REG_CONFIG], &mut buf)
+        .map_err(|e| sensor_fault(format!("failed to read hdc1080 config: {:?}", e)))?;
+
+    let mut config = u16::from_be_bytes(buf);
+    if on {
+        config |= HDC1080_CONFIG_HEAT;
+    } else {
+        config &= !HDC1080_CONFIG_HEAT;
+    }
+
+    let [msb, lsb] = config.to_be_bytes();
+    i2c.write(HDC1080_I2C_ADDR, &[HDC1080_REG_CONFIG, msb, lsb])
+        .map_err(|e| sensor_fault(format!("failed to write hdc1080 config: {:?}", e)))
+}
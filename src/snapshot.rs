@@ -0,0 +1,53 @@
+//! An immutable snapshot of the control state for readers (i.e. `/status`).
+//!
+//! The control tasks swap in a new snapshot whenever they change what it holds, so a reader
+//! clones a single `Arc` (one brief lock) rather than taking a lock per value.
+
+use alloc::string::String;
+use alloc::sync::Arc;
+
+use spin::RwLock;
+
+use crate::mister::{AutoScheduleState, Mode as MisterMode, Status as MisterStatus};
+use crate::sensor::SensorMetrics;
+
+// Unset until the first change (read as the defaults).
+static SNAPSHOT: RwLock<Option<Arc<StateSnapshot>>> = RwLock::new(None);
+
+pub(crate) fn load() -> Arc<StateSnapshot> {
+    SNAPSHOT.read().clone().unwrap_or_default()
+}
+
+/// Swaps in a copy of the current snapshot with `cb` applied.
+pub(crate) fn update(cb: impl FnOnce(&mut StateSnapshot)) {
+    let mut wr = SNAPSHOT.write();
+
+    let mut next = wr.as_deref().cloned().unwrap_or_default();
+    cb(&mut next);
+
+    let _ = wr.insert(Arc::new(next));
+}
+
+// Models
+
+#[derive(Clone)]
+pub(crate) struct StateSnapshot {
+    pub(crate) mode: Option<MisterMode>,
+    pub(crate) status: Option<MisterStatus>,
+    pub(crate) fault_reason: Option<String>,
+    pub(crate) auto_schedule: AutoScheduleState,
+    pub(crate) metrics: Option<SensorMetrics>,
+}
+
+impl Default for StateSnapshot {
+    fn default() -> Self {
+        // As the statics they mirror start out.
+        Self {
+            mode: None,
+            status: Some(MisterStatus::Off),
+            fault_reason: None,
+            auto_schedule: AutoScheduleState::default(),
+            metrics: None,
+        }
+    }
+}
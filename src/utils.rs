@@ -1,5 +1,16 @@
 use esp_wifi::wifi::log_timestamp;
 
+// ESP32 RNG_DATA_REG (the RNG peripheral itself is owned by the WIFI stack).
+const RNG_DATA_REG: u32 = 0x3ff7_5144;
+
+/// Millisecond uptime used for all timing (flapping guard, schedules etc), the hardware
+/// independent logic in `fungi-core` takes it as a `now` argument so its tests can step time.
 pub fn get_time_ms() -> u32 {
     unsafe { log_timestamp() }
 }
+
+/// Random number from the hardware RNG (only truly random while the radio is on, i.e. WIFI), for
+/// jitter rather than anything cryptographic.
+pub fn random_u32() -> u32 {
+    unsafe { core::ptr::read_volatile(RNG_DATA_REG as *const u32) }
+}